@@ -0,0 +1,206 @@
+//! Change data capture. A `ChangeCapture` follows the materialized TBox and
+//! ABox of a `Reasoner` and hands out their changes a closed timestamp at a
+//! time, so that downstream systems receive complete batches they can apply
+//! in order.
+
+use crate::dictionary::Dictionary;
+use crate::error::{Error, Result};
+use crate::graphs::{self, DEFAULT_GRAPH};
+use crate::materializations::EncodedTriple;
+use crate::reasoner::Reasoner;
+use differential_dataflow::operators::reduce::Threshold;
+use std::cell::RefCell;
+use std::fmt;
+use std::io::{self, Write};
+use std::rc::Rc;
+use std::str::FromStr;
+use timely::communication::Allocate;
+use timely::dataflow::operators::probe::Handle;
+use timely::dataflow::operators::{Exchange, Inspect, Probe};
+use timely::dataflow::{Scope, Stream};
+use timely::worker::Worker;
+
+/// A triple derived (`diff` 1) or retracted (`diff` -1) at `time`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Change {
+    pub time: usize,
+    /// Whether the triple is in the materialized TBox rather than the ABox.
+    pub tbox: bool,
+    pub triple: EncodedTriple,
+    pub diff: isize,
+}
+
+/// How changes are written out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeFormat {
+    /// One JSON object per change.
+    JsonLines,
+    /// Triples preceded by `+` or `-`, after a `# time <t> <box>` comment for
+    /// every batch: the delta format `--tbox-delta` and `history --batch`
+    /// read. Triples of named graphs are followed by their graph.
+    NTriples,
+}
+
+impl ChangeFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChangeFormat::JsonLines => "jsonl",
+            ChangeFormat::NTriples => "nt",
+        }
+    }
+
+    /// Writes `changes`, sorted by timestamp, then flushes `writer` so that
+    /// consumers see each batch whole. Terms are decoded with `dictionary`
+    /// if the input was encoded, and written as ids otherwise.
+    pub fn write(
+        &self,
+        writer: &mut impl Write,
+        changes: &[Change],
+        dictionary: Option<&Dictionary>,
+    ) -> io::Result<()> {
+        let mut batch = None;
+        for change in changes {
+            let (s, p, o, graph) = if change.tbox {
+                let (s, p, o) = change.triple;
+                (s, p, o, DEFAULT_GRAPH)
+            } else {
+                graphs::unscope(change.triple)
+            };
+            let graph = Some(graph).filter(|graph| *graph != DEFAULT_GRAPH);
+            let name = if change.tbox { "tbox" } else { "abox" };
+            match self {
+                ChangeFormat::JsonLines => {
+                    let term = |id: usize| match dictionary {
+                        Some(dictionary) => {
+                            json_string(dictionary.decode(id).unwrap_or("_:unknown"))
+                        }
+                        None => id.to_string(),
+                    };
+                    write!(
+                        writer,
+                        "{{\"time\":{},\"box\":\"{}\",\"s\":{},\"p\":{},\"o\":{}",
+                        change.time,
+                        name,
+                        term(s),
+                        term(p),
+                        term(o)
+                    )?;
+                    if let Some(graph) = graph {
+                        write!(writer, ",\"graph\":{}", term(graph))?;
+                    }
+                    writeln!(writer, ",\"diff\":{}}}", change.diff)?;
+                }
+                ChangeFormat::NTriples => {
+                    if batch != Some((change.time, change.tbox)) {
+                        batch = Some((change.time, change.tbox));
+                        writeln!(writer, "# time {} {}", change.time, name)?;
+                    }
+                    let term = |id: usize| match dictionary {
+                        Some(dictionary) => {
+                            dictionary.decode(id).unwrap_or("_:unknown").to_string()
+                        }
+                        None => id.to_string(),
+                    };
+                    let sign = if change.diff > 0 { '+' } else { '-' };
+                    write!(writer, "{} {} {} {}", sign, term(s), term(p), term(o))?;
+                    if let Some(graph) = graph {
+                        write!(writer, " {}", term(graph))?;
+                    }
+                    if dictionary.is_some() {
+                        write!(writer, " .")?;
+                    }
+                    writeln!(writer)?;
+                }
+            }
+        }
+        writer.flush()
+    }
+}
+
+impl FromStr for ChangeFormat {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "jsonl" => Ok(ChangeFormat::JsonLines),
+            "nt" => Ok(ChangeFormat::NTriples),
+            _ => Err(Error::Config(format!("unknown change format {:?}", name))),
+        }
+    }
+}
+
+impl fmt::Display for ChangeFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn json_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The changes to the sets of triples in `reasoner`'s materialized TBox
+/// (`true`) and ABox, imported into `scope` and gathered on worker 0. The
+/// traces count the derivations of every triple, so they are thresholded
+/// first: a triple is only added when its first derivation appears, and only
+/// removed when its last one goes away.
+pub(crate) fn gather<G: Scope<Timestamp = usize>>(
+    scope: &G,
+    reasoner: &mut Reasoner,
+) -> Stream<G, ((bool, EncodedTriple), usize, isize)> {
+    let tbox = reasoner
+        .tbox_trace
+        .import_named(scope, "Imported TBox materialization")
+        .as_collection(|triple, _| (true, *triple));
+    let abox = reasoner
+        .abox_trace
+        .import_named(scope, "Imported ABox materialization")
+        .as_collection(|triple, _| (false, *triple));
+    tbox.concat(&abox).distinct().inner.exchange(|_change| 0)
+}
+
+/// Captures the changes to a reasoner's materializations.
+pub struct ChangeCapture {
+    changes: Rc<RefCell<Vec<Change>>>,
+    probe: Handle<usize>,
+}
+
+impl ChangeCapture {
+    /// Follows the materializations of `reasoner` in a dataflow of its own.
+    /// Only changes from the traces' compaction frontier on are captured, so
+    /// this is best installed right after the reasoner.
+    pub fn install<A: Allocate>(worker: &mut Worker<A>, reasoner: &mut Reasoner) -> Self {
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let mut probe = Handle::new();
+
+        let sink = Rc::clone(&changes);
+        worker.dataflow::<usize, _, _>(|scope| {
+            gather(scope, reasoner)
+                .inspect(move |((tbox, triple), time, diff)| {
+                    sink.borrow_mut().push(Change {
+                        time: *time,
+                        tbox: *tbox,
+                        triple: *triple,
+                        diff: *diff,
+                    })
+                })
+                .probe_with(&mut probe);
+        });
+
+        ChangeCapture { changes, probe }
+    }
+
+    /// Steps `worker` until every change at a timestamp before `time` is
+    /// captured, and returns those changes sorted. Every worker has to call
+    /// this, but only worker 0 gets changes.
+    pub fn closed<A: Allocate>(&mut self, worker: &mut Worker<A>, time: usize) -> Vec<Change> {
+        let probe = &self.probe;
+        worker.step_while(|| probe.less_than(&time));
+        let mut changes = self.changes.borrow_mut();
+        let (mut closed, open): (Vec<Change>, Vec<Change>) =
+            changes.drain(..).partition(|change| change.time < time);
+        *changes = open;
+        closed.sort_unstable();
+        closed
+    }
+}
@@ -0,0 +1,427 @@
+//! Typed literals: parsing, canonical lexical forms, and the data values the
+//! OWL 2 RL `dt-*` rules of `owl2rl` compare.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt::UpperExp;
+
+pub const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Kind {
+    /// An integer type, with its inclusive bounds.
+    Integer(Option<i128>, Option<i128>),
+    Decimal,
+    Double,
+    Float,
+    Boolean,
+    String,
+}
+
+/// The datatypes of the OWL 2 RL datatype map that literals are interpreted for.
+const DATATYPES: [(&str, Kind); 18] = [
+    ("string", Kind::String),
+    ("boolean", Kind::Boolean),
+    ("decimal", Kind::Decimal),
+    ("integer", Kind::Integer(None, None)),
+    ("nonNegativeInteger", Kind::Integer(Some(0), None)),
+    ("positiveInteger", Kind::Integer(Some(1), None)),
+    ("nonPositiveInteger", Kind::Integer(None, Some(0))),
+    ("negativeInteger", Kind::Integer(None, Some(-1))),
+    (
+        "long",
+        Kind::Integer(Some(i64::MIN as i128), Some(i64::MAX as i128)),
+    ),
+    (
+        "int",
+        Kind::Integer(Some(i32::MIN as i128), Some(i32::MAX as i128)),
+    ),
+    (
+        "short",
+        Kind::Integer(Some(i16::MIN as i128), Some(i16::MAX as i128)),
+    ),
+    (
+        "byte",
+        Kind::Integer(Some(i8::MIN as i128), Some(i8::MAX as i128)),
+    ),
+    (
+        "unsignedLong",
+        Kind::Integer(Some(0), Some(u64::MAX as i128)),
+    ),
+    (
+        "unsignedInt",
+        Kind::Integer(Some(0), Some(u32::MAX as i128)),
+    ),
+    (
+        "unsignedShort",
+        Kind::Integer(Some(0), Some(u16::MAX as i128)),
+    ),
+    (
+        "unsignedByte",
+        Kind::Integer(Some(0), Some(u8::MAX as i128)),
+    ),
+    ("double", Kind::Double),
+    ("float", Kind::Float),
+];
+
+/// A decimal number `mantissa * 10^-scale`, with no trailing zeros in the
+/// mantissa so that equal numbers compare equal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    fn new(mut mantissa: i128, mut scale: u32) -> Self {
+        while scale > 0 && mantissa % 10 == 0 {
+            mantissa /= 10;
+            scale -= 1;
+        }
+        if mantissa == 0 {
+            scale = 0;
+        }
+        Decimal { mantissa, scale }
+    }
+
+    fn integer(&self) -> Option<i128> {
+        if self.scale == 0 {
+            Some(self.mantissa)
+        } else {
+            None
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    fn canonical(&self) -> String {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let scale = self.scale as usize;
+        if scale == 0 {
+            format!("{}{}.0", sign, digits)
+        } else if digits.len() > scale {
+            let (int, frac) = digits.split_at(digits.len() - scale);
+            format!("{}{}.{}", sign, int, frac)
+        } else {
+            format!("{}0.{}{}", sign, "0".repeat(scale - digits.len()), digits)
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        let align = |d: &Decimal| {
+            10i128
+                .checked_pow(scale - d.scale)
+                .and_then(|factor| d.mantissa.checked_mul(factor))
+        };
+        match (align(self), align(other)) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            _ => self
+                .to_f64()
+                .partial_cmp(&other.to_f64())
+                .unwrap_or(Ordering::Equal),
+        }
+    }
+}
+
+/// The data value of a literal.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// Integers and decimals share the value space of `owl:real`.
+    Number(Decimal),
+    Double(f64),
+    Float(f32),
+    Boolean(bool),
+    String(String),
+    LangString(String, String),
+}
+
+/// Orders two numbers of the same value space; other values are unordered.
+pub fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => Some(a.cmp(b)),
+        (Value::Double(a), Value::Double(b)) => a.partial_cmp(b),
+        (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+        _ => None,
+    }
+}
+
+/// A literal split into its lexical form and its datatype IRI (without the
+/// angle brackets) or language tag.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Literal<'a> {
+    pub lexical: &'a str,
+    pub datatype: Option<&'a str>,
+    pub language: Option<&'a str>,
+}
+
+impl<'a> Literal<'a> {
+    /// Parses an N-Triples literal term; IRIs and blank nodes yield `None`.
+    pub fn parse(term: &'a str) -> Option<Self> {
+        let body = term.strip_prefix('"')?;
+        let close = body.rfind('"')?;
+        let (lexical, suffix) = (&body[..close], &body[close + 1..]);
+        if suffix.is_empty() {
+            Some(Literal {
+                lexical,
+                datatype: None,
+                language: None,
+            })
+        } else if let Some(language) = suffix.strip_prefix('@') {
+            Some(Literal {
+                lexical,
+                datatype: None,
+                language: Some(language),
+            })
+        } else {
+            let datatype = suffix.strip_prefix("^^<")?.strip_suffix('>')?;
+            Some(Literal {
+                lexical,
+                datatype: Some(datatype),
+                language: None,
+            })
+        }
+    }
+
+    fn kind(&self) -> Option<Kind> {
+        match (self.datatype, self.language) {
+            (None, None) => Some(Kind::String),
+            (Some(datatype), _) => kind(datatype),
+            (None, Some(_)) => None,
+        }
+    }
+
+    /// The data value, or `None` if the datatype is not supported or the
+    /// lexical form is not valid for it.
+    pub fn value(&self) -> Option<Value> {
+        if let (None, Some(language)) = (self.datatype, self.language) {
+            return Some(Value::LangString(
+                self.lexical.to_string(),
+                language.to_ascii_lowercase(),
+            ));
+        }
+        let value = match self.kind()? {
+            Kind::String => Value::String(self.lexical.to_string()),
+            Kind::Boolean => match self.lexical.trim() {
+                "true" | "1" => Value::Boolean(true),
+                "false" | "0" => Value::Boolean(false),
+                _ => return None,
+            },
+            Kind::Integer(..) => Value::Number(parse_decimal(self.lexical, false)?),
+            Kind::Decimal => Value::Number(parse_decimal(self.lexical, true)?),
+            Kind::Double => Value::Double(parse_float(self.lexical)?),
+            Kind::Float => Value::Float(parse_float(self.lexical)? as f32),
+        };
+        if in_value_space(&value, self.kind()?) {
+            Some(value)
+        } else {
+            None
+        }
+    }
+}
+
+fn kind(datatype: &str) -> Option<Kind> {
+    let local = datatype.strip_prefix(XSD)?;
+    DATATYPES
+        .iter()
+        .find(|(name, _)| *name == local)
+        .map(|(_, kind)| *kind)
+}
+
+fn in_value_space(value: &Value, kind: Kind) -> bool {
+    match (value, kind) {
+        (Value::Number(number), Kind::Integer(min, max)) => match number.integer() {
+            Some(integer) => {
+                !matches!(min, Some(min) if integer < min)
+                    && !matches!(max, Some(max) if integer > max)
+            }
+            None => false,
+        },
+        (Value::Number(_), Kind::Decimal) => true,
+        (Value::Double(_), Kind::Double) => true,
+        (Value::Float(_), Kind::Float) => true,
+        (Value::Boolean(_), Kind::Boolean) => true,
+        (Value::String(_), Kind::String) => true,
+        _ => false,
+    }
+}
+
+fn parse_decimal(lexical: &str, fraction: bool) -> Option<Decimal> {
+    let lexical = lexical.trim();
+    let (negative, digits) = match lexical.as_bytes().first()? {
+        b'-' => (true, &lexical[1..]),
+        b'+' => (false, &lexical[1..]),
+        _ => (false, lexical),
+    };
+    let (int, frac) = match digits.split_once('.') {
+        Some(_) if !fraction => return None,
+        Some((int, frac)) => (int, frac),
+        None => (digits, ""),
+    };
+    if int.is_empty() && frac.is_empty()
+        || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    let mut mantissa: i128 = 0;
+    for digit in int.bytes().chain(frac.bytes()) {
+        mantissa = mantissa
+            .checked_mul(10)?
+            .checked_add((digit - b'0') as i128)?;
+    }
+    if negative {
+        mantissa = -mantissa;
+    }
+    Some(Decimal::new(mantissa, frac.len() as u32))
+}
+
+fn parse_float(lexical: &str) -> Option<f64> {
+    match lexical.trim() {
+        "INF" | "+INF" => Some(f64::INFINITY),
+        "-INF" => Some(f64::NEG_INFINITY),
+        "NaN" => Some(f64::NAN),
+        // Rust also accepts "inf" and "nan", which XSD does not.
+        other
+            if other
+                .bytes()
+                .any(|b| b.is_ascii_alphabetic() && b != b'e' && b != b'E') =>
+        {
+            None
+        }
+        other => other.parse().ok(),
+    }
+}
+
+fn canonical_float<F: UpperExp + Into<f64> + Copy>(value: F) -> String {
+    let wide: f64 = value.into();
+    if wide.is_nan() {
+        "NaN".to_string()
+    } else if wide.is_infinite() {
+        if wide > 0.0 { "INF" } else { "-INF" }.to_string()
+    } else {
+        let formatted = format!("{:E}", value);
+        match formatted.split_once('E') {
+            Some((mantissa, exponent)) if !mantissa.contains('.') => {
+                format!("{}.0E{}", mantissa, exponent)
+            }
+            _ => formatted,
+        }
+    }
+}
+
+/// The canonical form of `term`: literals of a supported datatype get the
+/// canonical lexical form of their value, and `xsd:string` literals become
+/// simple literals. Other terms, and literals whose lexical form is not
+/// valid, are returned unchanged.
+pub fn canonical(term: &str) -> Cow<'_, str> {
+    let literal = match Literal::parse(term) {
+        Some(literal) => literal,
+        None => return Cow::Borrowed(term),
+    };
+    let datatype = match literal.datatype {
+        Some(datatype) => datatype,
+        None => return Cow::Borrowed(term),
+    };
+    let lexical = match literal.value() {
+        Some(Value::String(_)) => return Cow::Owned(format!("\"{}\"", literal.lexical)),
+        Some(Value::Boolean(value)) => value.to_string(),
+        Some(Value::Number(number)) => match number.integer() {
+            Some(integer) if kind(datatype) != Some(Kind::Decimal) => integer.to_string(),
+            _ => number.canonical(),
+        },
+        Some(Value::Double(value)) => canonical_float(value),
+        Some(Value::Float(value)) => canonical_float(value),
+        Some(Value::LangString(..)) | None => return Cow::Borrowed(term),
+    };
+    if lexical == literal.lexical {
+        Cow::Borrowed(term)
+    } else {
+        Cow::Owned(format!("\"{}\"^^<{}>", lexical, datatype))
+    }
+}
+
+/// The value of a cardinality restriction's literal, whatever its integer
+/// datatype or lexical form.
+pub fn cardinality(term: &str) -> Option<u64> {
+    let literal = Literal::parse(term)?;
+    match (literal.kind()?, literal.value()?) {
+        (Kind::Integer(..), Value::Number(number)) | (Kind::Decimal, Value::Number(number)) => {
+            number.integer().and_then(|n| u64::try_from(n).ok())
+        }
+        _ => None,
+    }
+}
+
+/// The IRIs of the datatypes of the datatype map, in N-Triples form. Rules
+/// refer to a datatype by its position in this list.
+pub fn datatypes() -> Vec<String> {
+    DATATYPES
+        .iter()
+        .map(|(name, _)| format!("<{}{}>", XSD, name))
+        .collect()
+}
+
+/// A data value as data a dataflow can exchange: its value space and the
+/// canonical lexical form of the value in it, so that literals with equal
+/// values have equal data, such as `"1"^^xsd:integer` and `"1.0"^^xsd:decimal`.
+pub type Datum = (u8, String);
+
+const NUMBER: u8 = 0;
+const DOUBLE: u8 = 1;
+const FLOAT: u8 = 2;
+const BOOLEAN: u8 = 3;
+const STRING: u8 = 4;
+const LANG_STRING: u8 = 5;
+
+impl Value {
+    pub fn datum(&self) -> Datum {
+        match self {
+            Value::Number(number) => (NUMBER, number.canonical()),
+            Value::Double(value) => (DOUBLE, canonical_float(*value)),
+            Value::Float(value) => (FLOAT, canonical_float(*value)),
+            Value::Boolean(value) => (BOOLEAN, value.to_string()),
+            Value::String(lexical) => (STRING, lexical.clone()),
+            Value::LangString(lexical, language) => {
+                (LANG_STRING, format!("{}@{}", lexical, language))
+            }
+        }
+    }
+}
+
+/// The datum of `term`, if it is a literal with a data value.
+pub fn datum(term: &str) -> Option<Datum> {
+    Literal::parse(term)?.value().map(|value| value.datum())
+}
+
+/// Whether the value space of the `datatype`th datatype of `datatypes`
+/// contains `datum`.
+pub fn contains(datatype: usize, datum: &Datum) -> bool {
+    let kind = match DATATYPES.get(datatype) {
+        Some((_, kind)) => *kind,
+        None => return false,
+    };
+    match (datum.0, kind) {
+        (NUMBER, Kind::Integer(..)) => matches!(
+            parse_decimal(&datum.1, true),
+            Some(number) if in_value_space(&Value::Number(number), kind)
+        ),
+        (NUMBER, Kind::Decimal)
+        | (DOUBLE, Kind::Double)
+        | (FLOAT, Kind::Float)
+        | (BOOLEAN, Kind::Boolean)
+        | (STRING, Kind::String) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,95 @@
+use crate::datatypes;
+use crate::error::{Error, Result};
+use crate::load_encode_triples::loadkvenc;
+use crate::materializations::EncodedTriple;
+use crate::vocabulary::VOCABULARY;
+use lasso::{Key, Rodeo, Spur};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Maps N-Triples terms to the dense ids the dataflows work on. The terms of
+/// `VOCABULARY` are always interned first.
+pub struct Dictionary {
+    grand_ole_pry: Rodeo,
+}
+
+impl Default for Dictionary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dictionary {
+    pub fn new() -> Self {
+        let mut grand_ole_pry = Rodeo::default();
+        for term in VOCABULARY.iter() {
+            grand_ole_pry.get_or_intern(term);
+        }
+        Dictionary { grand_ole_pry }
+    }
+
+    /// Interns `term`, with literals in their canonical form so that
+    /// `"01"^^xsd:integer` and `"1"^^xsd:integer` share an id.
+    pub fn encode(&mut self, term: &str) -> usize {
+        self.grand_ole_pry
+            .get_or_intern(datatypes::canonical(term))
+            .into_usize()
+    }
+
+    pub fn encode_triple(&mut self, triple: &(String, String, String)) -> EncodedTriple {
+        (
+            self.encode(&triple.0),
+            self.encode(&triple.1),
+            self.encode(&triple.2),
+        )
+    }
+
+    pub fn get(&self, term: &str) -> Option<usize> {
+        self.grand_ole_pry
+            .get(datatypes::canonical(term))
+            .map(|key| key.into_usize())
+    }
+
+    pub fn decode(&self, id: usize) -> Option<&str> {
+        Spur::try_from_usize(id).and_then(|key| self.grand_ole_pry.try_resolve(&key))
+    }
+
+    /// The value of `id` if it is a literal usable as a cardinality, such as
+    /// `"1"^^xsd:nonNegativeInteger` or `"1"^^xsd:integer`.
+    pub fn cardinality(&self, id: usize) -> Option<u64> {
+        self.decode(id).and_then(datatypes::cardinality)
+    }
+
+    pub fn len(&self) -> usize {
+        self.grand_ole_pry.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.grand_ole_pry.is_empty()
+    }
+
+    /// Writes the dictionary in the `id term` format read by `loadkvenc`.
+    pub fn save(&self, path: &str) -> Result<()> {
+        let file = File::create(path).map_err(|source| Error::io(path, source))?;
+        let mut writer = BufWriter::new(file);
+        for id in 0..self.len() {
+            writeln!(writer, "{} {}", id, self.decode(id).unwrap())
+                .map_err(|source| Error::io(path, source))?;
+        }
+        writer.flush().map_err(|source| Error::io(path, source))
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let mut dictionary = Dictionary::new();
+        for entry in loadkvenc(path)? {
+            let (id, term) = entry?;
+            if dictionary.encode(&term) != id {
+                return Err(Error::Snapshot(format!(
+                    "{}: id {} of {} is out of sequence or conflicts with the vocabulary",
+                    path, id, term
+                )));
+            }
+        }
+        Ok(dictionary)
+    }
+}
@@ -0,0 +1,195 @@
+//! Graphviz export of a worker's dataflows. An `OperatorGraph` records the
+//! operators and channels timely logs while the dataflows are built, and
+//! renders scopes (regions and iterative scopes) as clusters, arrangements as
+//! cylinders and the channels out of `Feedback` operators as dashed edges.
+
+use crate::logging::{self, child, FEEDBACK};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+use timely::communication::Allocate;
+use timely::logging::TimelyEvent;
+use timely::worker::Worker;
+
+/// A channel: the address of its scope, and its source and target as
+/// (child, port); child 0 stands for the scope's own inputs and outputs.
+type Channel = (Vec<usize>, (usize, usize), (usize, usize));
+
+/// The operators and channels of a worker's dataflows.
+#[derive(Default)]
+pub(crate) struct Operators {
+    /// Operator addresses and names.
+    operators: Vec<(Vec<usize>, String)>,
+    channels: Vec<Channel>,
+}
+
+impl Operators {
+    pub(crate) fn operates(&mut self, addr: Vec<usize>, name: String) {
+        self.operators.push((addr, name));
+    }
+
+    pub(crate) fn channel(
+        &mut self,
+        scope: Vec<usize>,
+        source: (usize, usize),
+        target: (usize, usize),
+    ) {
+        self.channels.push((scope, source, target));
+    }
+
+    /// The operator graph in DOT format.
+    pub(crate) fn render(&self) -> String {
+        let scopes: BTreeSet<&[usize]> =
+            logging::scopes(self.operators.iter().map(|(addr, _name)| addr)).collect();
+        let name = |addr: &[usize]| {
+            self.operators
+                .iter()
+                .find(|(other, _name)| &other[..] == addr)
+                .map_or("", |(_addr, name)| &name[..])
+        };
+
+        // Channels that cross a scope's boundary end at one of its ports.
+        let mut ports: BTreeSet<(Vec<usize>, String)> = BTreeSet::new();
+        let mut endpoint = |scope: &[usize], (index, port_index): (usize, usize), direction| {
+            let addr = if index == 0 {
+                scope.to_vec()
+            } else {
+                child(scope, index)
+            };
+            if index == 0 || scopes.contains(&addr[..]) {
+                // A scope's inputs are its inner sources and its outputs its
+                // inner targets.
+                let direction = match (index, direction) {
+                    (0, "out") => "in",
+                    (0, _) => "out",
+                    (_, direction) => direction,
+                };
+                let port = port(&addr, direction, port_index);
+                ports.insert((addr, port.clone()));
+                port
+            } else {
+                node(&addr)
+            }
+        };
+        let edges: Vec<String> = self
+            .channels
+            .iter()
+            .map(|(scope, source, target)| {
+                let style = if source.0 != 0 && name(&child(scope, source.0)) == FEEDBACK {
+                    " [style=dashed, label=\"feedback\"]"
+                } else {
+                    ""
+                };
+                format!(
+                    "    {} -> {}{};\n",
+                    endpoint(scope, *source, "out"),
+                    endpoint(scope, *target, "in"),
+                    style
+                )
+            })
+            .collect();
+
+        let mut dot = String::from("digraph dataflow {\n    node [shape=box];\n");
+        let mut operators: Vec<&(Vec<usize>, String)> = self.operators.iter().collect();
+        operators.sort();
+        render_scope(&mut dot, &[], &operators, &scopes, &ports, 1);
+        edges.iter().for_each(|edge| dot.push_str(edge));
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Writes the operators directly inside the scope at `scope`, nesting the
+/// clusters of its child scopes.
+fn render_scope(
+    dot: &mut String,
+    scope: &[usize],
+    operators: &[&(Vec<usize>, String)],
+    scopes: &BTreeSet<&[usize]>,
+    ports: &BTreeSet<(Vec<usize>, String)>,
+    depth: usize,
+) {
+    let indent = "    ".repeat(depth);
+    for (_scope, port) in ports.iter().filter(|(other, _port)| &other[..] == scope) {
+        dot.push_str(&format!("{}{} [shape=point];\n", indent, port));
+    }
+    for (addr, name) in operators.iter().map(|operator| (&operator.0, &operator.1)) {
+        if addr.len() != scope.len() + 1 || !addr.starts_with(scope) {
+            continue;
+        }
+        let label = escape(name);
+        if scopes.contains(&addr[..]) {
+            dot.push_str(&format!(
+                "{}subgraph cluster_{} {{\n{}    label=\"{}\";\n",
+                indent,
+                id(addr),
+                indent,
+                label
+            ));
+            render_scope(dot, addr, operators, scopes, ports, depth + 1);
+            dot.push_str(&format!("{}}}\n", indent));
+        } else {
+            let shape = if name.starts_with("Arrange") {
+                ", shape=cylinder"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "{}{} [label=\"{}\"{}];\n",
+                indent,
+                node(addr),
+                label,
+                shape
+            ));
+        }
+    }
+}
+
+/// A boundary port of a scope, drawn as a point inside its cluster.
+fn port(scope: &[usize], direction: &str, index: usize) -> String {
+    format!("port_{}_{}{}", id(scope), direction, index)
+}
+
+fn node(addr: &[usize]) -> String {
+    format!("op_{}", id(addr))
+}
+
+fn id(addr: &[usize]) -> String {
+    addr.iter()
+        .map(|index| index.to_string())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Records the operator graph of the dataflows a worker builds.
+pub struct OperatorGraph {
+    operators: Rc<RefCell<Operators>>,
+}
+
+impl OperatorGraph {
+    /// Starts listening to `worker`'s operator log; only dataflows built
+    /// afterwards are recorded.
+    pub fn install<A: Allocate>(worker: &mut Worker<A>) -> Self {
+        let operators = Rc::new(RefCell::new(Operators::default()));
+        let log = Rc::clone(&operators);
+        logging::listen(worker, move |_at, event| match event {
+            TimelyEvent::Operates(event) => log.borrow_mut().operates(event.addr, event.name),
+            TimelyEvent::Channels(event) => {
+                log.borrow_mut()
+                    .channel(event.scope_addr, event.source, event.target)
+            }
+            _ => {}
+        });
+        OperatorGraph { operators }
+    }
+
+    /// The operator graph recorded so far, in DOT format.
+    pub fn render<A: Allocate>(&self, worker: &mut Worker<A>) -> String {
+        worker.log_register().flush();
+        self.operators.borrow().render()
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// A file could not be opened, read or written.
+    Io { path: String, source: io::Error },
+    /// A line of an input file is malformed.
+    Parse {
+        path: String,
+        line: usize,
+        message: String,
+    },
+    /// An invalid argument or configuration value.
+    Config(String),
+    /// A timely worker failed or could not be started.
+    Worker(String),
+    /// A snapshot is incomplete or does not match the current configuration.
+    Snapshot(String),
+    /// A timestamp that has not been assigned yet, or is no longer retained.
+    Timestamp(String),
+}
+
+impl Error {
+    pub(crate) fn io(path: impl AsRef<Path>, source: io::Error) -> Self {
+        Error::Io {
+            path: path.as_ref().display().to_string(),
+            source,
+        }
+    }
+
+    pub(crate) fn parse(path: &str, line: usize, message: String) -> Self {
+        Error::Parse {
+            path: path.to_string(),
+            line,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path, source),
+            Error::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{}: {}", path, line, message),
+            Error::Config(message) => write!(f, "{}", message),
+            Error::Worker(message) => write!(f, "worker failed: {}", message),
+            Error::Snapshot(message) => write!(f, "invalid snapshot: {}", message),
+            Error::Timestamp(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
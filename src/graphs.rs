@@ -0,0 +1,96 @@
+//! Named graphs. Quads enter the ABox as triples whose subject, and object
+//! unless the predicate is `rdf:type`, carry their graph in the high half of
+//! the bits of the id. Rules only ever join ABox triples on those positions,
+//! so a materialization of such triples never mixes graphs, while class and
+//! property ids stay shared with the TBox. Term and graph ids have to fit in
+//! the low half: 32 bits, or 16 where `usize` has 32.
+
+use crate::error::{Error, Result};
+use crate::materializations::EncodedTriple;
+use crate::vocabulary::ids::RDF_TYPE;
+use std::fmt;
+use std::str::FromStr;
+
+/// An encoded triple followed by the id of its graph.
+pub type EncodedQuad = (usize, usize, usize, usize);
+
+/// The graph of triples that are not in a named graph.
+pub const DEFAULT_GRAPH: usize = usize::MAX;
+
+const GRAPH_SHIFT: u32 = (std::mem::size_of::<usize>() * 4) as u32;
+const TERM_MASK: usize = (1 << GRAPH_SHIFT) - 1;
+
+/// How the ABox of several graphs is reasoned over; the TBox is always shared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GraphMode {
+    /// The ABox of every graph is materialized on its own, and inferences stay
+    /// in the graph of the triples they follow from.
+    PerGraph,
+    /// All graphs are materialized together. The materialization is in the
+    /// default graph, and the triples of named graphs also stay in theirs.
+    Union,
+}
+
+impl GraphMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GraphMode::PerGraph => "per-graph",
+            GraphMode::Union => "union",
+        }
+    }
+}
+
+impl FromStr for GraphMode {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "per-graph" => Ok(GraphMode::PerGraph),
+            "union" => Ok(GraphMode::Union),
+            _ => Err(Error::Config(format!("unknown graph mode {:?}", name))),
+        }
+    }
+}
+
+impl fmt::Display for GraphMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Encodes `quad` as an ABox triple; triples of the default graph are left
+/// unchanged. Fails if its subject, object or graph id does not fit in the
+/// low half of an id, where `unscope` would take it for a graph.
+pub fn scope(quad: EncodedQuad) -> Result<EncodedTriple> {
+    let (s, p, o, graph) = quad;
+    let fits = s <= TERM_MASK && o <= TERM_MASK && (graph == DEFAULT_GRAPH || graph < TERM_MASK);
+    if !fits {
+        return Err(Error::Config(format!(
+            "quad {:?} has an id that does not fit in {} bits",
+            quad, GRAPH_SHIFT
+        )));
+    }
+    if graph == DEFAULT_GRAPH {
+        return Ok((s, p, o));
+    }
+    let tag = (graph + 1) << GRAPH_SHIFT;
+    if p == RDF_TYPE {
+        Ok((tag | s, p, o))
+    } else {
+        Ok((tag | s, p, tag | o))
+    }
+}
+
+/// Decodes an ABox triple produced by `scope`, or inferred from such triples.
+pub fn unscope(triple: EncodedTriple) -> EncodedQuad {
+    let (s, p, o) = triple;
+    match s >> GRAPH_SHIFT {
+        0 => (s, p, o, DEFAULT_GRAPH),
+        tag => (s & TERM_MASK, p, o & TERM_MASK, tag - 1),
+    }
+}
+
+/// The graph of an ABox triple produced by `scope`.
+pub fn graph(triple: &EncodedTriple) -> usize {
+    unscope(*triple).3
+}
@@ -0,0 +1,277 @@
+//! Shared indexes. The materializations split the TBox by predicate in a
+//! single pass and keep one arrangement per view of the TBox and the ABox,
+//! which every rule imports instead of filtering and arranging its own copy.
+//! Views are arranged on first use, so a dataflow only pays for the ones its
+//! rules join with.
+
+use crate::vocabulary::ids::{
+    DOMAIN, INVERSE_OF, RANGE, RDF_TYPE, SUB_CLASS_OF, SUB_PROPERTY_OF, TRANSITIVE_PROPERTY,
+};
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::arrange::arrangement::ArrangeByKey;
+use differential_dataflow::operators::arrange::{Arranged, TraceAgent};
+use differential_dataflow::operators::reduce::Threshold;
+use differential_dataflow::trace::implementations::ord::OrdValSpine;
+use differential_dataflow::{AsCollection, Collection, ExchangeData, Hashable};
+use std::cell::RefCell;
+use timely::dataflow::operators::Partition;
+use timely::dataflow::scopes::ScopeParent;
+use timely::dataflow::Scope;
+
+/// An arrangement of `(key, value)` pairs by key.
+pub type Index<G, K, V> =
+    Arranged<G, TraceAgent<OrdValSpine<K, V, <G as ScopeParent>::Timestamp, isize>>>;
+
+/// Splits `triples`, keyed by subject, by predicate in a single pass: the
+/// i-th collection holds the `(subject, object)` pairs of `predicates[i]`,
+/// and triples with other predicates are dropped.
+pub fn partition_by_predicate<G: Scope>(
+    triples: &Collection<G, (usize, (usize, usize))>,
+    predicates: &[usize],
+) -> Vec<Collection<G, (usize, usize)>> {
+    let routes = predicates.to_vec();
+    let others = routes.len();
+    let mut parts: Vec<_> = triples
+        .inner
+        .partition(others as u64 + 1, move |((s, (p, o)), time, diff)| {
+            let part = routes.iter().position(|q| *q == p).unwrap_or(others);
+            (part as u64, ((s, o), time, diff))
+        })
+        .into_iter()
+        .map(|stream| stream.as_collection())
+        .collect();
+    parts.pop();
+    parts
+}
+
+/// A collection and its arrangement, built the first time it is asked for.
+struct Shared<G: Scope, K: ExchangeData, V: ExchangeData>
+where
+    G::Timestamp: Lattice,
+{
+    name: &'static str,
+    pairs: Collection<G, (K, V)>,
+    /// Whether every pair is arranged once, whatever its multiplicity.
+    distinct: bool,
+    arranged: RefCell<Option<Index<G, K, V>>>,
+}
+
+impl<G, K, V> Shared<G, K, V>
+where
+    G: Scope,
+    G::Timestamp: Lattice,
+    K: ExchangeData + Hashable,
+    V: ExchangeData + Hashable,
+{
+    fn new(name: &'static str, pairs: Collection<G, (K, V)>) -> Self {
+        Shared {
+            name,
+            pairs,
+            distinct: false,
+            arranged: RefCell::new(None),
+        }
+    }
+
+    fn distinct(name: &'static str, pairs: Collection<G, (K, V)>) -> Self {
+        Shared {
+            distinct: true,
+            ..Shared::new(name, pairs)
+        }
+    }
+
+    fn get(&self) -> Index<G, K, V> {
+        let pairs = &self.pairs;
+        let name = self.name;
+        let distinct = self.distinct;
+        self.arranged
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                if distinct {
+                    pairs.distinct().arrange_by_key_named(name)
+                } else {
+                    pairs.arrange_by_key_named(name)
+                }
+            })
+            .clone()
+    }
+}
+
+/// The schema axioms the ABox rules join with, from a TBox closed under
+/// `rdfs:subClassOf` and `rdfs:subPropertyOf`.
+pub struct TboxIndex<G: Scope>
+where
+    G::Timestamp: Lattice,
+{
+    sub_class_of: Shared<G, usize, usize>,
+    sub_property_of: Shared<G, usize, usize>,
+    domain: Shared<G, usize, usize>,
+    range: Shared<G, usize, usize>,
+    inverse_of: Shared<G, usize, usize>,
+    transitive: Shared<G, usize, ()>,
+}
+
+impl<G: Scope> TboxIndex<G>
+where
+    G::Timestamp: Lattice,
+{
+    pub fn new(tbox: &Collection<G, (usize, (usize, usize))>) -> Self {
+        let mut parts = partition_by_predicate(
+            tbox,
+            &[
+                SUB_CLASS_OF,
+                SUB_PROPERTY_OF,
+                DOMAIN,
+                RANGE,
+                INVERSE_OF,
+                RDF_TYPE,
+            ],
+        )
+        .into_iter();
+        let mut next = || parts.next().unwrap();
+        let (sub_class_of, sub_property_of, domain, range, inverse_of, types) =
+            (next(), next(), next(), next(), next(), next());
+
+        TboxIndex {
+            sub_class_of: Shared::new("Shared index rdfs:subClassOf", sub_class_of),
+            sub_property_of: Shared::new("Shared index rdfs:subPropertyOf", sub_property_of),
+            domain: Shared::new("Shared index rdfs:domain", domain),
+            range: Shared::new("Shared index rdfs:range", range),
+            inverse_of: Shared::new(
+                "Shared index owl:inverseOf",
+                inverse_of.concat(&inverse_of.map(|(p, q)| (q, p))),
+            ),
+            transitive: Shared::new(
+                "Shared index owl:TransitiveProperty",
+                types
+                    .filter(|(_p, class)| *class == TRANSITIVE_PROPERTY)
+                    .map(|(p, _class)| (p, ())),
+            ),
+        }
+    }
+
+    /// Subclass to superclass.
+    pub fn sub_class_of(&self) -> Index<G, usize, usize> {
+        self.sub_class_of.get()
+    }
+
+    /// Subproperty to superproperty.
+    pub fn sub_property_of(&self) -> Index<G, usize, usize> {
+        self.sub_property_of.get()
+    }
+
+    /// Property to the classes of its subjects.
+    pub fn domain(&self) -> Index<G, usize, usize> {
+        self.domain.get()
+    }
+
+    /// Property to the classes of its objects.
+    pub fn range(&self) -> Index<G, usize, usize> {
+        self.range.get()
+    }
+
+    /// Property to its inverses, in both directions of every axiom.
+    pub fn inverse_of(&self) -> Index<G, usize, usize> {
+        self.inverse_of.get()
+    }
+
+    /// The transitive properties.
+    pub fn transitive(&self) -> Index<G, usize, ()> {
+        self.transitive.get()
+    }
+}
+
+/// Property assertions `(p, (s, o))`, vertically partitioned by predicate.
+pub struct PropertyIndex<G: Scope>
+where
+    G::Timestamp: Lattice,
+{
+    by_predicate: Shared<G, usize, (usize, usize)>,
+    by_subject: Shared<G, (usize, usize), usize>,
+    by_object: Shared<G, (usize, usize), usize>,
+    subjects: Shared<G, usize, usize>,
+    objects: Shared<G, usize, usize>,
+}
+
+impl<G: Scope> PropertyIndex<G>
+where
+    G::Timestamp: Lattice,
+{
+    pub fn new(properties: &Collection<G, (usize, (usize, usize))>) -> Self {
+        PropertyIndex {
+            by_predicate: Shared::new("Shared index (p, (s, o))", properties.clone()),
+            by_subject: Shared::new(
+                "Shared index ((p, s), o)",
+                properties.map(|(p, (s, o))| ((p, s), o)),
+            ),
+            by_object: Shared::new(
+                "Shared index ((p, o), s)",
+                properties.map(|(p, (s, o))| ((p, o), s)),
+            ),
+            subjects: Shared::distinct(
+                "Shared index (p, s)",
+                properties.map(|(p, (s, _o))| (p, s)),
+            ),
+            objects: Shared::distinct("Shared index (p, o)", properties.map(|(p, (_s, o))| (p, o))),
+        }
+    }
+
+    /// The indexed assertions.
+    pub fn collection(&self) -> Collection<G, (usize, (usize, usize))> {
+        self.by_predicate.pairs.clone()
+    }
+
+    /// Predicate to subject and object.
+    pub fn by_predicate(&self) -> Index<G, usize, (usize, usize)> {
+        self.by_predicate.get()
+    }
+
+    /// Predicate and subject to object.
+    pub fn by_subject(&self) -> Index<G, (usize, usize), usize> {
+        self.by_subject.get()
+    }
+
+    /// Predicate and object to subject.
+    pub fn by_object(&self) -> Index<G, (usize, usize), usize> {
+        self.by_object.get()
+    }
+
+    /// Predicate to its subjects, once each: the keys of `by_subject`, by
+    /// predicate.
+    pub fn subjects(&self) -> Index<G, usize, usize> {
+        self.subjects.get()
+    }
+
+    /// Predicate to its objects, once each, like `subjects`.
+    pub fn objects(&self) -> Index<G, usize, usize> {
+        self.objects.get()
+    }
+}
+
+/// Class assertions `(class, individual)`.
+pub struct TypeIndex<G: Scope>
+where
+    G::Timestamp: Lattice,
+{
+    by_class: Shared<G, usize, usize>,
+}
+
+impl<G: Scope> TypeIndex<G>
+where
+    G::Timestamp: Lattice,
+{
+    pub fn new(types: &Collection<G, (usize, usize)>) -> Self {
+        TypeIndex {
+            by_class: Shared::new("Shared index (class, s)", types.clone()),
+        }
+    }
+
+    /// The indexed assertions.
+    pub fn collection(&self) -> Collection<G, (usize, usize)> {
+        self.by_class.pairs.clone()
+    }
+
+    /// Class to its individuals.
+    pub fn by_class(&self) -> Index<G, usize, usize> {
+        self.by_class.get()
+    }
+}
@@ -0,0 +1,603 @@
+//! Live ingestion from a socket. A `Listener` accepts producers on a TCP or
+//! Unix domain socket, one connection at a time, and reads lines of
+//! N-Triples or encoded triples, each optionally preceded by `-` to retract
+//! it. The lines are cut into micro-batches, every batch is one timestamp of
+//! the ABox, or carry the timestamp of their event and are buffered until a
+//! watermark passes it. Workers only take more once the dataflow has caught
+//! up, so a fast producer is slowed down by the socket instead of piling up
+//! updates.
+
+use crate::dictionary::Dictionary;
+use crate::error::{Error, Result};
+use crate::load_encode_triples::{parse3enc, parse3nt, parse_timed_update, parse_update};
+use crate::materializations::EncodedTriple;
+use crate::reasoner::Reasoner;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::str::FromStr;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TryRecvError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use timely::communication::Allocate;
+use timely::worker::Worker;
+
+/// Where producers connect: `unix:<path>` for a Unix domain socket, a TCP
+/// address otherwise.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Endpoint {
+    Tcp(String),
+    #[cfg(unix)]
+    Unix(String),
+}
+
+impl FromStr for Endpoint {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        match text.strip_prefix("unix:") {
+            #[cfg(unix)]
+            Some(path) => Ok(Endpoint::Unix(path.to_string())),
+            #[cfg(not(unix))]
+            Some(_) => Err(Error::Config(
+                "Unix domain sockets are not supported on this platform".to_string(),
+            )),
+            None => Ok(Endpoint::Tcp(text.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Endpoint::Tcp(address) => f.write_str(address),
+            #[cfg(unix)]
+            Endpoint::Unix(path) => write!(f, "unix:{}", path),
+        }
+    }
+}
+
+/// How the stream is cut into micro-batches: a batch is closed after `size`
+/// updates, or `interval` after its first update, whichever comes first, and
+/// when its producer disconnects.
+#[derive(Clone, Copy, Debug)]
+pub struct Batching {
+    pub size: usize,
+    pub interval: Option<Duration>,
+    /// How many closed timestamps may be left to materialize before workers
+    /// stop taking batches.
+    pub max_lag: usize,
+}
+
+enum Socket {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+enum Connection {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Connection {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Connection::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Connection::Tcp(stream) => stream.read(buffer),
+            #[cfg(unix)]
+            Connection::Unix(stream) => stream.read(buffer),
+        }
+    }
+}
+
+/// A bound socket, not accepting producers yet.
+pub struct Listener {
+    socket: Socket,
+    endpoint: Endpoint,
+}
+
+impl Listener {
+    pub fn bind(endpoint: &Endpoint) -> Result<Listener> {
+        let error = |source| Error::io(endpoint.to_string(), source);
+        let (socket, endpoint) = match endpoint {
+            Endpoint::Tcp(address) => {
+                let listener = TcpListener::bind(address).map_err(error)?;
+                let local = listener.local_addr().map_err(error)?;
+                (Socket::Tcp(listener), Endpoint::Tcp(local.to_string()))
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => (
+                Socket::Unix(UnixListener::bind(path).map_err(error)?),
+                endpoint.clone(),
+            ),
+        };
+        Ok(Listener { socket, endpoint })
+    }
+
+    /// The endpoint producers connect to, with the port picked for port `0`.
+    pub fn endpoint(&self) -> &Endpoint {
+        &self.endpoint
+    }
+
+    /// Starts accepting producers on a thread of its own, and returns the
+    /// feed with one input per worker. Lines are N-Triples encoded with
+    /// `dictionary` if there is one, encoded triples otherwise; malformed
+    /// lines are skipped, and returned by `Feed::join`. The feed ends after
+    /// `connections` producers have disconnected, if given.
+    pub fn start(
+        self,
+        batching: Batching,
+        workers: usize,
+        dictionary: Option<Dictionary>,
+        connections: Option<usize>,
+    ) -> (Feed, Vec<FeedInput>) {
+        let (senders, inputs) = channels(workers, batching.max_lag, Late::Correct);
+        let batcher = Batcher {
+            senders,
+            batching,
+            batch: Vec::new(),
+            opened: Instant::now(),
+        };
+        (self.spawn(batcher, dictionary, connections), inputs)
+    }
+
+    /// Like `start`, for lines preceded by the timestamp of their event,
+    /// which may arrive out of order. Events are inserted at their own
+    /// timestamp, and a timestamp is only closed once the watermark, which
+    /// trails the latest event by `lateness`, has passed it. Events behind
+    /// the watermark are handled as `late` says.
+    pub fn start_events(
+        self,
+        watermarks: Watermarks,
+        workers: usize,
+        dictionary: Option<Dictionary>,
+        connections: Option<usize>,
+    ) -> (Feed, Vec<FeedInput>) {
+        let (senders, inputs) = channels(workers, watermarks.max_lag, watermarks.late);
+        let buffer = EventBuffer {
+            senders,
+            watermarks,
+            events: Vec::new(),
+            latest: None,
+            watermark: 0,
+        };
+        (self.spawn(buffer, dictionary, connections), inputs)
+    }
+
+    fn spawn(
+        self,
+        mut forward: impl Forward + Send + 'static,
+        dictionary: Option<Dictionary>,
+        connections: Option<usize>,
+    ) -> Feed {
+        let reader = std::thread::spawn(move || {
+            let mut dictionary = dictionary;
+            let mut skipped = Vec::new();
+            let result = self.serve(&mut forward, dictionary.as_mut(), connections, &mut skipped);
+            #[cfg(unix)]
+            {
+                if let Endpoint::Unix(path) = &self.endpoint {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+            result.map(|()| Fed {
+                dictionary,
+                skipped,
+            })
+        });
+        Feed { reader }
+    }
+
+    fn accept(&self) -> io::Result<Connection> {
+        match &self.socket {
+            Socket::Tcp(listener) => listener.accept().map(|(stream, _)| Connection::Tcp(stream)),
+            #[cfg(unix)]
+            Socket::Unix(listener) => listener
+                .accept()
+                .map(|(stream, _)| Connection::Unix(stream)),
+        }
+    }
+
+    fn serve(
+        &self,
+        forward: &mut impl Forward,
+        mut dictionary: Option<&mut Dictionary>,
+        connections: Option<usize>,
+        skipped: &mut Vec<Error>,
+    ) -> Result<()> {
+        let path = self.endpoint.to_string();
+        let mut served = 0;
+        while connections.map_or(true, |connections| served < connections) {
+            let connection = self.accept().map_err(|source| Error::io(&path, source))?;
+            served += 1;
+            let mut reader = BufReader::new(connection);
+            let mut line = Vec::new();
+            let mut number = 0;
+            loop {
+                let remaining = forward.remaining();
+                if remaining == Some(Duration::from_secs(0)) {
+                    if !forward.flush() {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                // A zero timeout is rejected rather than taken as none.
+                reader
+                    .get_ref()
+                    .set_read_timeout(remaining.map(|remaining| remaining.max(MIN_TIMEOUT)))
+                    .map_err(|source| Error::io(&path, source))?;
+                // A partial line stays in `line` when the read times out, even
+                // if it ends inside a character.
+                let end = match reader.read_until(b'\n', &mut line) {
+                    Ok(0) => true,
+                    Ok(_) => false,
+                    Err(error)
+                        if error.kind() == io::ErrorKind::WouldBlock
+                            || error.kind() == io::ErrorKind::TimedOut =>
+                    {
+                        continue
+                    }
+                    Err(source) => return Err(Error::io(&path, source)),
+                };
+                if !line.is_empty() {
+                    number += 1;
+                    let pushed = match std::str::from_utf8(&line) {
+                        Ok(text) if text.trim().is_empty() || text.trim().starts_with('#') => {
+                            Ok(true)
+                        }
+                        Ok(text) => forward.push(text.trim(), dictionary.as_deref_mut()),
+                        Err(_) => Err("invalid UTF-8".to_string()),
+                    };
+                    match pushed {
+                        Ok(true) => {}
+                        Ok(false) => return Ok(()),
+                        // One producer's malformed line does not end the feed.
+                        Err(message) => skipped.push(Error::parse(&path, number, message)),
+                    }
+                    line.clear();
+                }
+                if end {
+                    break;
+                }
+            }
+            if !forward.flush() {
+                return Ok(());
+            }
+        }
+        forward.finish();
+        Ok(())
+    }
+}
+
+/// The shortest time the reader waits for a line while a batch is open.
+const MIN_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// Encodes the triple of an update, with `dictionary` if there is one.
+fn encode(
+    line: &str,
+    dictionary: Option<&mut Dictionary>,
+) -> std::result::Result<EncodedTriple, String> {
+    match dictionary {
+        Some(dictionary) => Ok(dictionary.encode_triple(&parse3nt(line)?)),
+        None => parse3enc(line),
+    }
+}
+
+/// What workers receive from the reader.
+enum Message {
+    /// A micro-batch, applied as the next timestamp.
+    Batch(Vec<(EncodedTriple, isize)>),
+    /// Events at their timestamps, and the watermark: every timestamp before
+    /// it is closed once they are inserted.
+    Events(Vec<(EncodedTriple, usize, isize)>, usize),
+}
+
+fn channels(
+    workers: usize,
+    max_lag: usize,
+    late: Late,
+) -> (Vec<SyncSender<Message>>, Vec<FeedInput>) {
+    (0..workers)
+        .map(|_| {
+            let (sender, receiver) = sync_channel(1);
+            let input = FeedInput {
+                receiver,
+                max_lag,
+                late,
+            };
+            (sender, input)
+        })
+        .unzip()
+}
+
+/// Sends `message` to worker 0, and `empty` to the others, so that all
+/// inputs close the same timestamps. `false` if the workers are gone.
+fn broadcast(
+    senders: &[SyncSender<Message>],
+    message: Message,
+    empty: impl Fn() -> Message,
+) -> bool {
+    let mut message = Some(message);
+    senders
+        .iter()
+        .all(|sender| sender.send(message.take().unwrap_or_else(&empty)).is_ok())
+}
+
+/// Turns the lines a listener reads into messages for the workers.
+trait Forward {
+    /// Takes a line that is neither blank nor a comment. `Ok(false)` if the
+    /// workers are gone.
+    fn push(
+        &mut self,
+        line: &str,
+        dictionary: Option<&mut Dictionary>,
+    ) -> std::result::Result<bool, String>;
+
+    /// How long the reader may wait for the next line.
+    fn remaining(&self) -> Option<Duration>;
+
+    /// Forwards what is buffered, blocking while the workers lag behind.
+    /// `false` if the workers are gone.
+    fn flush(&mut self) -> bool;
+
+    /// Forwards the rest once the feed ends.
+    fn finish(&mut self) -> bool {
+        self.flush()
+    }
+}
+
+/// Cuts updates into batches and forwards every batch to all workers.
+struct Batcher {
+    senders: Vec<SyncSender<Message>>,
+    batching: Batching,
+    batch: Vec<(EncodedTriple, isize)>,
+    /// When the first update of `batch` arrived.
+    opened: Instant,
+}
+
+impl Forward for Batcher {
+    /// Adds the update to the open batch, and closes it once full.
+    fn push(
+        &mut self,
+        line: &str,
+        dictionary: Option<&mut Dictionary>,
+    ) -> std::result::Result<bool, String> {
+        let (triple, diff) = parse_update(line);
+        let update = (encode(triple, dictionary)?, diff);
+        if self.batch.is_empty() {
+            self.opened = Instant::now();
+        }
+        self.batch.push(update);
+        Ok(self.batch.len() < self.batching.size || self.flush())
+    }
+
+    /// How long the open batch may still wait for updates.
+    fn remaining(&self) -> Option<Duration> {
+        match self.batching.interval {
+            Some(interval) if !self.batch.is_empty() => Some(
+                interval
+                    .checked_sub(self.opened.elapsed())
+                    .unwrap_or_default(),
+            ),
+            _ => None,
+        }
+    }
+
+    fn flush(&mut self) -> bool {
+        if self.batch.is_empty() {
+            return true;
+        }
+        let batch = Message::Batch(std::mem::take(&mut self.batch));
+        broadcast(&self.senders, batch, || Message::Batch(Vec::new()))
+    }
+}
+
+/// What to do with events behind the watermark, whose timestamp is closed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Late {
+    Drop,
+    /// Applies them at the earliest open timestamp instead, correcting the
+    /// materialization from then on.
+    Correct,
+}
+
+impl Late {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Late::Drop => "drop",
+            Late::Correct => "correct",
+        }
+    }
+}
+
+impl FromStr for Late {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "drop" => Ok(Late::Drop),
+            "correct" => Ok(Late::Correct),
+            _ => Err(Error::Config(format!(
+                "unknown late event policy {:?}",
+                name
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Late {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How event-time stamped lines are buffered.
+#[derive(Clone, Copy, Debug)]
+pub struct Watermarks {
+    /// How many timestamps the watermark trails the latest event by.
+    pub lateness: usize,
+    pub late: Late,
+    /// How many events are buffered at most before they are forwarded, even
+    /// if the watermark has not moved.
+    pub size: usize,
+    /// Like `Batching::max_lag`.
+    pub max_lag: usize,
+}
+
+/// Buffers events until the watermark moves past the latest forwarded one.
+struct EventBuffer {
+    senders: Vec<SyncSender<Message>>,
+    watermarks: Watermarks,
+    events: Vec<(EncodedTriple, usize, isize)>,
+    /// The latest event timestamp read.
+    latest: Option<usize>,
+    /// The watermark last forwarded.
+    watermark: usize,
+}
+
+impl EventBuffer {
+    fn forward(&mut self, watermark: usize) -> bool {
+        self.watermark = self.watermark.max(watermark);
+        let events = Message::Events(std::mem::take(&mut self.events), self.watermark);
+        let watermark = self.watermark;
+        broadcast(&self.senders, events, || {
+            Message::Events(Vec::new(), watermark)
+        })
+    }
+}
+
+impl Forward for EventBuffer {
+    fn push(
+        &mut self,
+        line: &str,
+        dictionary: Option<&mut Dictionary>,
+    ) -> std::result::Result<bool, String> {
+        let (time, triple, diff) = match parse_timed_update(line)? {
+            Some(update) => update,
+            None => return Ok(true),
+        };
+        self.events.push((encode(triple, dictionary)?, time, diff));
+
+        let latest = self.latest.map_or(time, |latest| latest.max(time));
+        self.latest = Some(latest);
+        let watermark = latest.saturating_sub(self.watermarks.lateness);
+        if watermark > self.watermark || self.events.len() >= self.watermarks.size {
+            Ok(self.forward(watermark))
+        } else {
+            Ok(true)
+        }
+    }
+
+    fn remaining(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Forwards the buffered events without moving the watermark, since
+    /// later producers may still send events for the open timestamps.
+    fn flush(&mut self) -> bool {
+        self.events.is_empty() || self.forward(self.watermark)
+    }
+
+    /// Closes every timestamp up to the latest event.
+    fn finish(&mut self) -> bool {
+        let end = self.latest.map_or(0, |latest| latest + 1);
+        self.forward(end)
+    }
+}
+
+/// What a `Feed` read, once it has ended.
+pub struct Fed {
+    /// The dictionary the stream was encoded with.
+    pub dictionary: Option<Dictionary>,
+    /// The malformed lines that were skipped, in the order they were read.
+    pub skipped: Vec<Error>,
+}
+
+/// The thread reading from a `Listener`.
+pub struct Feed {
+    reader: JoinHandle<Result<Fed>>,
+}
+
+impl Feed {
+    /// Waits for the feed to end.
+    pub fn join(self) -> Result<Fed> {
+        self.reader
+            .join()
+            .map_err(|_| Error::Worker("the socket reader panicked".to_string()))?
+    }
+}
+
+/// What a worker applied from a feed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Followed {
+    /// Micro-batches, or buffers of events.
+    pub batches: usize,
+    /// Events behind the watermark, dropped or applied as corrections.
+    pub late: usize,
+}
+
+/// One worker's end of a `Feed`.
+pub struct FeedInput {
+    receiver: Receiver<Message>,
+    max_lag: usize,
+    late: Late,
+}
+
+impl FeedInput {
+    /// Applies the feed to the ABox of `reasoner` until it ends: micro-batches
+    /// one timestamp each, events at their own timestamps. While more than
+    /// `max_lag` closed timestamps are not final, nothing is taken, which
+    /// blocks the reader and in turn the producer.
+    pub fn follow<A: Allocate>(self, worker: &mut Worker<A>, reasoner: &mut Reasoner) -> Followed {
+        let mut followed = Followed::default();
+        loop {
+            let time = reasoner.time();
+            if time > self.max_lag && !reasoner.is_complete(time - 1 - self.max_lag) {
+                worker.step_or_park(Some(Duration::from_millis(1)));
+                continue;
+            }
+            match self.receiver.try_recv() {
+                Ok(Message::Batch(batch)) => {
+                    for (triple, diff) in batch {
+                        reasoner.update_abox(triple, diff);
+                    }
+                    reasoner.close();
+                    followed.batches += 1;
+                }
+                Ok(Message::Events(events, watermark)) => {
+                    for (triple, time, diff) in events {
+                        if time >= reasoner.time() {
+                            reasoner.update_abox_at(triple, time, diff);
+                        } else {
+                            followed.late += 1;
+                            if self.late == Late::Correct {
+                                reasoner.update_abox(triple, diff);
+                            }
+                        }
+                    }
+                    reasoner.close_until(watermark);
+                    followed.batches += 1;
+                }
+                Err(TryRecvError::Empty) => {
+                    worker.step_or_park(Some(Duration::from_millis(1)));
+                }
+                Err(TryRecvError::Disconnected) => break,
+            }
+        }
+        reasoner.advance_to(worker, reasoner.time());
+        followed
+    }
+}
@@ -1,5 +1,28 @@
 #![feature(once_cell)]
 #![feature(type_alias_impl_trait)]
+pub mod changes;
+pub mod datatypes;
+pub mod dictionary;
+pub mod dot;
+pub mod error;
+pub mod graphs;
+pub mod indexes;
+pub mod ingest;
+pub mod lists;
 pub mod load_encode_triples;
+pub(crate) mod logging;
+pub mod magic;
 pub mod materializations;
 pub mod owl2rl;
+pub mod partition;
+pub mod profiling;
+pub mod reasoner;
+pub mod replay;
+pub mod runtime;
+pub mod server;
+pub mod snapshot;
+pub mod sparql;
+pub mod subscriptions;
+pub mod trig;
+pub mod vocabulary;
+pub mod watch;
@@ -0,0 +1,303 @@
+use crate::dictionary::Dictionary;
+use crate::materializations::EncodedTriple;
+use crate::vocabulary::ids::RDF_TYPE;
+use crate::vocabulary::{
+    OWL_ALL_DISJOINT_CLASSES, OWL_HAS_KEY, OWL_INTERSECTION_OF, OWL_MEMBERS, OWL_ONE_OF,
+    OWL_PROPERTY_CHAIN_AXIOM, OWL_UNION_OF, RDF_FIRST, RDF_NIL, RDF_REST,
+};
+use differential_dataflow::input::Input;
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::reduce::Threshold;
+use differential_dataflow::operators::{Consolidate, Iterate, Join};
+use differential_dataflow::Collection;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use timely::communication::Allocate;
+use timely::dataflow::operators::probe::Handle;
+use timely::dataflow::operators::{Broadcast, Inspect, Probe};
+use timely::dataflow::Scope;
+use timely::worker::Worker;
+
+/// The ids of the list vocabulary. Unlike `vocabulary::VOCABULARY` it has no
+/// fixed encoding, so encoded input is free to give these ids to other terms.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ListTerms {
+    first: usize,
+    rest: usize,
+    nil: usize,
+    property_chain_axiom: usize,
+    intersection_of: usize,
+    union_of: usize,
+    one_of: usize,
+    has_key: usize,
+    all_disjoint_classes: usize,
+    members: usize,
+}
+
+impl ListTerms {
+    /// The list vocabulary with the ids `dictionary` gives it. Terms it does
+    /// not know yet are interned, as `Schema::resolve` does.
+    pub fn resolve(dictionary: &mut Dictionary) -> Self {
+        ListTerms {
+            first: dictionary.encode(RDF_FIRST),
+            rest: dictionary.encode(RDF_REST),
+            nil: dictionary.encode(RDF_NIL),
+            property_chain_axiom: dictionary.encode(OWL_PROPERTY_CHAIN_AXIOM),
+            intersection_of: dictionary.encode(OWL_INTERSECTION_OF),
+            union_of: dictionary.encode(OWL_UNION_OF),
+            one_of: dictionary.encode(OWL_ONE_OF),
+            has_key: dictionary.encode(OWL_HAS_KEY),
+            all_disjoint_classes: dictionary.encode(OWL_ALL_DISJOINT_CLASSES),
+            members: dictionary.encode(OWL_MEMBERS),
+        }
+    }
+
+    /// Whether `predicate` refers to a list.
+    fn refers(&self, predicate: usize) -> bool {
+        [
+            self.property_chain_axiom,
+            self.intersection_of,
+            self.union_of,
+            self.one_of,
+            self.has_key,
+            self.members,
+        ]
+        .contains(&predicate)
+    }
+}
+
+/// A TBox axiom whose object is an `rdf:List`, with the list decoded.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ListAxiom {
+    /// `property owl:propertyChainAxiom (chain)`
+    PropertyChain { property: usize, chain: Vec<usize> },
+    /// `class owl:intersectionOf (classes)`
+    IntersectionOf { class: usize, classes: Vec<usize> },
+    /// `class owl:unionOf (classes)`
+    UnionOf { class: usize, classes: Vec<usize> },
+    /// `class owl:oneOf (individuals)`
+    OneOf {
+        class: usize,
+        individuals: Vec<usize>,
+    },
+    /// `class owl:hasKey (properties)`
+    HasKey {
+        class: usize,
+        properties: Vec<usize>,
+    },
+    /// `x rdf:type owl:AllDisjointClasses ; owl:members (classes)`
+    AllDisjointClasses { classes: Vec<usize> },
+    /// `subject owl:members (members)` for any other kind of subject.
+    Members { subject: usize, members: Vec<usize> },
+}
+
+impl ListAxiom {
+    fn new(terms: &ListTerms, predicate: usize, subject: usize, elements: Vec<usize>) -> Self {
+        if predicate == terms.property_chain_axiom {
+            ListAxiom::PropertyChain {
+                property: subject,
+                chain: elements,
+            }
+        } else if predicate == terms.intersection_of {
+            ListAxiom::IntersectionOf {
+                class: subject,
+                classes: elements,
+            }
+        } else if predicate == terms.union_of {
+            ListAxiom::UnionOf {
+                class: subject,
+                classes: elements,
+            }
+        } else if predicate == terms.one_of {
+            ListAxiom::OneOf {
+                class: subject,
+                individuals: elements,
+            }
+        } else if predicate == terms.has_key {
+            ListAxiom::HasKey {
+                class: subject,
+                properties: elements,
+            }
+        } else if predicate == terms.all_disjoint_classes {
+            ListAxiom::AllDisjointClasses { classes: elements }
+        } else {
+            ListAxiom::Members {
+                subject,
+                members: elements,
+            }
+        }
+    }
+
+    /// Every id the axiom mentions: its subject and the list's elements.
+    pub fn ids(&self) -> Vec<usize> {
+        match self {
+            ListAxiom::PropertyChain {
+                property: subject,
+                chain: elements,
+            }
+            | ListAxiom::IntersectionOf {
+                class: subject,
+                classes: elements,
+            }
+            | ListAxiom::UnionOf {
+                class: subject,
+                classes: elements,
+            }
+            | ListAxiom::OneOf {
+                class: subject,
+                individuals: elements,
+            }
+            | ListAxiom::HasKey {
+                class: subject,
+                properties: elements,
+            }
+            | ListAxiom::Members {
+                subject,
+                members: elements,
+            } => std::iter::once(*subject)
+                .chain(elements.iter().cloned())
+                .collect(),
+            ListAxiom::AllDisjointClasses { classes } => classes.clone(),
+        }
+    }
+}
+
+/// Decodes the `rdf:first`/`rdf:rest` lists of `tbox` into the axioms that
+/// refer to them. Lists that do not end in `rdf:nil`, or that loop, yield
+/// nothing; a list node with several `rdf:first` or `rdf:rest` yields one
+/// axiom per possible list.
+pub fn list_axioms<G>(
+    tbox: &Collection<G, EncodedTriple>,
+    terms: ListTerms,
+    outer: &mut G,
+) -> Collection<G, ListAxiom>
+where
+    G: Scope,
+    G::Timestamp: Lattice,
+{
+    encoded_list_axioms(tbox, terms, outer)
+        .map(move |(p, s, elements)| ListAxiom::new(&terms, p, s, elements))
+}
+
+/// The list axioms of `tbox`, decoded in a dataflow of their own that is
+/// dropped before returning. Every worker passes its own part of the TBox and
+/// gets all of the axioms back, so that all of them can build the same rules
+/// from them.
+pub fn decode<A: Allocate>(
+    worker: &mut Worker<A>,
+    tbox: &[EncodedTriple],
+    terms: ListTerms,
+) -> Vec<ListAxiom> {
+    let counts = Rc::new(RefCell::new(BTreeMap::new()));
+    let sink = counts.clone();
+    let mut probe = Handle::new();
+    let dataflow = worker.next_dataflow_index();
+    let mut input = worker.dataflow::<usize, _, _>(|scope| {
+        let (input, tbox) = scope.new_collection::<EncodedTriple, isize>();
+        encoded_list_axioms(&tbox, terms, scope)
+            .inner
+            .broadcast()
+            .inspect(move |(axiom, _time, diff)| {
+                *sink.borrow_mut().entry(axiom.clone()).or_insert(0) += diff;
+            })
+            .probe_with(&mut probe);
+        input
+    });
+    for triple in tbox.iter() {
+        input.insert(*triple);
+    }
+    drop(input);
+    worker.step_while(|| !probe.done());
+    worker.drop_dataflow(dataflow);
+
+    let axioms = counts
+        .borrow()
+        .iter()
+        .filter(|(_axiom, count)| **count > 0)
+        .map(|((p, s, elements), _count)| ListAxiom::new(&terms, *p, *s, elements.clone()))
+        .collect();
+    axioms
+}
+
+/// The list axioms of `tbox` as `(predicate, subject, elements)`, with
+/// `owl:members` of an `owl:AllDisjointClasses` under that class's id.
+fn encoded_list_axioms<G>(
+    tbox: &Collection<G, EncodedTriple>,
+    terms: ListTerms,
+    outer: &mut G,
+) -> Collection<G, (usize, usize, Vec<usize>)>
+where
+    G: Scope,
+    G::Timestamp: Lattice,
+{
+    outer.region_named("List decoding", |inner| {
+        let tbox = tbox.enter(inner);
+
+        let first = tbox
+            .filter(move |(_s, p, _o)| *p == terms.first)
+            .map(|(node, _p, element)| (node, element));
+        let rest = tbox
+            .filter(move |(_s, p, _o)| *p == terms.rest)
+            .map(|(node, _p, next)| (node, next));
+
+        // (next, (node, element)) for every cell of every list.
+        let cells = rest.join_map(&first, |&node, &next, &element| (next, (node, element)));
+
+        let last_cells = cells
+            .filter(move |(next, _cell)| *next == terms.nil)
+            .map(|(_nil, (node, element))| (node, vec![(node, element)]));
+
+        // (head, cells) for every node that reaches rdf:nil, growing the
+        // lists towards their heads.
+        let lists = last_cells
+            .iterate(|lists| {
+                let cells = cells.enter(&lists.scope());
+                let last_cells = last_cells.enter(&lists.scope());
+                cells
+                    .join(lists)
+                    .flat_map(|(_next, ((node, element), tail))| {
+                        if tail.iter().any(|(visited, _)| *visited == node) {
+                            None
+                        } else {
+                            let mut list = Vec::with_capacity(tail.len() + 1);
+                            list.push((node, element));
+                            list.extend(tail);
+                            Some((node, list))
+                        }
+                    })
+                    .concat(&last_cells)
+                    .distinct()
+            })
+            .map(|(head, cells)| {
+                let elements: Vec<usize> = cells.into_iter().map(|(_, e)| e).collect();
+                (head, elements)
+            });
+
+        let references = tbox
+            .filter(move |(_s, p, _o)| terms.refers(*p))
+            .map(|(s, p, head)| (head, (s, p)));
+
+        let axioms = references.join_map(&lists, |_head, &(s, p), elements| {
+            (s, (p, elements.clone()))
+        });
+
+        let disjoint_classes = tbox
+            .filter(move |(_s, p, o)| *p == RDF_TYPE && *o == terms.all_disjoint_classes)
+            .map(|(s, _p, _o)| s)
+            .distinct();
+
+        let members = axioms.filter(move |(_s, (p, _elements))| *p == terms.members);
+        let all_disjoint = members
+            .semijoin(&disjoint_classes)
+            .map(move |(s, (_p, elements))| (s, (terms.all_disjoint_classes, elements)));
+
+        axioms
+            .filter(move |(_s, (p, _elements))| *p != terms.members)
+            .concat(&members.antijoin(&disjoint_classes))
+            .concat(&all_disjoint)
+            .map(|(s, (p, elements))| (p, s, elements))
+            .consolidate()
+            .leave()
+    })
+}
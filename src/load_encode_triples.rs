@@ -1,27 +1,302 @@
-pub fn read_file(filename: &str) -> impl Iterator<Item = String> {
-    use std::fs::File;
-    use std::io::{BufRead, BufReader};
-    let file = BufReader::new(File::open(filename).unwrap());
-    file.lines().filter_map(|line| line.ok())
-}
-
-pub fn load3enc<'a>(filename: &str) -> impl Iterator<Item = (usize, usize, usize)> + 'a {
-    read_file(filename).map(move |line| {
-        let mut elts = line.split(' ');
-        (
-            elts.next().unwrap().parse().unwrap(),
-            elts.next().unwrap().parse().unwrap(),
-            elts.next().unwrap().parse().unwrap(),
-        )
-    })
+use crate::error::{Error, Result};
+use crate::materializations::EncodedTriple;
+use crate::sparql::TripleUpdate;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+/// Yields the non-blank lines of `filename` together with their 1-based line number.
+pub fn read_file(filename: &str) -> Result<impl Iterator<Item = Result<(usize, String)>>> {
+    let path = filename.to_string();
+    let file = File::open(filename).map_err(|source| Error::io(filename, source))?;
+    Ok(BufReader::new(file)
+        .lines()
+        .enumerate()
+        .filter_map(move |(index, line)| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(Ok((index + 1, line))),
+            Err(source) => Some(Err(Error::io(&path, source))),
+        }))
+}
+
+pub fn load3enc(filename: &str) -> Result<impl Iterator<Item = Result<(usize, usize, usize)>>> {
+    let path = filename.to_string();
+    Ok(read_file(filename)?.map(move |line| {
+        let (number, line) = line?;
+        parse3enc(&line).map_err(|message| Error::parse(&path, number, message))
+    }))
+}
+
+pub fn loadkvenc(filename: &str) -> Result<impl Iterator<Item = Result<(usize, String)>>> {
+    let path = filename.to_string();
+    Ok(read_file(filename)?.map(move |line| {
+        let (number, line) = line?;
+        parsekvenc(&line).map_err(|message| Error::parse(&path, number, message))
+    }))
+}
+
+pub fn load3nt(filename: &str) -> Result<impl Iterator<Item = Result<(String, String, String)>>> {
+    let path = filename.to_string();
+    Ok(read_file(filename)?.filter_map(move |line| {
+        let (number, line) = match line {
+            Ok(line) => line,
+            Err(error) => return Some(Err(error)),
+        };
+        if line.trim_start().starts_with('#') {
+            return None;
+        }
+        Some(parse3nt(&line).map_err(|message| Error::parse(&path, number, message)))
+    }))
+}
+
+/// A statement in N-Triples lexical form with its graph label; `None` is the
+/// default graph.
+pub type Quad = (String, String, String, Option<String>);
+
+/// Loads an N-Quads file.
+pub fn load4nq(filename: &str) -> Result<impl Iterator<Item = Result<Quad>>> {
+    let path = filename.to_string();
+    Ok(read_file(filename)?.filter_map(move |line| {
+        let (number, line) = match line {
+            Ok(line) => line,
+            Err(error) => return Some(Err(error)),
+        };
+        if line.trim_start().starts_with('#') {
+            return None;
+        }
+        Some(parse4nq(&line).map_err(|message| Error::parse(&path, number, message)))
+    }))
+}
+
+/// Loads a delta file of encoded triples, each preceded by `+` to insert it or
+/// `-` to delete it.
+pub fn load3enc_delta(
+    filename: &str,
+) -> Result<impl Iterator<Item = Result<(EncodedTriple, isize)>>> {
+    let path = filename.to_string();
+    Ok(read_file(filename)?.map(move |line| {
+        let (number, line) = line?;
+        parse_delta(&line)
+            .and_then(|(line, diff)| Ok((parse3enc(line)?, diff)))
+            .map_err(|message| Error::parse(&path, number, message))
+    }))
 }
 
-pub fn loadkvenc<'a>(filename: &str) -> impl Iterator<Item = (usize, String)> + 'a {
-    read_file(filename).map(move |line| {
-        let mut elts = line.split(' ');
-        (
-            elts.next().unwrap().parse().unwrap(),
-            elts.next().unwrap().to_string(),
+/// Loads a delta file of N-Triples statements, each preceded by `+` to insert
+/// it or `-` to delete it.
+pub fn load3nt_delta(filename: &str) -> Result<impl Iterator<Item = Result<TripleUpdate>>> {
+    let path = filename.to_string();
+    Ok(read_file(filename)?.filter_map(move |line| {
+        let (number, line) = match line {
+            Ok(line) => line,
+            Err(error) => return Some(Err(error)),
+        };
+        if line.trim_start().starts_with('#') {
+            return None;
+        }
+        Some(
+            parse_delta(&line)
+                .and_then(|(line, diff)| Ok((parse3nt(line)?, diff)))
+                .map_err(|message| Error::parse(&path, number, message)),
         )
-    })
+    }))
+}
+
+/// An update of a stream file: the number of its line, its timestamp, its
+/// triple and its diff.
+pub type StreamUpdate<T> = (usize, usize, T, isize);
+
+/// Loads a stream file of encoded triples, lines of `parse_timed_update`'s
+/// form.
+pub fn load3enc_stream(
+    filename: &str,
+) -> Result<impl Iterator<Item = Result<StreamUpdate<EncodedTriple>>>> {
+    load_stream(filename, parse3enc)
+}
+
+/// Loads a stream file of N-Triples statements, like `load3enc_stream`.
+pub fn load3nt_stream(
+    filename: &str,
+) -> Result<impl Iterator<Item = Result<StreamUpdate<(String, String, String)>>>> {
+    load_stream(filename, parse3nt)
+}
+
+/// Loads a stream file, parsing the triple of every update with `parse`.
+fn load_stream<T>(
+    filename: &str,
+    parse: impl Fn(&str) -> std::result::Result<T, String>,
+) -> Result<impl Iterator<Item = Result<StreamUpdate<T>>>> {
+    let path = filename.to_string();
+    Ok(read_file(filename)?.filter_map(move |line| {
+        let (number, line) = match line {
+            Ok(line) => line,
+            Err(error) => return Some(Err(error)),
+        };
+        let update = parse_timed_update(&line).and_then(|update| match update {
+            Some((time, triple, diff)) => Ok(Some((number, time, parse(triple)?, diff))),
+            None => Ok(None),
+        });
+        update
+            .map_err(|message| Error::parse(&path, number, message))
+            .transpose()
+    }))
+}
+
+/// Parses a line of an update stream, `<time> [+|-] <triple>`, into the
+/// timestamp, the triple and its diff, as `parse_update` does. `None` for
+/// blank lines and `#` comments.
+pub fn parse_timed_update(line: &str) -> std::result::Result<Option<(usize, &str, isize)>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let (time, update) = line
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| "missing triple after the timestamp".to_string())?;
+    let time: usize = time
+        .parse()
+        .map_err(|_| format!("invalid timestamp {:?}", time))?;
+    let (triple, diff) = parse_update(update);
+    Ok(Some((time, triple, diff)))
+}
+
+/// Splits the sign off an update, `[+|-] <triple>`: the triple is retracted
+/// if preceded by `-`, and inserted otherwise.
+pub fn parse_update(line: &str) -> (&str, isize) {
+    let line = line.trim_start();
+    match line.strip_prefix('-') {
+        Some(rest) => (rest, -1),
+        None => (line.strip_prefix('+').unwrap_or(line), 1),
+    }
+}
+
+fn parse_delta(line: &str) -> std::result::Result<(&str, isize), String> {
+    let line = line.trim_start();
+    if let Some(rest) = line.strip_prefix('+') {
+        Ok((rest, 1))
+    } else if let Some(rest) = line.strip_prefix('-') {
+        Ok((rest, -1))
+    } else {
+        Err("expected '+' or '-'".to_string())
+    }
+}
+
+pub fn parse3enc(line: &str) -> std::result::Result<(usize, usize, usize), String> {
+    let mut elts = line.split_whitespace();
+    let triple = (
+        parse_id(&mut elts, "subject")?,
+        parse_id(&mut elts, "predicate")?,
+        parse_id(&mut elts, "object")?,
+    );
+    match elts.next() {
+        None => Ok(triple),
+        Some(elt) => Err(format!("unexpected trailing {:?}", elt)),
+    }
+}
+
+pub fn parse3nt(line: &str) -> std::result::Result<(String, String, String), String> {
+    let terms = parse_nt_terms(line)?;
+    match terms.as_slice() {
+        [s, p, o] => Ok((s.to_string(), p.to_string(), o.to_string())),
+        _ => Err(format!("expected 3 terms, found {}", terms.len())),
+    }
+}
+
+pub fn parse4nq(line: &str) -> std::result::Result<Quad, String> {
+    let terms = parse_nt_terms(line)?;
+    match terms.as_slice() {
+        [s, p, o] => Ok((s.to_string(), p.to_string(), o.to_string(), None)),
+        [s, p, o, g] if !g.starts_with('"') => Ok((
+            s.to_string(),
+            p.to_string(),
+            o.to_string(),
+            Some(g.to_string()),
+        )),
+        [_, _, _, g] => Err(format!("invalid graph label {}", g)),
+        _ => Err(format!("expected 3 or 4 terms, found {}", terms.len())),
+    }
+}
+
+fn parsekvenc(line: &str) -> std::result::Result<(usize, String), String> {
+    let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+    let key = parse_id(&mut std::iter::once(key), "key")?;
+    match value.trim() {
+        "" => Err("missing value".to_string()),
+        value => Ok((key, value.to_string())),
+    }
+}
+
+fn parse_id<'a>(
+    elts: &mut impl Iterator<Item = &'a str>,
+    position: &str,
+) -> std::result::Result<usize, String> {
+    let elt = elts.next().ok_or_else(|| format!("missing {}", position))?;
+    elt.parse()
+        .map_err(|_| format!("invalid {} id {:?}", position, elt))
+}
+
+/// Splits an N-Triples statement into its terms, keeping their lexical form.
+pub fn parse_nt_terms(line: &str) -> std::result::Result<Vec<&str>, String> {
+    let mut terms = Vec::new();
+    let mut rest = line.trim();
+    loop {
+        if let Some(tail) = rest.strip_prefix('.') {
+            let tail = tail.trim_start();
+            if tail.is_empty() || tail.starts_with('#') {
+                return Ok(terms);
+            }
+        }
+        if rest.is_empty() {
+            return Err("missing terminating '.'".to_string());
+        }
+        let end = term_end(rest)?;
+        terms.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+}
+
+pub(crate) fn term_end(input: &str) -> std::result::Result<usize, String> {
+    match input.chars().next() {
+        None => Err("missing term".to_string()),
+        Some('<') => input
+            .find('>')
+            .map(|index| index + 1)
+            .ok_or_else(|| format!("unterminated IRI {:?}", input)),
+        Some('"') => {
+            let mut escaped = false;
+            let mut close = None;
+            for (index, c) in input.char_indices().skip(1) {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => {
+                        close = Some(index);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            let close = close.ok_or_else(|| format!("unterminated literal {:?}", input))?;
+            let suffix = &input[close + 1..];
+            if let Some(datatype) = suffix.strip_prefix("^^") {
+                Ok(close + 3 + term_end(datatype)?)
+            } else if let Some(tag) = suffix.strip_prefix('@') {
+                Ok(close
+                    + 2
+                    + tag
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+                        .unwrap_or(tag.len()))
+            } else {
+                Ok(close + 1)
+            }
+        }
+        Some(_) => {
+            let end = input.find(char::is_whitespace).unwrap_or(input.len());
+            // A blank node label may be directly followed by the terminating '.'.
+            if end > 1 && input[..end].ends_with('.') && input[end..].trim().is_empty() {
+                Ok(end - 1)
+            } else {
+                Ok(end)
+            }
+        }
+    }
 }
@@ -0,0 +1,45 @@
+//! Listening to timely's operator log, for the `Profiler` and the
+//! `OperatorGraph`. Both see operators by their address: the indices of the
+//! scopes they are nested in, followed by their own index in the innermost.
+
+use std::time::Duration;
+use timely::communication::Allocate;
+use timely::logging::TimelyEvent;
+use timely::worker::Worker;
+
+/// The operators feeding data back into an iterative scope.
+pub(crate) const FEEDBACK: &str = "Feedback";
+
+/// Hands the events of `worker`'s timely log to `record`, with the time they
+/// were logged at. Operators are only logged when they are built, so this has
+/// to come before the dataflows to follow.
+pub(crate) fn listen<A, F>(worker: &mut Worker<A>, mut record: F)
+where
+    A: Allocate,
+    F: FnMut(Duration, TimelyEvent) + 'static,
+{
+    worker
+        .log_register()
+        .insert::<TimelyEvent, _>("timely", move |_time, events| {
+            for (at, _worker, event) in events.drain(..) {
+                record(at, event);
+            }
+        });
+}
+
+/// The address of child `index` of the scope at `scope`.
+pub(crate) fn child(scope: &[usize], index: usize) -> Vec<usize> {
+    let mut addr = scope.to_vec();
+    addr.push(index);
+    addr
+}
+
+/// The scopes, that is the operators with children, among the ancestors of
+/// the operators at `addrs`. Repeats scopes with several children.
+pub(crate) fn scopes<'a>(
+    addrs: impl IntoIterator<Item = &'a Vec<usize>>,
+) -> impl Iterator<Item = &'a [usize]> {
+    addrs
+        .into_iter()
+        .flat_map(|addr| (1..addr.len()).map(move |len| &addr[..len]))
+}
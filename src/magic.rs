@@ -0,0 +1,325 @@
+//! Goal-directed evaluation of the `rdfspp` ABox rules. Instead of the whole
+//! closure, only the triples that can contribute to the answers of a set of
+//! goal patterns are derived, following a magic-sets rewrite of the rules
+//! adorned on their predicate and class arguments:
+//!
+//! * a goal `?x rdf:type C` makes `C` and its subclasses relevant, and with
+//!   them the properties whose domain or range is one of those classes;
+//! * a goal `?x P ?y`, or a relevant property `P`, makes relevant the
+//!   properties `P` is derived from through `rdfs:subPropertyOf` and
+//!   `owl:inverseOf`;
+//! * goals with an unbound predicate, or `rdf:type` goals with an unbound
+//!   class, make every property or class relevant.
+//!
+//! The `rdfspp` rules of `materializations` then only run over the axioms and
+//! assertions of relevant properties and classes. Subjects and objects bound
+//! in a goal only filter its answers. The goals are a collection like the
+//! TBox and the ABox, so adding or removing one updates the relevant part of
+//! the closure incrementally.
+
+use crate::indexes::partition_by_predicate;
+use crate::load_encode_triples::term_end;
+use crate::materializations::{rdfspp, tbox_spo_sco_materialization, EncodedTriple};
+use crate::reasoner::{accumulate, compact, matches, Pattern};
+use crate::vocabulary::ids::{
+    DOMAIN, INVERSE_OF, RANGE, RDF_TYPE, SUB_CLASS_OF, SUB_PROPERTY_OF, TRANSITIVE_PROPERTY,
+};
+use differential_dataflow::input::{Input, InputSession};
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::arrange::{ArrangeBySelf, TraceAgent};
+use differential_dataflow::operators::reduce::Threshold;
+use differential_dataflow::operators::{Iterate, Join};
+use differential_dataflow::trace::implementations::ord::OrdKeySpine;
+use differential_dataflow::Collection;
+use timely::communication::Allocate;
+use timely::dataflow::operators::probe::Handle;
+use timely::dataflow::Scope;
+use timely::worker::Worker;
+
+/// A goal pattern together with one of its answers.
+pub type Answer = (Pattern, EncodedTriple);
+
+/// The ABox triples of the `rdfspp` materialization that match `goals`, each
+/// paired with the goal it answers.
+pub fn rdfspp_goals<G>(
+    tbox: &Collection<G, EncodedTriple>,
+    abox: &Collection<G, EncodedTriple>,
+    goals: &Collection<G, Pattern>,
+    outer: &mut G,
+) -> Collection<G, Answer>
+where
+    G: Scope,
+    G::Timestamp: Lattice,
+{
+    let tbox = tbox_spo_sco_materialization(tbox, outer);
+
+    let (relevant_tbox, relevant_abox) = outer.region_named("Magic sets", |inner| {
+        let tbox = tbox.enter(inner);
+        let abox = abox.enter(inner);
+        let goals = goals.enter(inner);
+
+        let mut schema = partition_by_predicate(
+            &tbox,
+            &[
+                SUB_CLASS_OF,
+                SUB_PROPERTY_OF,
+                DOMAIN,
+                RANGE,
+                INVERSE_OF,
+                RDF_TYPE,
+            ],
+        )
+        .into_iter();
+        let mut next = || schema.next().unwrap();
+        // (sub, super) pairs, closed under transitivity.
+        let sub_class_of = next();
+        let sub_property_of = next();
+        let domain = next();
+        let range = next();
+        let inverse_of = next();
+        let transitive = next()
+            .filter(|(_p, class)| *class == TRANSITIVE_PROPERTY)
+            .map(|(p, _class)| (p, ()));
+
+        let types = abox
+            .filter(|(_s, p, _o)| *p == RDF_TYPE)
+            .map(|(x, _p, c)| (c, x));
+        let properties = abox
+            .filter(|(_s, p, _o)| *p != RDF_TYPE)
+            .map(|(x, p, y)| (p, (x, y)));
+
+        // Goals that make every class or every property relevant.
+        let all_classes_wanted = goals
+            .filter(|goal| goal.1.is_none() || (goal.1 == Some(RDF_TYPE) && goal.2.is_none()))
+            .map(|_| ((), ()))
+            .distinct();
+        let all_properties_wanted = goals
+            .filter(|goal| goal.1.is_none())
+            .map(|_| ((), ()))
+            .distinct();
+
+        let all_classes = types
+            .map(|(c, _x)| c)
+            .concat(&sub_class_of.flat_map(|(d, c)| vec![d, c]))
+            .concat(&domain.map(|(_p, d)| d))
+            .concat(&range.map(|(_p, r)| r))
+            .distinct()
+            .map(|c| ((), c));
+        let all_properties = properties
+            .map(|(p, _)| p)
+            .concat(&sub_property_of.flat_map(|(q, p)| vec![q, p]))
+            .concat(&inverse_of.flat_map(|(q, p)| vec![q, p]))
+            .distinct()
+            .map(|p| ((), p));
+
+        // Relevant classes: the goal classes and their subclasses.
+        let goal_classes = goals
+            .flat_map(|goal| match goal {
+                (_, Some(RDF_TYPE), Some(c)) => Some(c),
+                _ => None,
+            })
+            .concat(&all_classes_wanted.join_map(&all_classes, |_, _, &c| c))
+            .distinct();
+        let magic_classes = sub_class_of
+            .map(|(d, c)| (c, d))
+            .semijoin(&goal_classes)
+            .map(|(_c, d)| d)
+            .concat(&goal_classes)
+            .distinct();
+
+        // Relevant properties: the goal properties, those typing relevant
+        // classes, and everything they are derived from.
+        let typing_properties = domain
+            .concat(&range)
+            .map(|(p, c)| (c, p))
+            .semijoin(&magic_classes)
+            .map(|(_c, p)| p);
+        let goal_properties = goals
+            .flat_map(|goal| match goal.1 {
+                Some(p) if p != RDF_TYPE => Some(p),
+                _ => None,
+            })
+            .concat(&all_properties_wanted.join_map(&all_properties, |_, _, &p| p))
+            .concat(&typing_properties)
+            .distinct();
+        // (p, q): assertions of q derive assertions of p.
+        let derived_from = sub_property_of
+            .map(|(q, p)| (p, q))
+            .concat(&inverse_of)
+            .concat(&inverse_of.map(|(q, p)| (p, q)));
+        let magic_properties = goal_properties.iterate(|magic| {
+            let derived_from = derived_from.enter(&magic.scope());
+            let goal_properties = goal_properties.enter(&magic.scope());
+            derived_from
+                .semijoin(magic)
+                .map(|(_p, q)| q)
+                .concat(&goal_properties)
+                .distinct()
+        });
+
+        // The axioms and assertions about relevant properties and classes.
+        // As those are closed under `derived_from` and `rdfs:subClassOf`,
+        // the `rdfspp` closure of them is the full closure restricted to
+        // the relevant properties and classes.
+        let by_class = |pairs: &Collection<_, (usize, usize)>, predicate| {
+            pairs
+                .map(|(s, c)| (c, s))
+                .semijoin(&magic_classes)
+                .map(move |(c, s)| (s, predicate, c))
+        };
+        let relevant_tbox = by_class(&sub_class_of, SUB_CLASS_OF)
+            .concat(&by_class(&domain, DOMAIN))
+            .concat(&by_class(&range, RANGE))
+            .concat(
+                &sub_property_of
+                    .map(|(q, p)| (p, q))
+                    .semijoin(&magic_properties)
+                    .map(|(p, q)| (q, SUB_PROPERTY_OF, p)),
+            )
+            .concat(
+                &inverse_of
+                    .semijoin(&magic_properties)
+                    .map(|(q, p)| (q, INVERSE_OF, p)),
+            )
+            .concat(
+                &transitive
+                    .semijoin(&magic_properties)
+                    .map(|(p, ())| (p, RDF_TYPE, TRANSITIVE_PROPERTY)),
+            );
+        let relevant_abox = properties
+            .semijoin(&magic_properties)
+            .map(|(p, (x, y))| (x, p, y))
+            .concat(
+                &types
+                    .semijoin(&magic_classes)
+                    .map(|(c, x)| (x, RDF_TYPE, c)),
+            );
+
+        (relevant_tbox.leave(), relevant_abox.leave())
+    });
+
+    let (_tbox, abox) = rdfspp(&relevant_tbox, &relevant_abox, outer);
+
+    outer.region_named("Goal answers", |inner| {
+        let answers = abox
+            .enter(inner)
+            .flat_map(|triple| vec![(Some(triple.1), triple), (None, triple)]);
+
+        goals
+            .enter(inner)
+            .map(|goal| (goal.1, goal))
+            .join_map(&answers, |_p, &goal, &triple| (goal, triple))
+            .filter(|(goal, triple)| matches(goal, triple))
+            .distinct()
+            .leave()
+    })
+}
+
+/// The terms of a goal pattern such as `?x rdf:type <C>`, written as three
+/// whitespace separated N-Triples terms (or encoded ids), where a term
+/// starting with `?` is a variable.
+pub type GoalTerms = (Option<String>, Option<String>, Option<String>);
+
+/// Splits `text` into the terms of a goal pattern.
+pub fn parse_goal(text: &str) -> Result<GoalTerms, String> {
+    let mut terms = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let end = term_end(rest)?;
+        terms.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    if terms.len() != 3 {
+        return Err(format!(
+            "a goal has three terms, found {} in {:?}",
+            terms.len(),
+            text
+        ));
+    }
+    let term = |term: &str| {
+        if term.starts_with('?') {
+            None
+        } else {
+            Some(term.to_string())
+        }
+    };
+    Ok((term(terms[0]), term(terms[1]), term(terms[2])))
+}
+
+pub type AnswerTrace = TraceAgent<OrdKeySpine<Answer, usize, isize>>;
+
+/// One worker's goal-directed dataflow: like `Reasoner`, but with a third
+/// input of goal patterns and only their answers as output.
+pub struct GoalReasoner {
+    time: usize,
+    tbox_input: InputSession<usize, EncodedTriple, isize>,
+    abox_input: InputSession<usize, EncodedTriple, isize>,
+    goal_input: InputSession<usize, Pattern, isize>,
+    probe: Handle<usize>,
+    answer_trace: AnswerTrace,
+}
+
+impl GoalReasoner {
+    pub fn new<A: Allocate>(worker: &mut Worker<A>) -> Self {
+        let mut probe = Handle::new();
+        let (tbox_input, abox_input, goal_input, answer_trace) =
+            worker.dataflow::<usize, _, _>(|outer| {
+                let (tbox_in, tbox) = outer.new_collection::<EncodedTriple, isize>();
+                let (abox_in, abox) = outer.new_collection::<EncodedTriple, isize>();
+                let (goal_in, goals) = outer.new_collection::<Pattern, isize>();
+
+                let answers = rdfspp_goals(&tbox, &abox, &goals, outer);
+                answers.probe_with(&mut probe);
+
+                (tbox_in, abox_in, goal_in, answers.arrange_by_self().trace)
+            });
+
+        GoalReasoner {
+            time: 0,
+            tbox_input,
+            abox_input,
+            goal_input,
+            probe,
+            answer_trace,
+        }
+    }
+
+    pub fn update_tbox(&mut self, triple: EncodedTriple, diff: isize) {
+        self.tbox_input.update(triple, diff);
+    }
+
+    pub fn update_abox(&mut self, triple: EncodedTriple, diff: isize) {
+        self.abox_input.update(triple, diff);
+    }
+
+    /// Adds (`diff > 0`) or withdraws (`diff < 0`) a goal.
+    pub fn update_goal(&mut self, goal: Pattern, diff: isize) {
+        self.goal_input.update(goal, diff);
+    }
+
+    /// Closes the current timestamp and steps the worker until the answers
+    /// reflect every update inserted so far.
+    pub fn advance<A: Allocate>(&mut self, worker: &mut Worker<A>) {
+        self.time += 1;
+        let time = self.time;
+        self.tbox_input.advance_to(time);
+        self.tbox_input.flush();
+        self.abox_input.advance_to(time);
+        self.abox_input.flush();
+        self.goal_input.advance_to(time);
+        self.goal_input.flush();
+        let probe = &self.probe;
+        worker.step_while(|| probe.less_than(&time));
+        // Only the latest answers are ever read.
+        compact(&mut self.answer_trace, time - 1);
+    }
+
+    /// This worker's share of the answers, with the goal each one answers.
+    pub fn answers(&mut self) -> Vec<Answer> {
+        accumulate(&mut self.answer_trace, self.time)
+            .into_iter()
+            .filter(|(_answer, count)| *count > 0)
+            .map(|(answer, _count)| answer)
+            .collect()
+    }
+}
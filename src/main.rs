@@ -1,45 +1,147 @@
-use differential_dataflow::trace::cursor::CursorDebug;
-use differential_dataflow::trace::TraceReader;
+use differential_reasoner::changes::{ChangeCapture, ChangeFormat};
+use differential_reasoner::dictionary::Dictionary;
+use differential_reasoner::dot::OperatorGraph;
+use differential_reasoner::error::{Error, Result};
+use differential_reasoner::graphs::{EncodedQuad, GraphMode, DEFAULT_GRAPH};
+use differential_reasoner::ingest::{Batching, Late, Listener, Watermarks};
+use differential_reasoner::load_encode_triples::{
+    load3enc, load3enc_delta, load3enc_stream, load3nt, load3nt_delta, load3nt_stream, load4nq,
+};
+use differential_reasoner::magic::{parse_goal, Answer, GoalReasoner, GoalTerms};
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::partition::Schema;
+use differential_reasoner::profiling::{merge, Profiler, RegionProfile, ReportFormat};
+use differential_reasoner::reasoner::{Pattern, Profile, Reasoner};
+use differential_reasoner::replay::{batches, replay, LatencyHistogram, Pace};
+use differential_reasoner::runtime::{check_workers, Runtime, RuntimeConfig};
+use differential_reasoner::server::{Server, ServerConfig};
+use differential_reasoner::snapshot::{Manifest, DICTIONARY};
+use differential_reasoner::trig::{load_trig, GraphTriple};
+use differential_reasoner::watch::DeltaDirectory;
 
-use differential_dataflow::input::Input;
-use differential_dataflow::operators::arrange::ArrangeBySelf;
-use differential_reasoner::load_encode_triples::load3enc;
-use differential_reasoner::materializations::*;
-use timely::dataflow::operators::probe::Handle;
-
-use clap::{App, Arg};
-use lasso::{Key, Rodeo};
+use clap::{App, AppSettings, Arg, ArgMatches};
 use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::time::Instant;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use timely::communication::Allocate;
+use timely::worker::Worker;
+
+#[derive(Clone)]
+enum Source {
+    Files {
+        t_path: String,
+        a_path: String,
+        encode: bool,
+    },
+    /// Files mixing TBox and ABox triples, split by the dataflow.
+    Mixed {
+        paths: Vec<String>,
+        encode: bool,
+    },
+    Snapshot(String, Manifest),
+}
+
+fn main() {
+    if let Err(error) = run() {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
 
-fn read_file(filename: &str) -> impl Iterator<Item = String> {
-    let file = BufReader::new(File::open(filename).unwrap());
-    file.lines().filter_map(|line| line.ok())
+fn expressivity_arg(index: usize) -> Arg<'static> {
+    Arg::new("EXPRESSIVITY")
+        .about("Sets the expressivity")
+        .possible_values(&["rdfs", "rdfspp"])
+        .required(true)
+        .index(index)
 }
 
-pub fn load3nt<'a>(filename: &str) -> impl Iterator<Item = (String, String, String)> + 'a {
-    read_file(filename).map(move |line| {
-        let mut line_clean = line;
+fn workers_arg(index: usize) -> Arg<'static> {
+    Arg::new("WORKERS")
+        .about("Sets the amount of workers")
+        .required(true)
+        .index(index)
+}
 
-        line_clean.pop();
+fn encode_arg() -> Arg<'static> {
+    Arg::new("ENCODE")
+        .about("Encodes the input; .nq files are read as N-Quads and .trig files as TriG")
+        .short('e')
+}
 
-        line_clean.pop();
+fn mapping_arg() -> Arg<'static> {
+    Arg::new("MAPPING")
+        .about("Routes the OWL schema terms of encoded input by the mapping of its dictionary")
+        .long("mapping")
+        .takes_value(true)
+        .value_name("FILE")
+        .conflicts_with("ENCODE")
+}
 
-        let mut elts = line_clean.split(' ');
+fn snapshot_arg() -> Arg<'static> {
+    Arg::new("SNAPSHOT")
+        .about("Writes the input and the materialization to a snapshot directory")
+        .long("snapshot")
+        .takes_value(true)
+        .value_name("DIR")
+}
 
-        (
-            elts.next().unwrap().parse().unwrap(),
-            elts.next().unwrap().parse().unwrap(),
-            elts.next().unwrap().parse().unwrap(),
-        )
-    })
+fn tbox_delta_arg() -> Arg<'static> {
+    Arg::new("TBOX_DELTA")
+        .about("Applies a file of `+`/`-` prefixed TBox triples after the initial materialization")
+        .long("tbox-delta")
+        .takes_value(true)
+        .multiple_occurrences(true)
+        .value_name("FILE")
 }
 
-fn main() {
+fn graphs_arg() -> Arg<'static> {
+    Arg::new("GRAPHS")
+        .about("Reasons over each named graph separately or over their union")
+        .long("graphs")
+        .possible_values(&["union", "per-graph"])
+        .default_value("union")
+}
+
+fn profile_arg() -> Arg<'static> {
+    Arg::new("PROFILE")
+        .about("Reports elapsed time, records, arrangement sizes and iterations per region")
+        .long("profile")
+        .possible_values(&["table", "json"])
+        .value_name("FORMAT")
+}
+
+fn changes_arg() -> Arg<'static> {
+    Arg::new("CHANGES")
+        .about("Writes every change to the materialization to a file, a closed timestamp at a time")
+        .long("changes")
+        .takes_value(true)
+        .value_name("FILE")
+}
+
+fn output_arg() -> Arg<'static> {
+    Arg::new("OUTPUT")
+        .about("Writes the materialization to a file, with the graph of every ABox triple")
+        .long("output")
+        .takes_value(true)
+        .value_name("FILE")
+}
+
+fn change_format_arg() -> Arg<'static> {
+    Arg::new("CHANGE_FORMAT")
+        .about("Sets the format of the changes file")
+        .long("change-format")
+        .possible_values(&["jsonl", "nt"])
+        .default_value("jsonl")
+}
+
+fn run() -> Result<()> {
     let matches = App::new("differential-reasoner")
         .version("0.2.0")
         .about("Reasons in a differential manner 😎")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::new("TBOX_PATH")
                 .about("Sets the tbox file path")
@@ -52,203 +154,1257 @@ fn main() {
                 .required(true)
                 .index(2),
         )
-        .arg(
-            Arg::new("EXPRESSIVITY")
-                .about("Sets the expressivity")
-                .required(true)
-                .index(3),
+        .arg(expressivity_arg(3))
+        .arg(workers_arg(4))
+        .arg(encode_arg())
+        .arg(snapshot_arg())
+        .arg(tbox_delta_arg())
+        .arg(graphs_arg())
+        .arg(profile_arg())
+        .arg(changes_arg())
+        .arg(change_format_arg())
+        .arg(output_arg())
+        .subcommand(
+            App::new("restore")
+                .about("Resumes from a snapshot instead of loading input files")
+                .arg(
+                    Arg::new("SNAPSHOT_PATH")
+                        .about("Sets the snapshot directory")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(expressivity_arg(2))
+                .arg(workers_arg(3))
+                .arg(snapshot_arg())
+                .arg(tbox_delta_arg())
+                .arg(graphs_arg())
+                .arg(profile_arg())
+                .arg(changes_arg())
+                .arg(change_format_arg())
+                .arg(output_arg()),
         )
-        .arg(
-            Arg::new("WORKERS")
-                .about("Sets the amount of workers")
-                .required(true)
-                .index(4),
+        .subcommand(
+            App::new("mixed")
+                .about("Loads files of both TBox and ABox triples, split by their vocabulary")
+                .arg(expressivity_arg(1))
+                .arg(workers_arg(2))
+                .arg(
+                    Arg::new("PATHS")
+                        .about("Sets the input file paths")
+                        .required(true)
+                        .multiple_values(true)
+                        .index(3),
+                )
+                .arg(encode_arg())
+                .arg(mapping_arg())
+                .arg(snapshot_arg())
+                .arg(tbox_delta_arg())
+                .arg(graphs_arg())
+                .arg(profile_arg())
+                .arg(changes_arg())
+                .arg(change_format_arg())
+                .arg(output_arg()),
         )
-        .arg(Arg::new("ENCODE").about("Encodes the input").short('e'))
-        .get_matches();
-
-    let t_path: String = matches.value_of("TBOX_PATH").unwrap().to_string();
-    let a_path: String = matches.value_of("ABOX_PATH").unwrap().to_string();
-    let expressivity: String = matches.value_of("EXPRESSIVITY").unwrap().to_string();
-    let workers: usize = matches
-        .value_of("WORKERS")
-        .unwrap()
-        .to_string()
-        .parse::<usize>()
-        .unwrap();
-    let encode: bool = matches.is_present("ENCODE");
+        .subcommand(
+            App::new("query")
+                .about("Answers goal patterns by deriving only the part of the rdfspp closure they need")
+                .arg(
+                    Arg::new("TBOX_PATH")
+                        .about("Sets the tbox file path")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("ABOX_PATH")
+                        .about("Sets the abox file path")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(workers_arg(3))
+                .arg(
+                    Arg::new("GOAL")
+                        .about("Adds a goal pattern of three terms, where `?`-prefixed terms are variables")
+                        .long("goal")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .required(true)
+                        .value_name("PATTERN"),
+                )
+                .arg(encode_arg()),
+        )
+        .subcommand(
+            App::new("history")
+                .about("Reads the materialization as of an earlier batch of updates, or its changes since one")
+                .arg(
+                    Arg::new("TBOX_PATH")
+                        .about("Sets the tbox file path")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("ABOX_PATH")
+                        .about("Sets the abox file path")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(expressivity_arg(3))
+                .arg(workers_arg(4))
+                .arg(
+                    Arg::new("BATCH")
+                        .about("Applies a file of `+`/`-` prefixed triples as the next batch; batch 0 is the initial load")
+                        .long("batch")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("AT")
+                        .about("Reads the materialization as of this batch instead of the last one")
+                        .long("at")
+                        .takes_value(true)
+                        .value_name("BATCH"),
+                )
+                .arg(
+                    Arg::new("SINCE")
+                        .about("Prints the triples derived or retracted since this batch, prefixed with `+` or `-`")
+                        .long("since")
+                        .takes_value(true)
+                        .value_name("BATCH"),
+                )
+                .arg(
+                    Arg::new("PATTERN")
+                        .about("Only reads triples matching a pattern of three terms, where `?`-prefixed terms are variables")
+                        .long("pattern")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("BOX")
+                        .about("Reads the materialized TBox or ABox")
+                        .long("box")
+                        .possible_values(&["tbox", "abox"])
+                        .default_value("abox"),
+                )
+                .arg(
+                    Arg::new("ENCODE")
+                        .about("Encodes the input, read as N-Triples, and decodes the output")
+                        .short('e'),
+                )
+                .arg(mapping_arg()),
+        )
+        .subcommand(
+            App::new("ingest")
+                .about("Materializes a live stream of abox updates read from a socket")
+                .arg(
+                    Arg::new("TBOX_PATH")
+                        .about("Sets the tbox file path")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(expressivity_arg(2))
+                .arg(workers_arg(3))
+                .arg(
+                    Arg::new("LISTEN")
+                        .about("Sets the TCP address, or `unix:<path>` socket, producers connect to")
+                        .long("listen")
+                        .takes_value(true)
+                        .value_name("ENDPOINT")
+                        .default_value("127.0.0.1:9090"),
+                )
+                .arg(
+                    Arg::new("BATCH_SIZE")
+                        .about("Closes a timestamp after this many updates")
+                        .long("batch-size")
+                        .takes_value(true)
+                        .default_value("1000"),
+                )
+                .arg(
+                    Arg::new("INTERVAL")
+                        .about("Closes a timestamp this many milliseconds after its first update")
+                        .long("interval")
+                        .takes_value(true)
+                        .value_name("MILLISECONDS")
+                        .default_value("100"),
+                )
+                .arg(
+                    Arg::new("MAX_LAG")
+                        .about("Sets how many timestamps may wait to be materialized before reading stops")
+                        .long("max-lag")
+                        .takes_value(true)
+                        .default_value("1"),
+                )
+                .arg(
+                    Arg::new("CONNECTIONS")
+                        .about("Stops after this many producers have disconnected")
+                        .long("connections")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("EVENT_TIME")
+                        .about("Reads lines preceded by the timestamp of their event, in any order")
+                        .long("event-time"),
+                )
+                .arg(
+                    Arg::new("LATENESS")
+                        .about("Sets how many timestamps the watermark trails the latest event by")
+                        .long("lateness")
+                        .takes_value(true)
+                        .default_value("0"),
+                )
+                .arg(
+                    Arg::new("LATE")
+                        .about("Drops events behind the watermark, or applies them at the earliest open timestamp")
+                        .long("late")
+                        .possible_values(&["drop", "correct"])
+                        .default_value("correct"),
+                )
+                .arg(
+                    Arg::new("ENCODE")
+                        .about("Reads N-Triples instead of encoded triples")
+                        .short('e'),
+                ),
+        )
+        .subcommand(
+            App::new("watch")
+                .about("Applies the delta files dropped into a directory as they appear")
+                .arg(
+                    Arg::new("TBOX_PATH")
+                        .about("Sets the tbox file path")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("ABOX_PATH")
+                        .about("Sets the abox file path")
+                        .required(true)
+                        .index(2),
+                )
+                .arg(expressivity_arg(3))
+                .arg(workers_arg(4))
+                .arg(
+                    Arg::new("DIR")
+                        .about("Sets the directory to watch for `+`/`-` prefixed delta files")
+                        .required(true)
+                        .index(5),
+                )
+                .arg(
+                    Arg::new("POLL")
+                        .about("Sets how many milliseconds to wait between looks at the directory")
+                        .long("poll")
+                        .takes_value(true)
+                        .value_name("MILLISECONDS")
+                        .default_value("1000"),
+                )
+                .arg(
+                    Arg::new("ENCODE")
+                        .about("Reads N-Triples, and `.nt` delta files instead of `.ntenc` ones")
+                        .short('e'),
+                )
+                .arg(mapping_arg()),
+        )
+        .subcommand(
+            App::new("replay")
+                .about("Replays a file of timestamped updates and reports the latency of every timestamp")
+                .arg(
+                    Arg::new("STREAM_PATH")
+                        .about("Sets the path of the file of `<timestamp> <+|-> triple` lines")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(expressivity_arg(2))
+                .arg(workers_arg(3))
+                .arg(
+                    Arg::new("RATE")
+                        .about("Replays the stream timestamps, in milliseconds, this many times faster than real time, instead of as fast as possible")
+                        .long("rate")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::new("HISTOGRAM")
+                        .about("Writes the latency histogram to a file instead of standard output")
+                        .long("histogram")
+                        .takes_value(true)
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("ENCODE")
+                        .about("Reads N-Triples instead of encoded triples")
+                        .short('e'),
+                )
+                .arg(mapping_arg()),
+        )
+        .subcommand(
+            App::new("dot")
+                .about("Writes the operator graph of the dataflow in Graphviz DOT format")
+                .arg(expressivity_arg(1))
+                .arg(graphs_arg())
+                .arg(
+                    Arg::new("OUTPUT")
+                        .about("Writes the graph to a file instead of standard output")
+                        .long("output")
+                        .takes_value(true)
+                        .value_name("FILE"),
+                ),
+        )
+        .subcommand(
+            App::new("serve")
+                .about("Keeps the dataflow running behind an HTTP update and query API")
+                .arg(expressivity_arg(1))
+                .arg(workers_arg(2))
+                .arg(
+                    Arg::new("ADDRESS")
+                        .about("Sets the address to listen on")
+                        .long("address")
+                        .takes_value(true)
+                        .default_value("127.0.0.1:8080"),
+                )
+                .arg(
+                    Arg::new("TBOX")
+                        .about("Loads an N-Triples tbox file on startup")
+                        .long("tbox")
+                        .takes_value(true)
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("ABOX")
+                        .about("Loads an N-Triples abox file on startup")
+                        .long("abox")
+                        .takes_value(true)
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("HISTORY")
+                        .about("Sets how many timestamps before the latest one stay readable")
+                        .long("history")
+                        .takes_value(true)
+                        .default_value("16"),
+                ),
+        )
+        .try_get_matches()
+        .unwrap_or_else(|error| error.exit());
 
-    let now = Instant::now();
+    match matches.subcommand() {
+        Some(("serve", matches)) => return serve(matches),
+        Some(("query", matches)) => return query(matches),
+        Some(("history", matches)) => return history(matches),
+        Some(("ingest", matches)) => return ingest(matches),
+        Some(("watch", matches)) => return watch(matches),
+        Some(("replay", matches)) => return replay_stream(matches),
+        Some(("dot", matches)) => return dot(matches),
+        _ => {}
+    }
+
+    let (source, matches) = match matches.subcommand() {
+        Some(("restore", matches)) => {
+            let dir = matches.value_of("SNAPSHOT_PATH").unwrap().to_string();
+            let manifest = Manifest::read(&dir)?;
+            (Source::Snapshot(dir, manifest), matches)
+        }
+        Some(("mixed", matches)) => (
+            Source::Mixed {
+                paths: matches
+                    .values_of("PATHS")
+                    .unwrap()
+                    .map(|path| path.to_string())
+                    .collect(),
+                encode: matches.is_present("ENCODE"),
+            },
+            matches,
+        ),
+        _ => (
+            Source::Files {
+                t_path: matches.value_of("TBOX_PATH").unwrap().to_string(),
+                a_path: matches.value_of("ABOX_PATH").unwrap().to_string(),
+                encode: matches.is_present("ENCODE"),
+            },
+            &matches,
+        ),
+    };
 
-    let summaries = timely::execute(timely::Config::process(workers), move |worker| {
-        let mut tbox_probe = Handle::new();
-        let mut abox_probe = Handle::new();
+    let profile: Profile = matches.value_of("EXPRESSIVITY").unwrap().parse()?;
+    let graphs: GraphMode = matches.value_of("GRAPHS").unwrap().parse()?;
+    let report = match matches.value_of("PROFILE") {
+        Some(format) => Some(format.parse::<ReportFormat>()?),
+        None => None,
+    };
+    let workers = workers(matches)?;
+    let change_format: ChangeFormat = matches.value_of("CHANGE_FORMAT").unwrap().parse()?;
+    let changes = matches.value_of("CHANGES").map(|path| path.to_string());
+    let snapshot = matches.value_of("SNAPSHOT").map(|dir| dir.to_string());
+    let output = matches.value_of("OUTPUT").map(|path| path.to_string());
+    let schema = match &source {
+        // `load_mixed` resolves the schema through a fresh dictionary too.
+        Source::Mixed { encode: true, .. } => Schema::resolve(&mut Dictionary::new()),
+        Source::Mixed { .. } => schema(matches, None)?,
+        Source::Files { .. } | Source::Snapshot(..) => Schema::default(),
+    };
+    let tbox_deltas: Vec<String> = matches
+        .values_of("TBOX_DELTA")
+        .map(|paths| paths.map(|path| path.to_string()).collect())
+        .unwrap_or_default();
 
-        let (mut tbox_input_stream, mut abox_input_stream, mut tbox_trace, mut abox_trace) = worker
-            .dataflow::<usize, _, _>(|outer| {
-                let (mut _abox_in, abox) = outer.new_collection::<(usize, usize, usize), isize>();
-                let (mut _tbox_in, tbox) = outer.new_collection::<(usize, usize, usize), isize>();
+    let time = match &source {
+        Source::Snapshot(dir, manifest) => {
+            manifest.check(profile)?;
+            println!("Restoring snapshot {} at time {}", dir, manifest.time);
+            manifest.time
+        }
+        Source::Files { .. } | Source::Mixed { .. } => 1,
+    };
+
+    let now = Instant::now();
 
-                let (tbox, abox) = match &expressivity[..] {
-                    "rdfs" => rdfs(&tbox, &abox, outer),
-                    _ => rdfspp(&tbox, &abox, outer),
+    let steps = {
+        let source = source.clone();
+        let snapshot = snapshot.clone();
+        let tbox_deltas = tbox_deltas.clone();
+        let changes = changes.clone();
+        let output = output.is_some();
+        timely::execute(
+            timely::Config::process(workers),
+            move |worker| -> Result<Share> {
+                let profiler = report.map(|_| Profiler::install(worker));
+                let mut reasoner = Reasoner::with_schema(worker, profile, graphs, schema.clone());
+                let mut capture = changes
+                    .as_ref()
+                    .map(|_| ChangeCapture::install(worker, &mut reasoner));
+                let mut sink = match &changes {
+                    Some(path) if 0 == worker.index() => Some(ChangeSink {
+                        format: change_format,
+                        path: path.clone(),
+                        writer: BufWriter::new(File::create(path).map_err(|source| Error::Io {
+                            path: path.clone(),
+                            source,
+                        })?),
+                    }),
+                    _ => None,
                 };
+                let mut dictionary = None;
 
-                tbox.probe_with(&mut tbox_probe);
-                abox.probe_with(&mut abox_probe);
+                if 0 == worker.index() {
+                    dictionary = match &source {
+                        Source::Files {
+                            t_path,
+                            a_path,
+                            encode: true,
+                        } => Some(load_and_intern(t_path, a_path, &mut reasoner)?),
+                        Source::Files { t_path, a_path, .. } => {
+                            load_encoded(t_path, a_path, &mut reasoner)?;
+                            None
+                        }
+                        Source::Mixed { paths, encode } => {
+                            load_mixed(paths, *encode, &mut reasoner)?
+                        }
+                        Source::Snapshot(dir, manifest) => {
+                            reasoner.restore(dir, manifest)?;
+                            let path = Path::new(dir).join(DICTIONARY);
+                            if path.exists() {
+                                Some(Dictionary::load(&path.display().to_string())?)
+                            } else {
+                                None
+                            }
+                        }
+                    };
+                }
 
-                let tbox_arr = tbox.arrange_by_self();
-                let abox_arr = abox.arrange_by_self();
+                reasoner.advance_to(worker, time);
+                emit_changes(
+                    worker,
+                    &reasoner,
+                    capture.as_mut(),
+                    sink.as_mut(),
+                    dictionary.as_ref(),
+                )?;
+                let mut steps = vec![(reasoner.tbox().len(), reasoner.abox().len())];
 
-                (_tbox_in, _abox_in, tbox_arr.trace, abox_arr.trace)
-            });
+                for path in tbox_deltas.iter() {
+                    if 0 == worker.index() {
+                        load_tbox_delta(path, dictionary.as_mut(), &mut reasoner)?;
+                    }
+                    reasoner.advance(worker);
+                    emit_changes(
+                        worker,
+                        &reasoner,
+                        capture.as_mut(),
+                        sink.as_mut(),
+                        dictionary.as_ref(),
+                    )?;
+                    steps.push((reasoner.tbox().len(), reasoner.abox().len()));
+                }
 
-        if 0 == worker.index() {
-            if let true = encode {
-                let abox = load3nt(&a_path);
-                println!("A-box location: {}", &a_path);
-                let tbox = load3nt(&t_path);
-                println!("T-box location: {}", &t_path);
-
-                let mut grand_ole_pry = Rodeo::default();
-
-                let rdfsco: &str = "<http://www.w3.org/2000/01/rdf-schema#subClassOf>";
-                let rdfspo: &str = "<http://www.w3.org/2000/01/rdf-schema#subPropertyOf>";
-                let rdfsd: &str = "<http://www.w3.org/2000/01/rdf-schema#domain>";
-                let rdfsr: &str = "<http://www.w3.org/2000/01/rdf-schema#range>";
-                let rdft: &str = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#type>";
-                let owltr: &str = "<http://www.w3.org/2002/07/owl#TransitiveProperty>";
-                let owlio: &str = "<http://www.w3.org/2002/07/owl#inverseOf>";
-
-                let _owlthing: &str = "<http://www.w3.org/2002/07/owl#Thing>";
-                let _rdfcomment: &str = "<http://www.w3.org/2000/01/rdf-schema#comment>";
-                let _rdfrest: &str = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#rest>";
-                let _rdffirst: &str = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#first>";
-                let _owlmqc: &str = "<http://www.w3.org/2002/07/owl#maxQualifiedCardinality>";
-                let _owlsvf: &str = "<http://www.w3.org/2002/07/owl#someValuesFrom>";
-                let _owlec: &str = "<http://www.w3.org/2002/07/owl#equivalentClass>";
-                let _owlito: &str = "<http://www.w3.org/2002/07/owl#intersectionOf>";
-                let _owlm: &str = "<http://www.w3.org/2002/07/owl#members>";
-                let _owlep: &str = "<http://www.w3.org/2002/07/owl#equivalentProperty>";
-                let _owlop: &str = "<http://www.w3.org/2002/07/owl#onProperty>";
-                let _owlpca: &str = "<http://www.w3.org/2002/07/owl#propertyChainAxiom>";
-                let _owldw: &str = "<http://www.w3.org/2002/07/owl#disjointWith>";
-                let _owlpdw: &str = "<http://www.w3.org/2002/07/owl#propertyDisjointWith>";
-                let _owluo: &str = "<http://www.w3.org/2002/07/owl#unionOf>";
-                let _owll: &str = "<http://www.w3.org/2000/01/rdf-schema#label>";
-                let _owlhk: &str = "<http://www.w3.org/2002/07/owl#hasKey>";
-                let _owlavf: &str = "<http://www.w3.org/2002/07/owl#allValuesFrom>";
-                let _owlco: &str = "<http://www.w3.org/2002/07/owl#complementOf>";
-                let _owloc: &str = "<http://www.w3.org/2002/07/owl#onClass>";
-                let _owldm: &str = "<http://www.w3.org/2002/07/owl#distinctMembers>";
-                let _owlfp: &str = "<http://www.w3.org/2002/07/owl#FunctionalProperty>";
-                let _owlni: &str = "<http://www.w3.org/2002/07/owl#NamedIndividual>";
-                let _owlop: &str = "<http://www.w3.org/2002/07/owl#ObjectProperty>";
-                let _rdfn: &str = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#nil>";
-                let _owlc: &str = "<http://www.w3.org/2002/07/owl#Class>";
-                let _xmlonenni: &str =
-                    "\"1\"^^<http://www.w3.org/2001/XMLSchema#nonNegativeInteger>";
-                let _xmlzeronni: &str =
-                    "\"0\"^^<http://www.w3.org/2001/XMLSchema#nonNegativeInteger>";
-                let _owladc: &str = "<http://www.w3.org/2002/07/owl#AllDisjointClasses>";
-                let _owlr: &str = "<http://www.w3.org/2002/07/owl#Restriction>";
-                let _owldp: &str = "<http://www.w3.org/2002/07/owl#DatatypeProperty>";
-                let _rdfl: &str = "<http://www.w3.org/2000/01/rdf-schema#Literal>";
-                let _owlo: &str = "<http://www.w3.org/2002/07/owl#Ontology>";
-                let _owlap: &str = "<http://www.w3.org/2002/07/owl#AsymmetricProperty>";
-                let _owlsp: &str = "<http://www.w3.org/2002/07/owl#SymmetricProperty>";
-                let _owlip: &str = "<http://www.w3.org/2002/07/owl#IrreflexiveProperty>";
-                let _owlad: &str = "<http://www.w3.org/2002/07/owl#AllDifferent>";
-                let _owlifp: &str = "<http://www.w3.org/2002/07/owl#InverseFunctionalProperty>";
-
-                grand_ole_pry.get_or_intern(rdfsco);
-                grand_ole_pry.get_or_intern(rdfspo);
-                grand_ole_pry.get_or_intern(rdfsd);
-                grand_ole_pry.get_or_intern(rdfsr);
-                grand_ole_pry.get_or_intern(rdft);
-                grand_ole_pry.get_or_intern(owltr);
-                grand_ole_pry.get_or_intern(owlio);
-
-                tbox.for_each(|triple| {
-                    let s = &triple.0[..];
-                    let p = &triple.1[..];
-                    let o = &triple.2[..];
-
-                    let key_s = grand_ole_pry.get_or_intern(s);
-                    let key_p = grand_ole_pry.get_or_intern(p);
-                    let key_o = grand_ole_pry.get_or_intern(o);
-
-                    let key_s_int = key_s.into_usize();
-                    let key_p_int = key_p.into_usize();
-                    let key_o_int = key_o.into_usize();
-
-                    tbox_input_stream.insert((key_s_int, key_p_int, key_o_int));
-                });
-                abox.for_each(|triple| {
-                    let s = &triple.0[..];
-                    let p = &triple.1[..];
-                    let o = &triple.2[..];
-
-                    let key_s = grand_ole_pry.get_or_intern(s);
-                    let key_p = grand_ole_pry.get_or_intern(p);
-                    let key_o = grand_ole_pry.get_or_intern(o);
-
-                    let key_s_int = key_s.into_usize();
-                    let key_p_int = key_p.into_usize();
-                    let key_o_int = key_o.into_usize();
-
-                    abox_input_stream.insert((key_s_int, key_p_int, key_o_int));
-                });
-            } else {
-                {
-                    let tbox = load3enc(&t_path);
-                    let abox = load3enc(&a_path);
-                    tbox.for_each(|triple| {
-                        tbox_input_stream.insert((triple.0, triple.1, triple.2));
-                    });
-                    abox.for_each(|triple| {
-                        abox_input_stream.insert((triple.0, triple.1, triple.2));
-                    });
+                if let Some(dir) = &snapshot {
+                    if let Some(dictionary) = &dictionary {
+                        std::fs::create_dir_all(dir).map_err(|source| Error::Io {
+                            path: dir.clone(),
+                            source,
+                        })?;
+                        dictionary.save(&Path::new(dir).join(DICTIONARY).display().to_string())?;
+                    }
+                    reasoner.snapshot(dir, worker.index())?;
                 }
-            };
-        }
-        tbox_input_stream.advance_to(1);
-        tbox_input_stream.flush();
-        worker.step();
-        abox_input_stream.advance_to(1);
-        abox_input_stream.flush();
-        worker.step();
-        worker.step_while(|| abox_probe.less_than(abox_input_stream.time()));
-        let (mut tbox_cursor, tbox_storage) = tbox_trace.cursor();
-        let (mut abox_cursor, abox_storage) = abox_trace.cursor();
-        (
-            tbox_cursor.to_vec(&tbox_storage),
-            abox_cursor.to_vec(&abox_storage),
+
+                let profile = profiler.map(|profiler| profiler.report(worker));
+                let quads = if output {
+                    materialized_quads(&mut reasoner)
+                } else {
+                    Vec::new()
+                };
+                Ok(Share {
+                    steps,
+                    profile,
+                    quads,
+                    dictionary,
+                })
+            },
         )
-    })
-    .unwrap()
-    .join();
+        .map_err(Error::Worker)?
+        .join()
+    };
 
-    let mut abox_triples = 0;
-    let mut tbox_triples = 0;
+    let mut sizes = vec![(0, 0); tbox_deltas.len() + 1];
+    let mut profiles = Vec::new();
+    let mut quads = Vec::new();
+    let mut dictionary = None;
 
-    for worker in summaries.into_iter() {
-        let (tbox, abox) = worker.unwrap();
-        tbox_triples += tbox.len();
-        abox_triples += abox.len();
+    for worker in steps.into_iter() {
+        let share = worker.map_err(Error::Worker)??;
+        for (size, (tbox, abox)) in sizes.iter_mut().zip(share.steps) {
+            size.0 += tbox;
+            size.1 += abox;
+        }
+        profiles.extend(share.profile);
+        quads.extend(share.quads);
+        dictionary = dictionary.or(share.dictionary);
     }
 
+    let (tbox_triples, abox_triples) = sizes[0];
+
     println!(
         "Full tbox size {:?} \nFull abox size {:?}",
         tbox_triples, abox_triples
     );
 
-    if let true = encode {
-        println!(
+    match &source {
+        Source::Files { encode: true, .. } | Source::Mixed { encode: true, .. } => println!(
             "loading+interning+materialization time: {:?}",
             now.elapsed()
-        )
+        ),
+        Source::Files { .. } | Source::Mixed { .. } => {
+            println!("loading+materialization time: {:?}", now.elapsed())
+        }
+        Source::Snapshot(_, manifest) => {
+            println!("restore+materialization time: {:?}", now.elapsed());
+            if (manifest.tbox_size, manifest.abox_size) != (tbox_triples, abox_triples) {
+                return Err(Error::Snapshot(format!(
+                    "restored materialization has {} tbox and {} abox triples, expected {} and {}",
+                    tbox_triples, abox_triples, manifest.tbox_size, manifest.abox_size
+                )));
+            }
+        }
+    }
+
+    for (path, (tbox, abox)) in tbox_deltas.iter().zip(&sizes[1..]) {
+        println!(
+            "After tbox delta {}: tbox size {:?}, abox size {:?}",
+            path, tbox, abox
+        );
+    }
+
+    if let Some(format) = report {
+        print!("{}", format.render(&merge(profiles)));
+    }
+
+    if let Some(path) = &output {
+        quads.sort_unstable();
+        write_quads(path, &quads, dictionary.as_ref())?;
+        println!("Materialization written to {}", path);
+    }
+
+    if let Some(dir) = &snapshot {
+        let (tbox_triples, abox_triples) = sizes[tbox_deltas.len()];
+        let time = time + tbox_deltas.len();
+        Manifest::new(profile, time, workers, tbox_triples, abox_triples).write(dir)?;
+        println!("Snapshot written to {}", dir);
+    }
+
+    Ok(())
+}
+
+/// What a worker of `run` hands back: the sizes of its share of the
+/// materialization after every step, its profile, its share of the final
+/// materialization if it is written out, and worker 0's dictionary.
+struct Share {
+    steps: Vec<(usize, usize)>,
+    profile: Option<Vec<RegionProfile>>,
+    quads: Vec<EncodedQuad>,
+    dictionary: Option<Dictionary>,
+}
+
+/// This worker's share of the materialized TBox, in the default graph, and
+/// ABox, in the graphs of its triples.
+fn materialized_quads(reasoner: &mut Reasoner) -> Vec<EncodedQuad> {
+    let mut quads: Vec<EncodedQuad> = reasoner
+        .tbox()
+        .into_iter()
+        .map(|(s, p, o)| (s, p, o, DEFAULT_GRAPH))
+        .collect();
+    quads.extend(reasoner.abox_quads());
+    quads
+}
+
+/// Writes `quads` one per line: as N-Quads decoded with `dictionary` if the
+/// input was encoded, as ids otherwise. Triples of the default graph have no
+/// graph term.
+fn write_quads(path: &str, quads: &[EncodedQuad], dictionary: Option<&Dictionary>) -> Result<()> {
+    let io_error = |source| Error::Io {
+        path: path.to_string(),
+        source,
+    };
+    let mut writer = BufWriter::new(File::create(path).map_err(io_error)?);
+    let term = |id: usize| match dictionary {
+        Some(dictionary) => dictionary.decode(id).unwrap_or("_:unknown").to_string(),
+        None => id.to_string(),
+    };
+    for &(s, p, o, graph) in quads {
+        write!(writer, "{} {} {}", term(s), term(p), term(o)).map_err(io_error)?;
+        if graph != DEFAULT_GRAPH {
+            write!(writer, " {}", term(graph)).map_err(io_error)?;
+        }
+        if dictionary.is_some() {
+            write!(writer, " .").map_err(io_error)?;
+        }
+        writeln!(writer).map_err(io_error)?;
+    }
+    writer.flush().map_err(io_error)
+}
+
+/// The file changes to the materialization are written to.
+struct ChangeSink {
+    format: ChangeFormat,
+    path: String,
+    writer: BufWriter<File>,
+}
+
+/// Writes the changes of the timestamps `reasoner` has closed to `sink`,
+/// which only worker 0 has.
+fn emit_changes<A: Allocate>(
+    worker: &mut Worker<A>,
+    reasoner: &Reasoner,
+    capture: Option<&mut ChangeCapture>,
+    sink: Option<&mut ChangeSink>,
+    dictionary: Option<&Dictionary>,
+) -> Result<()> {
+    if let Some(capture) = capture {
+        let changes = capture.closed(worker, reasoner.time());
+        if let Some(sink) = sink {
+            sink.format
+                .write(&mut sink.writer, &changes, dictionary)
+                .map_err(|source| Error::Io {
+                    path: sink.path.clone(),
+                    source,
+                })?;
+        }
+    }
+    Ok(())
+}
+
+fn workers(matches: &ArgMatches) -> Result<usize> {
+    // A count that is not a number fails the check as no workers at all.
+    check_workers(matches.value_of_t("WORKERS").unwrap_or(0))
+}
+
+/// The schema mixed input is routed by: resolved through `dictionary` when
+/// the input is encoded with it, or through the dictionary of `--mapping`.
+/// Without either, encoded input only shares the fixed vocabulary.
+fn schema(matches: &ArgMatches, dictionary: Option<&mut Dictionary>) -> Result<Schema> {
+    match (dictionary, matches.value_of("MAPPING")) {
+        (Some(dictionary), _) => Ok(Schema::resolve(dictionary)),
+        (None, Some(path)) => Ok(Schema::resolve(&mut Dictionary::load(path)?)),
+        (None, None) => Ok(Schema::default()),
+    }
+}
+
+fn serve(matches: &ArgMatches) -> Result<()> {
+    let server = Server::start(ServerConfig {
+        address: matches.value_of("ADDRESS").unwrap().to_string(),
+        profile: matches.value_of("EXPRESSIVITY").unwrap().parse()?,
+        workers: workers(matches)?,
+        tbox_path: matches.value_of("TBOX").map(|path| path.to_string()),
+        abox_path: matches.value_of("ABOX").map(|path| path.to_string()),
+        history: matches
+            .value_of_t("HISTORY")
+            .map_err(|_| Error::Config("HISTORY must be a non-negative integer".to_string()))?,
+    })?;
+    println!("Listening on http://{}", server.local_addr());
+    server.wait()
+}
+
+fn dot(matches: &ArgMatches) -> Result<()> {
+    let profile: Profile = matches.value_of("EXPRESSIVITY").unwrap().parse()?;
+    let graphs: GraphMode = matches.value_of("GRAPHS").unwrap().parse()?;
+
+    // One worker builds the same dataflow as any other.
+    let dot = timely::execute(timely::Config::thread(), move |worker| {
+        let graph = OperatorGraph::install(worker);
+        let _reasoner = Reasoner::with_graphs(worker, profile, graphs);
+        graph.render(worker)
+    })
+    .map_err(Error::Worker)?
+    .join()
+    .pop()
+    .unwrap()
+    .map_err(Error::Worker)?;
+
+    match matches.value_of("OUTPUT") {
+        Some(path) => std::fs::write(path, dot).map_err(|source| Error::Io {
+            path: path.to_string(),
+            source,
+        }),
+        None => {
+            print!("{}", dot);
+            Ok(())
+        }
+    }
+}
+
+fn query(matches: &ArgMatches) -> Result<()> {
+    let t_path = matches.value_of("TBOX_PATH").unwrap().to_string();
+    let a_path = matches.value_of("ABOX_PATH").unwrap().to_string();
+    let workers = workers(matches)?;
+    let encode = matches.is_present("ENCODE");
+    let texts: Vec<String> = matches
+        .values_of("GOAL")
+        .unwrap()
+        .map(|goal| goal.to_string())
+        .collect();
+    let goals = texts
+        .iter()
+        .map(|text| parse_goal(text).map_err(Error::Config))
+        .collect::<Result<Vec<GoalTerms>>>()?;
+
+    let now = Instant::now();
+
+    let shares = timely::execute(
+        timely::Config::process(workers),
+        move |worker| -> Result<(Vec<Option<Pattern>>, Vec<Answer>)> {
+            let mut reasoner = GoalReasoner::new(worker);
+            let mut resolved = Vec::new();
+
+            if 0 == worker.index() {
+                let mut dictionary = if encode {
+                    Some(Dictionary::new())
+                } else {
+                    None
+                };
+                read_triples(&t_path, dictionary.as_mut())?
+                    .into_iter()
+                    .for_each(|triple| reasoner.update_tbox(triple, 1));
+                read_triples(&a_path, dictionary.as_mut())?
+                    .into_iter()
+                    .for_each(|triple| reasoner.update_abox(triple, 1));
+
+                for goal in goals.iter() {
+                    let pattern = resolve(goal, dictionary.as_ref())?;
+                    if let Some(pattern) = pattern {
+                        reasoner.update_goal(pattern, 1);
+                    }
+                    resolved.push(pattern);
+                }
+            }
+
+            reasoner.advance(worker);
+            Ok((resolved, reasoner.answers()))
+        },
+    )
+    .map_err(Error::Worker)?
+    .join();
+
+    let mut resolved = Vec::new();
+    let mut answers = Vec::new();
+    for share in shares {
+        let (patterns, share) = share.map_err(Error::Worker)??;
+        resolved.extend(patterns);
+        answers.extend(share);
+    }
+
+    for (text, pattern) in texts.iter().zip(resolved) {
+        let count = answers
+            .iter()
+            .filter(|(goal, _triple)| Some(*goal) == pattern)
+            .count();
+        println!("Goal {}: {} answers", text, count);
+    }
+    println!(
+        "loading+goal-directed materialization time: {:?}",
+        now.elapsed()
+    );
+
+    Ok(())
+}
+
+fn history(matches: &ArgMatches) -> Result<()> {
+    let profile: Profile = matches.value_of("EXPRESSIVITY").unwrap().parse()?;
+    let workers = workers(matches)?;
+    let tbox_only = matches.value_of("BOX") == Some("tbox");
+    let paths: Vec<String> = matches
+        .values_of("BATCH")
+        .map(|paths| paths.map(|path| path.to_string()).collect())
+        .unwrap_or_default();
+    let batch = |name: &str| -> Result<Option<usize>> {
+        match matches.value_of(name) {
+            None => Ok(None),
+            Some(value) => match value.parse() {
+                Ok(batch) if batch <= paths.len() => Ok(Some(batch)),
+                _ => Err(Error::Config(format!(
+                    "{} must be a batch number between 0 and {}",
+                    name,
+                    paths.len()
+                ))),
+            },
+        }
+    };
+    let at = batch("AT")?.unwrap_or_else(|| paths.len());
+    let since = batch("SINCE")?;
+
+    // The input is encoded up front, so that the output can be decoded once
+    // all workers are done.
+    let mut dictionary = if matches.is_present("ENCODE") {
+        Some(Dictionary::new())
     } else {
-        println!("loading+materialization time: {:?}", now.elapsed())
+        None
+    };
+    let schema = schema(matches, dictionary.as_mut())?;
+    let tbox = read_triples(matches.value_of("TBOX_PATH").unwrap(), dictionary.as_mut())?;
+    let abox = read_triples(matches.value_of("ABOX_PATH").unwrap(), dictionary.as_mut())?;
+    let batches = paths
+        .iter()
+        .map(|path| read_delta(path, dictionary.as_mut()))
+        .collect::<Result<Vec<_>>>()?;
+    let pattern = match matches.value_of("PATTERN") {
+        Some(text) => resolve(
+            &parse_goal(text).map_err(Error::Config)?,
+            dictionary.as_ref(),
+        )?,
+        None => Some((None, None, None)),
+    };
+    let pattern = match pattern {
+        Some(pattern) => pattern,
+        None => return Ok(()),
+    };
+
+    let shares = timely::execute(timely::Config::process(workers), move |worker| {
+        let mut reasoner = Reasoner::with_schema(worker, profile, GraphMode::Union, schema.clone());
+        // Batch `i` is timestamp `i`; the earliest one read has to stay exact.
+        reasoner.hold(Some(since.map_or(at, |since| since.min(at))));
+        if 0 == worker.index() {
+            tbox.iter().for_each(|triple| reasoner.insert_tbox(*triple));
+            abox.iter().for_each(|triple| reasoner.insert_abox(*triple));
+        }
+        reasoner.advance(worker);
+        for batch in batches.iter() {
+            if 0 == worker.index() {
+                for &(triple, diff) in batch.iter() {
+                    reasoner.update(triple, diff);
+                }
+            }
+            reasoner.advance(worker);
+        }
+
+        match (since, tbox_only) {
+            (Some(since), true) => reasoner.diff_tbox(&pattern, since, at),
+            (Some(since), false) => reasoner.diff_abox(&pattern, since, at),
+            (None, true) => reasoner.query_tbox(&pattern, at).map(present),
+            (None, false) => reasoner.query_abox(&pattern, at).map(present),
+        }
+    })
+    .map_err(Error::Worker)?
+    .join();
+
+    let mut triples = Vec::new();
+    for share in shares {
+        triples.extend(share.map_err(Error::Worker)??);
+    }
+    triples.sort_unstable();
+
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+    for ((s, p, o), diff) in triples {
+        let sign = match (since, diff > 0) {
+            (None, _) => "",
+            (Some(_), true) => "+ ",
+            (Some(_), false) => "- ",
+        };
+        let line = match &dictionary {
+            Some(dictionary) => format!(
+                "{}{} {} {} .",
+                sign,
+                dictionary.decode(s).unwrap_or("_:unknown"),
+                dictionary.decode(p).unwrap_or("_:unknown"),
+                dictionary.decode(o).unwrap_or("_:unknown")
+            ),
+            None => format!("{}{} {} {}", sign, s, p, o),
+        };
+        writeln!(out, "{}", line).map_err(|source| Error::Io {
+            path: "standard output".to_string(),
+            source,
+        })?;
+    }
+    Ok(())
+}
+
+fn ingest(matches: &ArgMatches) -> Result<()> {
+    let t_path = matches.value_of("TBOX_PATH").unwrap();
+    let profile: Profile = matches.value_of("EXPRESSIVITY").unwrap().parse()?;
+    let workers = workers(matches)?;
+    let count = |name: &str| -> Result<usize> {
+        matches
+            .value_of_t(name)
+            .map_err(|_| Error::Config(format!("{} must be a non-negative integer", name)))
+    };
+    let batching = Batching {
+        size: count("BATCH_SIZE")?.max(1),
+        interval: Some(Duration::from_millis(count("INTERVAL")? as u64)),
+        max_lag: count("MAX_LAG")?,
+    };
+    let connections = match matches.value_of("CONNECTIONS") {
+        Some(_) => Some(count("CONNECTIONS")?),
+        None => None,
+    };
+
+    let mut dictionary = if matches.is_present("ENCODE") {
+        Some(Dictionary::new())
+    } else {
+        None
+    };
+    let tbox = read_triples(t_path, dictionary.as_mut())?;
+    let listener = Listener::bind(&matches.value_of("LISTEN").unwrap().parse()?)?;
+    println!("Listening on {}", listener.endpoint());
+    let (feed, inputs) = if matches.is_present("EVENT_TIME") {
+        let watermarks = Watermarks {
+            lateness: count("LATENESS")?,
+            late: matches.value_of("LATE").unwrap().parse::<Late>()?,
+            size: batching.size,
+            max_lag: batching.max_lag,
+        };
+        listener.start_events(watermarks, workers, dictionary, connections)
+    } else {
+        listener.start(batching, workers, dictionary, connections)
+    };
+    let inputs = Arc::new(Mutex::new(inputs.into_iter().map(Some).collect::<Vec<_>>()));
+
+    let now = Instant::now();
+    let shares = timely::execute(timely::Config::process(workers), move |worker| {
+        let input = inputs.lock().unwrap()[worker.index()]
+            .take()
+            .expect("worker started twice");
+        let mut reasoner = Reasoner::new(worker, profile);
+        if 0 == worker.index() {
+            tbox.iter().for_each(|triple| reasoner.insert_tbox(*triple));
+        }
+        reasoner.advance(worker);
+        let followed = input.follow(worker, &mut reasoner);
+        (followed, reasoner.tbox().len(), reasoner.abox().len())
+    })
+    .map_err(Error::Worker)?
+    .join();
+    let fed = feed.join()?;
+    for error in &fed.skipped {
+        eprintln!("skipping {}", error);
+    }
+
+    let (mut batches, mut late, mut tbox_triples, mut abox_triples) = (0, 0, 0, 0);
+    for share in shares {
+        let (followed, tbox, abox) = share.map_err(Error::Worker)?;
+        batches = followed.batches;
+        late += followed.late;
+        tbox_triples += tbox;
+        abox_triples += abox;
+    }
+    println!(
+        "Ingested {} batches in {:?}\nFull tbox size {:?} \nFull abox size {:?}",
+        batches,
+        now.elapsed(),
+        tbox_triples,
+        abox_triples
+    );
+    if late > 0 {
+        println!("Late events: {}", late);
+    }
+    Ok(())
+}
+
+fn watch(matches: &ArgMatches) -> Result<()> {
+    let profile: Profile = matches.value_of("EXPRESSIVITY").unwrap().parse()?;
+    let workers = workers(matches)?;
+    let dir = matches.value_of("DIR").unwrap();
+    let poll: u64 = matches
+        .value_of_t("POLL")
+        .map_err(|_| Error::Config("POLL must be a non-negative integer".to_string()))?;
+
+    let dictionary = if matches.is_present("ENCODE") {
+        Some(Dictionary::new())
+    } else {
+        None
+    };
+    let mut deltas = DeltaDirectory::open(dir, dictionary)?;
+    let config = RuntimeConfig {
+        profile,
+        workers,
+        history: 0,
+        schema: schema(matches, deltas.dictionary_mut())?,
+    };
+    let runtime = match deltas.snapshot() {
+        Some(snapshot) => {
+            println!("Resuming from snapshot {}", snapshot.display());
+            Runtime::restore(config, &snapshot.display().to_string())?
+        }
+        None => {
+            let tbox = read_triples(
+                matches.value_of("TBOX_PATH").unwrap(),
+                deltas.dictionary_mut(),
+            )?;
+            let abox = read_triples(
+                matches.value_of("ABOX_PATH").unwrap(),
+                deltas.dictionary_mut(),
+            )?;
+            let runtime = Runtime::start(config)?;
+            let reasoner = runtime.handle();
+            reasoner.wait(reasoner.submit(present(tbox), present(abox)))?;
+            runtime
+        }
+    };
+    let reasoner = runtime.handle();
+
+    let replayed = deltas.recover(&reasoner)?;
+    if replayed > 0 {
+        println!("Replayed {} delta files applied before", replayed);
+        deltas.checkpoint(&reasoner)?;
+    }
+    println!("Watching {} for .{} delta files", dir, deltas.extension());
+    let all: Pattern = (None, None, None);
+    loop {
+        let applied = deltas.poll(&reasoner)?;
+        for (name, time) in applied.iter() {
+            println!("Applied {} at timestamp {}", name, time);
+        }
+        // Only the latest timestamp is still readable.
+        if let Some((_name, time)) = applied.last() {
+            println!(
+                "Full tbox size {:?} \nFull abox size {:?}",
+                reasoner.query(true, all, *time)?.len(),
+                reasoner.query(false, all, *time)?.len()
+            );
+            deltas.checkpoint(&reasoner)?;
+        }
+        std::thread::sleep(Duration::from_millis(poll));
+    }
+}
+
+fn replay_stream(matches: &ArgMatches) -> Result<()> {
+    let path = matches.value_of("STREAM_PATH").unwrap();
+    let profile: Profile = matches.value_of("EXPRESSIVITY").unwrap().parse()?;
+    let workers = workers(matches)?;
+    let pace = match matches.value_of("RATE") {
+        Some(_) => match matches.value_of_t::<f64>("RATE") {
+            Ok(rate) if rate > 0.0 => Pace::Scaled(rate),
+            _ => return Err(Error::Config("RATE must be a positive number".to_string())),
+        },
+        None => Pace::AsFastAsPossible,
+    };
+
+    let mut dictionary = if matches.is_present("ENCODE") {
+        Some(Dictionary::new())
+    } else {
+        None
+    };
+    let schema = schema(matches, dictionary.as_mut())?;
+    let stream = match dictionary.as_mut() {
+        Some(dictionary) => batches(
+            path,
+            load3nt_stream(path)?.map(|update| {
+                let (line, time, triple, diff) = update?;
+                Ok((line, time, dictionary.encode_triple(&triple), diff))
+            }),
+        )?,
+        None => batches(path, load3enc_stream(path)?)?,
+    };
+    let timestamps = stream.len();
+    let stream = Arc::new(stream);
+
+    let start = Instant::now();
+    let shares = timely::execute(timely::Config::process(workers), move |worker| {
+        let mut reasoner = Reasoner::with_schema(worker, profile, GraphMode::Union, schema.clone());
+        let histogram = replay(worker, &mut reasoner, &stream, pace, start);
+        (histogram, reasoner.tbox().len(), reasoner.abox().len())
+    })
+    .map_err(Error::Worker)?
+    .join();
+    let elapsed = start.elapsed();
+
+    let (mut histogram, mut tbox_triples, mut abox_triples) = (LatencyHistogram::default(), 0, 0);
+    for share in shares {
+        let (share, tbox, abox) = share.map_err(Error::Worker)?;
+        histogram.merge(&share);
+        tbox_triples += tbox;
+        abox_triples += abox;
+    }
+    println!(
+        "Replayed {} timestamps in {:?}\nFull tbox size {:?} \nFull abox size {:?}",
+        timestamps, elapsed, tbox_triples, abox_triples
+    );
+    match matches.value_of("HISTOGRAM") {
+        Some(path) => {
+            std::fs::write(path, histogram.render()).map_err(|source| Error::Io {
+                path: path.to_string(),
+                source,
+            })?;
+            println!("Latency histogram written to {}", path);
+        }
+        None => print!("{}", histogram.render()),
+    }
+    Ok(())
+}
+
+fn present(triples: Vec<EncodedTriple>) -> Vec<(EncodedTriple, isize)> {
+    triples.into_iter().map(|triple| (triple, 1)).collect()
+}
+
+/// Reads the triples of `path`: N-Triples encoded with `dictionary` if there
+/// is one, encoded triples otherwise.
+fn read_triples(path: &str, dictionary: Option<&mut Dictionary>) -> Result<Vec<EncodedTriple>> {
+    match dictionary {
+        Some(dictionary) => load3nt(path)?
+            .map(|triple| Ok(dictionary.encode_triple(&triple?)))
+            .collect(),
+        None => load3enc(path)?.collect(),
+    }
+}
+
+/// Reads the delta file at `path`, like `read_triples`.
+fn read_delta(
+    path: &str,
+    dictionary: Option<&mut Dictionary>,
+) -> Result<Vec<(EncodedTriple, isize)>> {
+    match dictionary {
+        Some(dictionary) => load3nt_delta(path)?
+            .map(|update| {
+                let (triple, diff) = update?;
+                Ok((dictionary.encode_triple(&triple), diff))
+            })
+            .collect(),
+        None => load3enc_delta(path)?.collect(),
+    }
+}
+
+/// Resolves the terms of `goal` with `dictionary` if the input was encoded,
+/// or parses them as ids. `None` if a term was never encoded, since the
+/// pattern matches nothing then.
+fn resolve(goal: &GoalTerms, dictionary: Option<&Dictionary>) -> Result<Option<Pattern>> {
+    let resolve = |term: &Option<String>| -> Result<Option<Option<usize>>> {
+        match (term, dictionary) {
+            (None, _) => Ok(Some(None)),
+            (Some(term), Some(dictionary)) => Ok(dictionary.get(term).map(Some)),
+            (Some(term), None) => term
+                .parse()
+                .map(|id| Some(Some(id)))
+                .map_err(|_| Error::Config(format!("{:?} is not an encoded term", term))),
+        }
+    };
+    match (resolve(&goal.0)?, resolve(&goal.1)?, resolve(&goal.2)?) {
+        (Some(s), Some(p), Some(o)) => Ok(Some((s, p, o))),
+        _ => Ok(None),
+    }
+}
+
+fn load_encoded(t_path: &str, a_path: &str, reasoner: &mut Reasoner) -> Result<()> {
+    let tbox = load3enc(t_path)?;
+    let abox = load3enc(a_path)?;
+    for triple in tbox {
+        reasoner.insert_tbox(triple?);
+    }
+    for triple in abox {
+        reasoner.insert_abox(triple?);
+    }
+    Ok(())
+}
+
+/// Loads files of both TBox and ABox triples, which the dataflow routes to the
+/// right box by the reasoner's schema.
+fn load_mixed(
+    paths: &[String],
+    encode: bool,
+    reasoner: &mut Reasoner,
+) -> Result<Option<Dictionary>> {
+    if !encode {
+        for path in paths {
+            for triple in load3enc(path)? {
+                reasoner.insert(triple?);
+            }
+        }
+        return Ok(None);
+    }
+
+    let mut dictionary = Dictionary::new();
+    // Gives the schema terms the ids the reasoner's schema was resolved with.
+    Schema::resolve(&mut dictionary);
+    for path in paths {
+        println!("Input location: {}", path);
+        for statement in load_graph_triples(path)? {
+            let (triple, graph) = statement?;
+            let (s, p, o) = dictionary.encode_triple(&triple);
+            let graph = graph.map_or(DEFAULT_GRAPH, |graph| dictionary.encode(&graph));
+            reasoner.insert_quad((s, p, o, graph))?;
+        }
+    }
+    Ok(Some(dictionary))
+}
+
+/// Applies the TBox delta file at `path`: N-Triples when the input was
+/// encoded with `dictionary`, encoded triples otherwise.
+fn load_tbox_delta(
+    path: &str,
+    dictionary: Option<&mut Dictionary>,
+    reasoner: &mut Reasoner,
+) -> Result<()> {
+    for (triple, diff) in read_delta(path, dictionary)? {
+        reasoner.update_tbox(triple, diff);
+    }
+    Ok(())
+}
+
+/// Reads the statements of `path` with the graph they belong to, choosing the
+/// syntax by file extension.
+fn load_graph_triples(path: &str) -> Result<Box<dyn Iterator<Item = Result<GraphTriple>>>> {
+    if path.ends_with(".nq") {
+        Ok(Box::new(load4nq(path)?.map(|quad| {
+            quad.map(|(s, p, o, graph)| ((s, p, o), graph))
+        })))
+    } else if path.ends_with(".trig") {
+        Ok(Box::new(load_trig(path)?.into_iter().map(Ok)))
+    } else {
+        Ok(Box::new(
+            load3nt(path)?.map(|triple| triple.map(|triple| (triple, None))),
+        ))
+    }
+}
+
+fn load_and_intern(t_path: &str, a_path: &str, reasoner: &mut Reasoner) -> Result<Dictionary> {
+    let abox = load_graph_triples(a_path)?;
+    println!("A-box location: {}", a_path);
+    let tbox = load_graph_triples(t_path)?;
+    println!("T-box location: {}", t_path);
+
+    let mut dictionary = Dictionary::new();
+
+    // The TBox is shared by all graphs.
+    for statement in tbox {
+        let (triple, _graph) = statement?;
+        reasoner.insert_tbox(dictionary.encode_triple(&triple));
+    }
+    for statement in abox {
+        let (triple, graph) = statement?;
+        let (s, p, o) = dictionary.encode_triple(&triple);
+        let graph = graph.map_or(DEFAULT_GRAPH, |graph| dictionary.encode(&graph));
+        reasoner.insert_abox_quad((s, p, o, graph))?;
     }
+    Ok(dictionary)
 }
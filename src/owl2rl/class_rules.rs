@@ -1,17 +1,20 @@
 use std::cmp::Ordering::{Equal, Greater, Less};
 
-use differential_dataflow::{collection::concatenate, lattice::Lattice, ExchangeData};
+use differential_dataflow::{
+    collection::concatenate, difference::Present, lattice::Lattice, AsCollection, ExchangeData,
+};
 use dogsdogsdogs::{
     altneu::AltNeu, PrefixExtender, ProposeExtensionMethod, ValidateExtensionMethod,
 };
 use timely::{
-    dataflow::{Scope, ScopeParent},
+    dataflow::{operators::ToStream, Scope, ScopeParent},
     progress::Timestamp,
+    worker::AsWorker,
 };
 
 use crate::owl2rl::IRI;
 
-use super::{Class, Property, SameAs};
+use super::{Clash, Class, Property, SameAs};
 
 /*
 T(?c, owl:intersectionOf, ?x)
@@ -23,7 +26,7 @@ T(?y, rdf:type, ?cn)
 =>
 T(?y, rdf:type, ?c)
 */
-fn cls_int1<G, T>(class_list: Vec<&Class<G, T>>, target_class: &mut Class<G, T>)
+pub(super) fn cls_int1<G, T>(class_list: Vec<&Class<G, T>>, target_class: &mut Class<G, T>)
 where
     G: Scope,
     G: ScopeParent<Timestamp = AltNeu<T>>,
@@ -57,7 +60,7 @@ T(?y, rdf:type, ?c2)
 ...
 T(?y, rdf:type, ?cn)
  */
-fn cls_int2<G, T>(mut class_list: Vec<&mut Class<G, T>>, target_class: &Class<G, T>)
+pub(super) fn cls_int2<G, T>(mut class_list: Vec<&mut Class<G, T>>, target_class: &Class<G, T>)
 where
     G: Scope,
     G: ScopeParent<Timestamp = AltNeu<T>>,
@@ -69,8 +72,71 @@ where
     // IGNORE; Handled by TBox expansion via sco
 }
 
-// cls-uni
-// IGNORE; Handled by TBox expansion via sco
+/*
+T(?c, owl:unionOf, ?x)
+LIST[?x, ?c1, ..., ?cn]
+T(?y, rdf:type, ?ci)
+=>
+T(?y, rdf:type, ?c)
+ */
+// Every member of the union is a subclass of it, so each ?ci feeds ?c the way
+// T(?ci, rdfs:subClassOf, ?c) would.
+pub(super) fn cls_uni<G, T>(class_list: Vec<&Class<G, T>>, target_class: &mut Class<G, T>)
+where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    for c_i in class_list {
+        target_class.add(c_i.stream().clone());
+    }
+}
+
+/*
+T(?c1, owl:complementOf, ?c2)
+T(?x, rdf:type, ?c1)
+T(?x, rdf:type, ?c2)
+=>
+false
+ */
+fn cls_com<G, T>(class1: &Class<G, T>, class2: &Class<G, T>, clash: &mut Clash<G>)
+where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    clash.add(
+        class1
+            .stream()
+            .map(|x| (x, ()))
+            .validate_using(&mut class2.extender_neu().extend_using(|&x| x))
+            .map(|(x, ())| x),
+    );
+    clash.add(
+        class2
+            .stream()
+            .map(|x| (x, ()))
+            .validate_using(&mut class1.extender_alt().extend_using(|&x| x))
+            .map(|(x, ())| x),
+    );
+}
+
+/*
+T(?c1, owl:disjointWith, ?c2)
+T(?x, rdf:type, ?c1)
+T(?x, rdf:type, ?c2)
+=>
+false
+ */
+// The clash of cls-com, for classes declared disjoint rather than complements.
+pub(super) fn cax_dw<G, T>(class1: &Class<G, T>, class2: &Class<G, T>, clash: &mut Clash<G>)
+where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    cls_com(class1, class2, clash);
+}
 
 /*
 T(?x, owl:someValuesFrom, ?y)
@@ -235,6 +301,61 @@ where
     same_as.add(d_upy);
 }
 
+/*
+T(?x, owl:maxCardinality, "0"^^xsd:nonNegativeInteger)
+T(?x, owl:onProperty, ?p)
+T(?u, rdf:type, ?x)
+T(?u, ?p, ?y)
+=>
+false
+ */
+fn cls_maxc1<G, T>(class: &Class<G, T>, property: &Property<G, T>, clash: &mut Clash<G>)
+where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    clash.add(
+        class
+            .stream()
+            .propose_using(&mut property.by_s_neu().extend_using(|&u| u))
+            .map(|(u, _y)| u),
+    );
+    clash.add(
+        property
+            .stream()
+            .map(|(u, _y)| (u, ()))
+            .validate_using(&mut class.extender_alt().extend_using(|&u| u))
+            .map(|(u, ())| u),
+    );
+}
+
+/*
+T(?x, owl:maxCardinality, ?n)
+T(?x, owl:onProperty, ?p)
+ */
+// Picks cls-maxc1 or cls-maxc2 by the value of ?n, as `Dictionary::cardinality`
+// reads it, so "0"^^xsd:int and "1"^^xsd:integer restrict as the
+// xsd:nonNegativeInteger forms do. Other cardinalities entail nothing in
+// OWL 2 RL.
+fn cls_maxc<G, T>(
+    cardinality: Option<u64>,
+    class: &Class<G, T>,
+    property: &Property<G, T>,
+    same_as: &mut SameAs<G, T>,
+    clash: &mut Clash<G>,
+) where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    match cardinality {
+        Some(0) => cls_maxc1(class, property, clash),
+        Some(1) => cls_maxc2(property, class, same_as),
+        _ => {}
+    }
+}
+
 #[allow(clippy::type_complexity)]
 /*
 T(?x, owl:maxQualifiedCardinality, "1"^^xsd:nonNegativeInteger)
@@ -360,13 +481,128 @@ T(?y1, rdf:type, ?c)
 ...
 T(?yn, rdf:type, ?c)
  */
-fn cls_oo<G, T>(mut class_list: Vec<&mut Class<G, T>>, target_class: &Class<G, T>)
+pub(super) fn cls_oo<G, T>(individuals: &[IRI], target_class: &mut Class<G, T>)
 where
     G: Scope,
     G: ScopeParent<Timestamp = AltNeu<T>>,
     T: Lattice + ExchangeData + Timestamp,
 {
-    for ref mut c_i in class_list.iter_mut() {
-        c_i.add(target_class.stream().clone());
+    let mut scope = target_class.stream().scope();
+    // The conclusions hold from the start, and one worker is enough to state
+    // them.
+    let members = individuals
+        .iter()
+        .filter(|_| scope.index() == 0)
+        .map(|&y| (y, AltNeu::<T>::minimum(), Present))
+        .collect::<Vec<_>>();
+    target_class.add(members.to_stream(&mut scope).as_collection());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dictionary::Dictionary;
+    use crate::owl2rl::testing::{run_rule as run, Fact};
+    use std::collections::BTreeSet;
+
+    enum Rule {
+        /// Class 2 is the union of classes 0 and 1.
+        Uni,
+        /// Class 0 is the complement of class 1.
+        Com,
+        /// Class 0 has a max cardinality of 0 on property 0.
+        MaxC1,
+        /// Class 0 has a max cardinality of the given literal on property 0.
+        MaxC(&'static str),
+    }
+
+    /// Feeds `classes_in` (class index, member) and `properties_in` (property
+    /// index, subject, object) to `rule`, and returns the distinct IRIs it
+    /// derives: new members of the target class, or clashing individuals.
+    fn run_rule(
+        rule: Rule,
+        classes_in: Vec<(usize, IRI)>,
+        properties_in: Vec<(usize, IRI, IRI)>,
+    ) -> BTreeSet<IRI> {
+        let facts = classes_in
+            .into_iter()
+            .map(|(i, x)| Fact::Member(i, x))
+            .chain(
+                properties_in
+                    .into_iter()
+                    .map(|(i, x, y)| Fact::Pair(i, x, y)),
+            )
+            .collect();
+        let mut steps = run(3, 1, vec![facts], move |relations| {
+            let classes = &relations.classes;
+            let properties = &relations.properties;
+            let clash = &mut relations.clash;
+            match rule {
+                Rule::Uni => {
+                    let mut union = Class::new(classes[2].stream().clone());
+                    cls_uni(vec![&classes[0], &classes[1]], &mut union);
+                    union.feedback_
+                }
+                Rule::Com => {
+                    cls_com(&classes[0], &classes[1], clash);
+                    clash.feedback_.drain(..).collect()
+                }
+                Rule::MaxC1 => {
+                    cls_maxc1(&classes[0], &properties[0], clash);
+                    clash.feedback_.drain(..).collect()
+                }
+                Rule::MaxC(literal) => {
+                    let mut dictionary = Dictionary::new();
+                    let cardinality = dictionary.encode(literal);
+                    // Only cls-maxc2 derives owl:sameAs, which is not collected.
+                    cls_maxc(
+                        dictionary.cardinality(cardinality),
+                        &classes[0],
+                        &properties[0],
+                        &mut relations.same_as,
+                        clash,
+                    );
+                    clash.feedback_.drain(..).collect()
+                }
+            }
+        });
+        steps.pop().unwrap()
+    }
+
+    fn iris(iris: &[IRI]) -> BTreeSet<IRI> {
+        iris.iter().cloned().collect()
+    }
+
+    #[test]
+    fn cls_uni_types_members_of_every_union_member() {
+        let typed = run_rule(Rule::Uni, vec![(0, 1), (1, 2), (2, 3)], vec![]);
+        assert_eq!(typed, iris(&[1, 2]));
+    }
+
+    #[test]
+    fn cls_com_clashes_on_members_of_both_classes() {
+        let clashing = run_rule(Rule::Com, vec![(0, 1), (0, 2), (1, 2), (1, 3)], vec![]);
+        assert_eq!(clashing, iris(&[2]));
+    }
+
+    #[test]
+    fn cls_maxc1_clashes_on_members_with_a_value() {
+        let clashing = run_rule(
+            Rule::MaxC1,
+            vec![(0, 1), (0, 2)],
+            vec![(0, 2, 20), (0, 2, 21), (0, 3, 30)],
+        );
+        assert_eq!(clashing, iris(&[2]));
+    }
+
+    #[test]
+    fn cls_maxc_reads_the_cardinality_by_value() {
+        let classes = vec![(0, 1), (0, 2)];
+        let properties = vec![(0, 2, 20), (0, 3, 30)];
+        let zero = "\"0\"^^<http://www.w3.org/2001/XMLSchema#int>";
+        let one = "\"01\"^^<http://www.w3.org/2001/XMLSchema#integer>";
+        let clashing = run_rule(Rule::MaxC(zero), classes.clone(), properties.clone());
+        assert_eq!(clashing, iris(&[2]));
+        assert_eq!(run_rule(Rule::MaxC(one), classes, properties), iris(&[]));
     }
 }
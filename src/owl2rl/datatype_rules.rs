@@ -0,0 +1,358 @@
+use std::cmp::Ordering::{Equal, Greater, Less};
+
+use differential_dataflow::{lattice::Lattice, Collection, ExchangeData};
+use dogsdogsdogs::{altneu::AltNeu, ProposeExtensionMethod, ValidateExtensionMethod};
+use timely::{
+    dataflow::{Scope, ScopeParent},
+    progress::Timestamp,
+};
+
+use crate::datatypes::{contains, Datum};
+use crate::owl2rl::{Diff, IRI};
+
+use super::{Clash, Class, Literals, SameAs};
+
+/*
+=>
+T(?dt, rdf:type, rdfs:Datatype) for every datatype ?dt of the datatype map
+ */
+fn dt_type1<G, T>(datatypes: &Collection<G, IRI, Diff>, target_class: &mut Class<G, T>)
+where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    target_class.add(datatypes.clone());
+}
+
+/*
+?lt is a literal whose value is in the value space of ?dt
+=>
+T(?lt, rdf:type, ?dt)
+ */
+// `datatype` is the position of ?dt in `datatypes::datatypes`, and
+// `target_class` its class.
+fn dt_type2<G, T>(literals: &Literals<G, T>, datatype: usize, target_class: &mut Class<G, T>)
+where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    target_class.add(literals.stream().flat_map(move |(lt, datum)| {
+        if contains(datatype, &datum) {
+            Some(lt)
+        } else {
+            None
+        }
+    }));
+}
+
+/*
+?lt1 and ?lt2 are literals with the same data value
+=>
+T(?lt1, owl:sameAs, ?lt2)
+ */
+// Both atoms are literals, so a literal is joined with those of earlier
+// timestamps through `neu`, and with those of its own timestamp once, through
+// `alt`, as the second atom.
+fn dt_eq<G, T>(literals: &Literals<G, T>, same_as: &mut SameAs<G, T>)
+where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    let ordered = |lt1: IRI, lt2: IRI| match lt1.cmp(&lt2) {
+        Less => Some((lt1, lt2)),
+        Greater => Some((lt2, lt1)),
+        Equal => None,
+    };
+    same_as.add(
+        literals
+            .stream()
+            .propose_using(
+                &mut literals
+                    .by_value_neu()
+                    .extend_using(|&(_lt1, ref datum): &(IRI, Datum)| datum.clone()),
+            )
+            .flat_map(move |((lt1, _datum), lt2)| ordered(lt1, lt2)),
+    );
+    same_as.add(
+        literals
+            .stream()
+            .propose_using(
+                &mut literals
+                    .by_value_alt()
+                    .extend_using(|&(_lt2, ref datum): &(IRI, Datum)| datum.clone()),
+            )
+            .flat_map(move |((lt2, _datum), lt1)| ordered(lt1, lt2)),
+    );
+}
+
+/*
+T(?lt1, owl:sameAs, ?lt2)
+?lt1 and ?lt2 are literals with different data values
+=>
+false
+ */
+// dt-diff relates every two literals with different values, which is
+// quadratic in the literals, and eq-diff1 only ever clashes on the pairs also
+// in owl:sameAs; this checks those pairs instead. The atoms are the pair, the
+// value of ?lt1 and the value of ?lt2, in that order.
+fn dt_diff<G, T>(literals: &Literals<G, T>, same_as: &SameAs<G, T>, clash: &mut Clash<G>)
+where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    let differ = |lt1: IRI, datum1: Datum, datum2: Datum| {
+        if datum1 != datum2 {
+            Some(lt1)
+        } else {
+            None
+        }
+    };
+    clash.add(
+        same_as
+            .stream()
+            .propose_using(
+                &mut literals
+                    .by_id_neu()
+                    .extend_using(|&(lt1, _lt2): &(IRI, IRI)| lt1),
+            )
+            .propose_using(
+                &mut literals
+                    .by_id_neu()
+                    .extend_using(|&((_lt1, lt2), _): &((IRI, IRI), Datum)| lt2),
+            )
+            .flat_map(
+                move |(((lt1, _lt2), datum1), datum2): (((IRI, IRI), Datum), Datum)| {
+                    differ(lt1, datum1, datum2)
+                },
+            ),
+    );
+    clash.add(
+        literals
+            .stream()
+            .propose_using(&mut same_as.alt().extend_using(|&(lt1, _): &(IRI, Datum)| lt1))
+            .propose_using(
+                &mut literals
+                    .by_id_neu()
+                    .extend_using(|&((_lt1, _), lt2): &((IRI, Datum), IRI)| lt2),
+            )
+            .flat_map(
+                move |(((lt1, datum1), _lt2), datum2): (((IRI, Datum), IRI), Datum)| {
+                    differ(lt1, datum1, datum2)
+                },
+            ),
+    );
+    clash.add(
+        literals
+            .stream()
+            .propose_using(
+                &mut same_as
+                    .inverse_alt()
+                    .extend_using(|&(lt2, _): &(IRI, Datum)| lt2),
+            )
+            .propose_using(
+                &mut literals
+                    .by_id_alt()
+                    .extend_using(|&((_lt2, _), lt1): &((IRI, Datum), IRI)| lt1),
+            )
+            .flat_map(
+                move |(((_lt2, datum2), lt1), datum1): (((IRI, Datum), IRI), Datum)| {
+                    differ(lt1, datum1, datum2)
+                },
+            ),
+    );
+}
+
+/*
+T(?lt, rdf:type, ?dt)
+?lt is a literal whose value is not in the value space of ?dt
+=>
+false
+ */
+// As in dt-type2, `datatype` is the position of ?dt and `class` its class.
+fn dt_not_type<G, T>(
+    literals: &Literals<G, T>,
+    datatype: usize,
+    class: &Class<G, T>,
+    clash: &mut Clash<G>,
+) where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    clash.add(
+        class
+            .stream()
+            .propose_using(&mut literals.by_id_neu().extend_using(|&lt| lt))
+            .flat_map(move |(lt, datum)| {
+                if contains(datatype, &datum) {
+                    None
+                } else {
+                    Some(lt)
+                }
+            }),
+    );
+    clash.add(
+        literals
+            .stream()
+            .flat_map(move |(lt, datum)| {
+                if contains(datatype, &datum) {
+                    None
+                } else {
+                    Some((lt, ()))
+                }
+            })
+            .validate_using(&mut class.extender_alt().extend_using(|&lt| lt))
+            .map(|(lt, ())| lt),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::{datatypes, datum, XSD};
+    use crate::owl2rl::testing::{run_rule as run, Fact};
+    use std::collections::BTreeSet;
+
+    enum Rule {
+        /// The class is that of the datatype at this position.
+        Type2(usize),
+        Eq,
+        Diff,
+        /// As for `Type2`.
+        NotType(usize),
+    }
+
+    /// The literals 1 = 1, 2 = 1.0, 3 = 2 as a byte, 4 = -1 and 5 = 300.
+    fn literals() -> Vec<(IRI, Datum)> {
+        [
+            (1, "\"1\"^^<{}integer>"),
+            (2, "\"1.0\"^^<{}decimal>"),
+            (3, "\"2\"^^<{}byte>"),
+            (4, "\"-1\"^^<{}integer>"),
+            (5, "\"300\"^^<{}integer>"),
+        ]
+        .iter()
+        .map(|&(id, term)| (id, datum(&term.replace("{}", XSD)).unwrap()))
+        .collect()
+    }
+
+    fn literal(id: IRI) -> Fact {
+        let (lt, datum) = literals().into_iter().find(|(lt, _)| *lt == id).unwrap();
+        Fact::Literal(lt, datum)
+    }
+
+    fn byte() -> usize {
+        datatypes()
+            .iter()
+            .position(|datatype| datatype.ends_with("#byte>"))
+            .unwrap()
+    }
+
+    /// Feeds each step of facts to `rule`, over the members of class 0, and
+    /// returns what it derives after every step: owl:sameAs pairs, or new
+    /// members and clashing individuals `x` as `(x, x)`.
+    fn run_steps(rule: Rule, steps: Vec<Vec<Fact>>) -> Vec<BTreeSet<(IRI, IRI)>> {
+        run(1, 0, steps, move |relations| {
+            let literals = &relations.literals;
+            let class = &mut relations.classes[0];
+            let same_as = &mut relations.same_as;
+            let clash = &mut relations.clash;
+            let singles = |derived: &mut Vec<Collection<_, IRI, super::Diff>>| {
+                derived
+                    .drain(..)
+                    .map(|members| members.map(|x| (x, x)))
+                    .collect::<Vec<_>>()
+            };
+            match rule {
+                Rule::Type2(datatype) => {
+                    dt_type2(literals, datatype, class);
+                    singles(&mut class.feedback_)
+                }
+                Rule::Eq => {
+                    dt_eq(literals, same_as);
+                    same_as.feedback_.drain(..).collect()
+                }
+                Rule::Diff => {
+                    dt_diff(literals, same_as, clash);
+                    singles(&mut clash.feedback_)
+                }
+                Rule::NotType(datatype) => {
+                    dt_not_type(literals, datatype, class, clash);
+                    singles(&mut clash.feedback_)
+                }
+            }
+        })
+    }
+
+    /// Feeds `literals()`, the members of a class and owl:sameAs pairs to
+    /// `rule` at once, and returns what it derives.
+    fn run_rule(
+        rule: Rule,
+        members: Vec<IRI>,
+        same_as_in: Vec<(IRI, IRI)>,
+    ) -> BTreeSet<(IRI, IRI)> {
+        let facts = literals()
+            .into_iter()
+            .map(|(lt, datum)| Fact::Literal(lt, datum))
+            .chain(members.into_iter().map(|x| Fact::Member(0, x)))
+            .chain(same_as_in.into_iter().map(|(x, y)| Fact::SameAs(x, y)))
+            .collect();
+        run_steps(rule, vec![facts]).pop().unwrap()
+    }
+
+    fn singles(iris: &[IRI]) -> BTreeSet<(IRI, IRI)> {
+        iris.iter().map(|&x| (x, x)).collect()
+    }
+
+    #[test]
+    fn dt_type2_types_literals_by_value() {
+        let typed = run_rule(Rule::Type2(byte()), vec![], vec![]);
+        assert_eq!(typed, singles(&[1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn dt_eq_relates_literals_with_equal_values() {
+        let same = run_rule(Rule::Eq, vec![], vec![]);
+        assert_eq!(same, vec![(1, 2)].into_iter().collect());
+    }
+
+    #[test]
+    fn dt_eq_relates_literals_arriving_in_different_steps() {
+        let steps = vec![vec![literal(2)], vec![literal(3)], vec![literal(1)]];
+        let pair = vec![(1, 2)].into_iter().collect();
+        assert_eq!(
+            run_steps(Rule::Eq, steps),
+            vec![BTreeSet::new(), BTreeSet::new(), pair]
+        );
+    }
+
+    #[test]
+    fn dt_diff_clashes_on_the_same_literals_with_different_values() {
+        let clashing = run_rule(Rule::Diff, vec![], vec![(1, 2), (4, 5), (6, 7)]);
+        assert_eq!(clashing, singles(&[4]));
+    }
+
+    #[test]
+    fn dt_diff_clashes_on_literals_arriving_with_or_after_their_pair() {
+        // 1 arrives after its pair's 2, and -1 and 300 together after theirs.
+        let steps = vec![
+            vec![Fact::SameAs(1, 3), Fact::SameAs(4, 5), literal(3)],
+            vec![literal(1)],
+            vec![literal(4), literal(5)],
+        ];
+        assert_eq!(
+            run_steps(Rule::Diff, steps),
+            vec![singles(&[]), singles(&[1]), singles(&[1, 4])]
+        );
+    }
+
+    #[test]
+    fn dt_not_type_clashes_on_literals_outside_the_value_space() {
+        let clashing = run_rule(Rule::NotType(byte()), vec![1, 5, 6], vec![]);
+        assert_eq!(clashing, singles(&[5]));
+    }
+}
@@ -1,15 +1,37 @@
 pub(crate) mod class_rules;
+pub(crate) mod datatype_rules;
 pub(crate) mod property_rules;
 use std::lazy::OnceCell;
 
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use crate::datatypes::Datum;
+use crate::error::{Error, Result};
+use crate::lists::ListAxiom;
+use crate::materializations::EncodedTriple;
+use crate::vocabulary::ids::RDF_TYPE;
+use class_rules::{cax_dw, cls_int1, cls_int2, cls_oo, cls_uni};
+use property_rules::{prp_key, prp_spo2};
+
+use differential_dataflow::collection::concatenate;
+use differential_dataflow::input::Input;
 use differential_dataflow::operators::arrange::{arrangement::ArrangeBySelf, Arranged, TraceAgent};
+use differential_dataflow::operators::{iterate, Join, Threshold};
 use differential_dataflow::{
-    difference::Present, lattice::Lattice, trace::implementations::ord::OrdKeySpine, Collection,
-    ExchangeData,
+    difference::Present, lattice::Lattice, trace::implementations::ord::OrdKeySpine, AsCollection,
+    Collection, ExchangeData,
 };
 
 use dogsdogsdogs::altneu::AltNeu;
 use dogsdogsdogs::CollectionIndex;
+use timely::communication::Allocate;
+use timely::dataflow::operators::probe::Handle as ProbeHandle;
+use timely::dataflow::operators::{Exchange, Inspect, Map, Probe};
+use timely::order::Product;
+use timely::worker::Worker;
 use timely::{
     dataflow::{Scope, ScopeParent},
     progress::Timestamp,
@@ -24,6 +46,8 @@ pub(crate) type SingleArrangement<G> =
 
 pub(crate) type DoubleIndex<G> = CollectionIndex<IRI, IRI, <G as ScopeParent>::Timestamp, Diff>;
 pub(crate) type SingleIndex<G> = CollectionIndex<IRI, (), <G as ScopeParent>::Timestamp, Diff>;
+pub(crate) type DatumIndex<G> = CollectionIndex<IRI, Datum, <G as ScopeParent>::Timestamp, Diff>;
+pub(crate) type ValueIndex<G> = CollectionIndex<Datum, IRI, <G as ScopeParent>::Timestamp, Diff>;
 
 pub(crate) struct Property<G, T>
 where
@@ -69,16 +93,51 @@ where
     by_o_neu: DoubleIndex<G>,*/
     alt_: OnceCell<DoubleIndex<G>>,
     neu_: OnceCell<DoubleIndex<G>>,
+    inverse_alt_: OnceCell<DoubleIndex<G>>,
     stream_: Collection<G, (IRI, IRI), Diff>,
     feedback_: Vec<Collection<G, (IRI, IRI), Diff>>,
 }
 
+/// The literals of the dataflow with their data values. A literal enters it
+/// when it is interned, before any triple that mentions it.
+pub(crate) struct Literals<G, T>
+where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    by_id_alt_: OnceCell<DatumIndex<G>>,
+    by_id_neu_: OnceCell<DatumIndex<G>>,
+    by_value_alt_: OnceCell<ValueIndex<G>>,
+    by_value_neu_: OnceCell<ValueIndex<G>>,
+    stream_: Collection<G, (IRI, Datum), Diff>,
+}
+
+/// Individuals for which a rule concluded `false`.
+pub(crate) struct Clash<G>
+where
+    G: Scope,
+{
+    feedback_: Vec<Collection<G, IRI, Diff>>,
+}
+
 impl<G, T> Property<G, T>
 where
     G: Scope,
     G: ScopeParent<Timestamp = AltNeu<T>>,
     T: Lattice + ExchangeData + Timestamp,
 {
+    pub(crate) fn new(stream: Collection<G, (IRI, IRI), Diff>) -> Self {
+        Property {
+            by_s_alt_: OnceCell::new(),
+            by_o_alt_: OnceCell::new(),
+            by_s_neu_: OnceCell::new(),
+            by_o_neu_: OnceCell::new(),
+            stream_: stream,
+            feedback_: Vec::new(),
+        }
+    }
+
     fn by_s_alt(&self) -> &DoubleIndex<G> {
         self.by_s_alt_
             .get_or_init(|| CollectionIndex::index(&self.stream_))
@@ -130,6 +189,17 @@ where
     G: ScopeParent<Timestamp = AltNeu<T>>,
     T: Lattice + ExchangeData + Timestamp,
 {
+    pub(crate) fn new(stream: Collection<G, IRI, Diff>) -> Self {
+        Class {
+            alt_: OnceCell::new(),
+            neu_: OnceCell::new(),
+            alt_extender_: OnceCell::new(),
+            neu_extender_: OnceCell::new(),
+            stream_: stream,
+            feedback_: Vec::new(),
+        }
+    }
+
     fn add(&mut self, collection: Collection<G, IRI, Diff>) {
         self.feedback_.push(collection);
     }
@@ -181,6 +251,16 @@ where
     G: ScopeParent<Timestamp = AltNeu<T>>,
     T: Lattice + ExchangeData + Timestamp,
 {
+    pub(crate) fn new(stream: Collection<G, (IRI, IRI), Diff>) -> Self {
+        SameAs {
+            alt_: OnceCell::new(),
+            neu_: OnceCell::new(),
+            inverse_alt_: OnceCell::new(),
+            stream_: stream,
+            feedback_: Vec::new(),
+        }
+    }
+
     fn alt(&self) -> &DoubleIndex<G> {
         self.alt_
             .get_or_init(|| CollectionIndex::index(&self.stream_))
@@ -196,7 +276,487 @@ where
         })
     }
 
+    /// Indexed by the second individual of every pair.
+    fn inverse_alt(&self) -> &DoubleIndex<G> {
+        self.inverse_alt_.get_or_init(|| {
+            CollectionIndex::index(&self.stream_.map_in_place(|(x, y)| std::mem::swap(x, y)))
+        })
+    }
+
+    fn stream(&self) -> &Collection<G, (IRI, IRI), Diff> {
+        &self.stream_
+    }
+
     fn add(&mut self, collection: Collection<G, (IRI, IRI), Diff>) {
         self.feedback_.push(collection);
     }
 }
+
+impl<G, T> Literals<G, T>
+where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    pub(crate) fn new(stream: Collection<G, (IRI, Datum), Diff>) -> Self {
+        Literals {
+            by_id_alt_: OnceCell::new(),
+            by_id_neu_: OnceCell::new(),
+            by_value_alt_: OnceCell::new(),
+            by_value_neu_: OnceCell::new(),
+            stream_: stream,
+        }
+    }
+
+    fn by_id_alt(&self) -> &DatumIndex<G> {
+        self.by_id_alt_
+            .get_or_init(|| CollectionIndex::index(&self.stream_))
+    }
+
+    fn by_id_neu(&self) -> &DatumIndex<G> {
+        self.by_id_neu_.get_or_init(|| {
+            CollectionIndex::index(&self.stream_.delay(|t| {
+                let mut t_neu = t.clone();
+                t_neu.neu = true;
+                t_neu
+            }))
+        })
+    }
+
+    fn by_value_alt(&self) -> &ValueIndex<G> {
+        self.by_value_alt_.get_or_init(|| {
+            CollectionIndex::index(&self.stream_.map(|(literal, datum)| (datum, literal)))
+        })
+    }
+
+    fn by_value_neu(&self) -> &ValueIndex<G> {
+        self.by_value_neu_.get_or_init(|| {
+            CollectionIndex::index(
+                &self
+                    .stream_
+                    .delay(|t| {
+                        let mut t_neu = t.clone();
+                        t_neu.neu = true;
+                        t_neu
+                    })
+                    .map(|(literal, datum)| (datum, literal)),
+            )
+        })
+    }
+
+    fn stream(&self) -> &Collection<G, (IRI, Datum), Diff> {
+        &self.stream_
+    }
+}
+
+impl<G> Default for Clash<G>
+where
+    G: Scope,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<G> Clash<G>
+where
+    G: Scope,
+{
+    pub(crate) fn new() -> Self {
+        Clash {
+            feedback_: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, collection: Collection<G, IRI, Diff>) {
+        self.feedback_.push(collection);
+    }
+}
+
+/// The individuals that `triples`, a materialization, makes inconsistent by
+/// cax-dw or cls-com: those typed with two classes declared disjoint with
+/// `disjoint_with`, or complements with `complement_of`. Every worker passes
+/// its own part of the materialization, and the clashes are gathered on
+/// worker 0; the dataflow is dropped before returning.
+pub fn clashes<A: Allocate>(
+    worker: &mut Worker<A>,
+    triples: &[EncodedTriple],
+    disjoint_with: usize,
+    complement_of: usize,
+) -> Vec<usize> {
+    let clashing = Rc::new(RefCell::new(BTreeSet::new()));
+    let sink = clashing.clone();
+    let mut probe = ProbeHandle::new();
+    let dataflow = worker.next_dataflow_index();
+    let mut input = worker.dataflow::<Time, _, _>(|scope| {
+        let (input, triples) = scope.new_collection::<EncodedTriple, isize>();
+        let pairs = triples.flat_map(move |(s, p, o)| {
+            Some((s, o)).filter(|_| p == disjoint_with || p == complement_of)
+        });
+        let types = triples.flat_map(|(s, p, o)| Some((s, o)).filter(|_| p == RDF_TYPE));
+        // Members of the first class of a pair, keyed by the second one they
+        // would also need to be a member of.
+        types
+            .map(|(x, class)| (class, x))
+            .join_map(&pairs, |_class1, &x, &class2| ((x, class2), ()))
+            .semijoin(&types)
+            .map(|((x, _class2), ())| x)
+            .distinct()
+            .inner
+            .exchange(|_clash| 0)
+            .inspect(move |(x, _time, _diff)| {
+                sink.borrow_mut().insert(*x);
+            })
+            .probe_with(&mut probe);
+        input
+    });
+    for triple in triples.iter() {
+        input.insert(*triple);
+    }
+    drop(input);
+    worker.step_while(|| !probe.done());
+    worker.drop_dataflow(dataflow);
+
+    let clashing = clashing.borrow().iter().cloned().collect();
+    clashing
+}
+
+/// What the rules over lists conclude from an ABox.
+pub struct ListConclusions<G: Scope> {
+    /// The ABox with the triples derived from it.
+    pub triples: Collection<G, EncodedTriple>,
+    /// The individuals prp-key makes the same.
+    pub same_as: Collection<G, (usize, usize)>,
+    /// The individuals cax-adc finds in two disjoint classes.
+    pub clashes: Collection<G, usize>,
+}
+
+/// Instantiates the rules that read a list, prp-spo2, prp-key, cls-int1,
+/// cls-int2, cls-uni, cls-oo and cax-adc, for `axioms`, such as those
+/// `lists::decode` returns, and applies them to `abox` up to a fixpoint. The
+/// rules only ever add conclusions, so these stay when the triples they were
+/// drawn from are retracted. Fails if an id of `axioms` does not fit in an
+/// `IRI`; triples of `abox` with such an id are left out of the rules.
+pub fn list_rules<G>(
+    abox: &Collection<G, EncodedTriple>,
+    axioms: &[ListAxiom],
+    outer: &mut G,
+) -> Result<ListConclusions<G>>
+where
+    G: Scope,
+    G::Timestamp: Lattice + ExchangeData,
+{
+    for id in axioms.iter().flat_map(ListAxiom::ids) {
+        IRI::try_from(id).map_err(|_| Error::Config(format!("id {} exceeds 32 bits", id)))?;
+    }
+    let axioms = axioms.to_vec();
+
+    let (triples, same_as, clashes) = outer.region_named("List rules", |region| {
+        let abox = abox.enter(region);
+        let (triples, same_as, clashes) = region.iterative::<usize, _, _>(|inner| {
+            let variable =
+                iterate::SemigroupVariable::new(inner, Product::new(Default::default(), 1));
+            let current = variable.distinct();
+            let facts = current
+                .inner
+                .flat_map(|((s, p, o), time, diff)| {
+                    match (IRI::try_from(s), IRI::try_from(p), IRI::try_from(o)) {
+                        (Ok(s), Ok(p), Ok(o)) if diff > 0 => Some(((s, p, o), time, Present)),
+                        _ => None,
+                    }
+                })
+                .as_collection();
+
+            let (derived, same_as, clashes) =
+                inner.scoped::<AltNeu<_>, _, _>("List rule deltas", |rules| {
+                    let (derived, same_as, clashes) = instantiate(&facts.enter(rules), &axioms);
+                    (derived.leave(), same_as.leave(), clashes.leave())
+                });
+            let derived = counted(&derived.map(|(s, p, o)| (s as usize, p as usize, o as usize)));
+            let same_as = counted(&same_as.map(|(x, y)| (x as usize, y as usize)));
+            let clashes = counted(&clashes.map(|x| x as usize));
+
+            variable.set(&abox.enter(inner).concat(&derived));
+            (
+                current.leave(),
+                same_as.distinct().leave(),
+                clashes.distinct().leave(),
+            )
+        });
+        (triples.leave(), same_as.leave(), clashes.leave())
+    });
+    Ok(ListConclusions {
+        triples,
+        same_as,
+        clashes,
+    })
+}
+
+/// `collection`, counting every conclusion once.
+fn counted<G, D>(collection: &Collection<G, D, Diff>) -> Collection<G, D>
+where
+    G: Scope,
+    D: ExchangeData,
+{
+    collection
+        .inner
+        .map(|(datum, time, _present)| (datum, time, 1))
+        .as_collection()
+}
+
+/// The list rules of `axioms` over `facts`: the triples, owl:sameAs pairs and
+/// clashes they conclude. The classes and properties the rules read are made
+/// once, and shared by the axioms that mention them; the ones they add to are
+/// made for every rule, so that none is borrowed both ways.
+#[allow(clippy::type_complexity)]
+fn instantiate<G, T>(
+    facts: &Collection<G, (IRI, IRI, IRI), Diff>,
+    axioms: &[ListAxiom],
+) -> (
+    Collection<G, (IRI, IRI, IRI), Diff>,
+    Collection<G, (IRI, IRI), Diff>,
+    Collection<G, IRI, Diff>,
+)
+where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    let rdf_type = RDF_TYPE as IRI;
+    let class = |c: IRI| {
+        Class::new(facts.flat_map(move |(x, p, o)| Some(x).filter(|_| p == rdf_type && o == c)))
+    };
+    let property =
+        |q: IRI| Property::new(facts.flat_map(move |(x, p, y)| Some((x, y)).filter(|_| p == q)));
+    let iris = |ids: &[usize]| ids.iter().map(|&id| id as IRI).collect::<Vec<_>>();
+
+    let mut classes = BTreeMap::new();
+    let mut properties = BTreeMap::new();
+    for axiom in axioms.iter() {
+        let (read_classes, read_properties) = match axiom {
+            ListAxiom::PropertyChain { chain, .. } => (vec![], chain.clone()),
+            ListAxiom::IntersectionOf { class, classes } => {
+                (classes.iter().chain(Some(class)).cloned().collect(), vec![])
+            }
+            ListAxiom::UnionOf { classes, .. } | ListAxiom::AllDisjointClasses { classes } => {
+                (classes.clone(), vec![])
+            }
+            ListAxiom::HasKey { class, properties } => (vec![*class], properties.clone()),
+            ListAxiom::OneOf { .. } | ListAxiom::Members { .. } => (vec![], vec![]),
+        };
+        for c in iris(&read_classes) {
+            classes.entry(c).or_insert_with(|| class(c));
+        }
+        for q in iris(&read_properties) {
+            properties.entry(q).or_insert_with(|| property(q));
+        }
+    }
+
+    let mut scope = facts.scope();
+    let mut derived = Vec::new();
+    let mut members_of = |c: IRI, class: Class<G, T>| {
+        derived.push(concatenate(&mut scope, class.feedback_).map(move |x| (x, rdf_type, c)));
+    };
+    // No rule here reads owl:sameAs.
+    let mut same_as = SameAs::new(facts.flat_map(|_| None));
+    let mut clash = Clash::new();
+    let mut chains = Vec::new();
+    for axiom in axioms.iter() {
+        match axiom {
+            ListAxiom::PropertyChain { property: p, chain } => {
+                let mut target = property(*p as IRI);
+                prp_spo2(
+                    iris(chain).iter().map(|q| &properties[q]).collect(),
+                    &mut target,
+                );
+                chains.push((*p as IRI, target));
+            }
+            ListAxiom::IntersectionOf {
+                class: c,
+                classes: cs,
+            } => {
+                let cs = iris(cs);
+                let mut target = class(*c as IRI);
+                cls_int1(cs.iter().map(|ci| &classes[ci]).collect(), &mut target);
+                members_of(*c as IRI, target);
+                let mut members = cs.iter().map(|&ci| class(ci)).collect::<Vec<_>>();
+                cls_int2(members.iter_mut().collect(), &classes[&(*c as IRI)]);
+                for (&ci, member) in cs.iter().zip(members) {
+                    members_of(ci, member);
+                }
+            }
+            ListAxiom::UnionOf {
+                class: c,
+                classes: cs,
+            } => {
+                let mut target = class(*c as IRI);
+                cls_uni(
+                    iris(cs).iter().map(|ci| &classes[ci]).collect(),
+                    &mut target,
+                );
+                members_of(*c as IRI, target);
+            }
+            ListAxiom::OneOf {
+                class: c,
+                individuals,
+            } => {
+                let mut target = class(*c as IRI);
+                cls_oo(&iris(individuals), &mut target);
+                members_of(*c as IRI, target);
+            }
+            ListAxiom::HasKey {
+                class: c,
+                properties: ps,
+            } if !ps.is_empty() => {
+                prp_key(
+                    iris(ps).iter().map(|q| &properties[q]).collect(),
+                    &classes[&(*c as IRI)],
+                    &mut same_as,
+                );
+            }
+            // cax-adc is cax-dw for every two members.
+            ListAxiom::AllDisjointClasses { classes: cs } => {
+                let cs = iris(cs);
+                for (i, c1) in cs.iter().enumerate() {
+                    for c2 in cs[i + 1..].iter().filter(|&c2| c2 != c1) {
+                        cax_dw(&classes[c1], &classes[c2], &mut clash);
+                    }
+                }
+            }
+            ListAxiom::HasKey { .. } | ListAxiom::Members { .. } => {}
+        }
+    }
+    for (p, chain) in chains {
+        derived.push(concatenate(&mut scope, chain.feedback_).map(move |(x, y)| (x, p, y)));
+    }
+
+    (
+        concatenate(&mut scope, derived),
+        concatenate(&mut scope, same_as.feedback_),
+        concatenate(&mut scope, clash.feedback_),
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::*;
+    use timely::communication::allocator::Thread;
+    use timely::dataflow::scopes::Child;
+
+    /// The scope rules under test are built in.
+    pub(crate) type RuleScope<'a, 'b> = Child<'b, Child<'a, Worker<Thread>, Time>, AltNeu<Time>>;
+
+    /// An input fact of a rule under test.
+    #[derive(Clone, Debug)]
+    pub(crate) enum Fact {
+        /// A member of the class at this position.
+        Member(usize, IRI),
+        /// A subject and object of the property at this position.
+        Pair(usize, IRI, IRI),
+        SameAs(IRI, IRI),
+        Literal(IRI, Datum),
+    }
+
+    /// What a rule under test reads, and adds its conclusions to.
+    pub(crate) struct Relations<G, T>
+    where
+        G: Scope,
+        G: ScopeParent<Timestamp = AltNeu<T>>,
+        T: Lattice + ExchangeData + Timestamp,
+    {
+        pub(crate) classes: Vec<Class<G, T>>,
+        pub(crate) properties: Vec<Property<G, T>>,
+        pub(crate) same_as: SameAs<G, T>,
+        pub(crate) literals: Literals<G, T>,
+        pub(crate) clash: Clash<G>,
+    }
+
+    /// Builds a rule with `build` over `classes` classes and `properties`
+    /// properties, inserts each of `steps` at a timestamp of its own, and
+    /// returns what the collections `build` returns hold after every step.
+    pub(crate) fn run_rule<D, F>(
+        classes: usize,
+        properties: usize,
+        steps: Vec<Vec<Fact>>,
+        build: F,
+    ) -> Vec<BTreeSet<D>>
+    where
+        D: ExchangeData + Ord,
+        F: for<'a, 'b> FnOnce(
+                &mut Relations<RuleScope<'a, 'b>, Time>,
+            ) -> Vec<Collection<RuleScope<'a, 'b>, D, Diff>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        timely::execute_directly(move |worker| {
+            let derived = Rc::new(RefCell::new(BTreeSet::new()));
+            let sink = derived.clone();
+            let mut probe = ProbeHandle::new();
+
+            let (mut class_inputs, mut property_inputs, mut same_as_input, mut literal_input) =
+                worker.dataflow::<Time, _, _>(|scope| {
+                    let (class_inputs, class_streams): (Vec<_>, Vec<_>) = (0..classes)
+                        .map(|_| scope.new_collection::<IRI, Diff>())
+                        .unzip();
+                    let (property_inputs, property_streams): (Vec<_>, Vec<_>) = (0..properties)
+                        .map(|_| scope.new_collection::<(IRI, IRI), Diff>())
+                        .unzip();
+                    let (same_as_input, same_as) = scope.new_collection::<(IRI, IRI), Diff>();
+                    let (literal_input, literals) = scope.new_collection::<(IRI, Datum), Diff>();
+
+                    scope
+                        .scoped::<AltNeu<Time>, _, _>("rule", |inner| {
+                            let mut relations = Relations {
+                                classes: class_streams
+                                    .iter()
+                                    .map(|stream| Class::new(stream.enter(inner)))
+                                    .collect(),
+                                properties: property_streams
+                                    .iter()
+                                    .map(|stream| Property::new(stream.enter(inner)))
+                                    .collect(),
+                                same_as: SameAs::new(same_as.enter(inner)),
+                                literals: Literals::new(literals.enter(inner)),
+                                clash: Clash::new(),
+                            };
+                            concatenate(inner, build(&mut relations)).leave()
+                        })
+                        .inspect(move |(datum, _time, _diff)| {
+                            sink.borrow_mut().insert(datum.clone());
+                        })
+                        .probe_with(&mut probe);
+
+                    (class_inputs, property_inputs, same_as_input, literal_input)
+                });
+
+            let mut results = Vec::new();
+            for (time, facts) in steps.into_iter().enumerate() {
+                for fact in facts {
+                    match fact {
+                        Fact::Member(i, x) => class_inputs[i].update(x, Present),
+                        Fact::Pair(i, x, y) => property_inputs[i].update((x, y), Present),
+                        Fact::SameAs(x, y) => same_as_input.update((x, y), Present),
+                        Fact::Literal(lt, datum) => literal_input.update((lt, datum), Present),
+                    }
+                }
+                let next = time as Time + 1;
+                for input in class_inputs.iter_mut() {
+                    input.advance_to(next);
+                    input.flush();
+                }
+                for input in property_inputs.iter_mut() {
+                    input.advance_to(next);
+                    input.flush();
+                }
+                same_as_input.advance_to(next);
+                same_as_input.flush();
+                literal_input.advance_to(next);
+                literal_input.flush();
+                worker.step_while(|| probe.less_than(&next));
+                results.push(derived.borrow().clone());
+            }
+            results
+        })
+    }
+}
@@ -1,11 +1,11 @@
-use differential_dataflow::{difference::Present, lattice::Lattice, ExchangeData};
-use dogsdogsdogs::{altneu::AltNeu, operators::lookup_map, ProposeExtensionMethod};
+use differential_dataflow::{lattice::Lattice, Collection, ExchangeData};
+use dogsdogsdogs::{altneu::AltNeu, ProposeExtensionMethod, ValidateExtensionMethod};
 use timely::{
     dataflow::{Scope, ScopeParent},
     progress::Timestamp,
 };
 
-use super::{Class, Property, SameAs};
+use super::{Class, Diff, Property, SameAs, IRI};
 
 fn prp_dom<G, T>(property: &Property<G, T>, class: &mut Class<G, T>)
 where
@@ -134,8 +134,10 @@ T(?un, ?pn, ?un+1)
 =>
 T(?u1, ?p, ?un+1)
  */
-fn prp_spo2<G, T>(property_chain: Vec<&Property<G, T>>, target_property: &mut Property<G, T>)
-where
+pub(super) fn prp_spo2<G, T>(
+    property_chain: Vec<&Property<G, T>>,
+    target_property: &mut Property<G, T>,
+) where
     G: Scope,
     G: ScopeParent<Timestamp = AltNeu<T>>,
     T: Lattice + ExchangeData + Timestamp,
@@ -216,7 +218,7 @@ T(?y, ?pn, ?zn)
 =>
 T(?x, owl:sameAs, ?y)
  */
-fn prp_key<G, T>(
+pub(super) fn prp_key<G, T>(
     property_list: Vec<&Property<G, T>>,
     class: &Class<G, T>,
     same_as: &mut SameAs<G, T>,
@@ -225,43 +227,275 @@ fn prp_key<G, T>(
     G: ScopeParent<Timestamp = AltNeu<T>>,
     T: Lattice + ExchangeData + Timestamp,
 {
-    assert_eq!(
-        1,
-        property_list.len(),
-        "Multi-property keys not yet supported"
+    assert!(
+        !property_list.is_empty(),
+        "owl:hasKey needs at least one property"
     );
 
-    let p0 = property_list[0];
-
-    // TODO: Use common extender_alt() system to share underlying
-    let d_xp0 = lookup_map(
-        p0.stream(),
-        class.alt().clone(),
-        move |&(x, _z0), key| {
-            *key = x;
-        },
-        |&(x, z0), _diff, _, _sum| ((x, z0), Present),
-        Default::default(),
-        Default::default(),
-        Default::default(),
-    );
+    // The body atoms, in the order that decides which side of a delta sees
+    // the other atoms' current updates.
+    let atoms = [KEY_X, KEY_Y]
+        .iter()
+        .flat_map(|&subject| {
+            std::iter::once(KeyAtom {
+                subject,
+                property: None,
+            })
+            .chain((0..property_list.len()).map(move |i| KeyAtom {
+                subject,
+                property: Some(i),
+            }))
+        })
+        .collect::<Vec<_>>();
 
-    let d_xc = class
-        .stream()
-        .propose_using(&mut p0.by_s_alt().extend_using(|&x| x))
-        .concat(&d_xp0)
-        .propose_using(&mut p0.by_o_alt().extend_using(|&(_x, z0)| z0));
-    let d_xc = lookup_map(
-        &d_xc,
-        class.alt().clone(),
-        move |&((_x, _z0), y), key| {
-            *key = y;
-        },
-        |&((x, _z0), y), _diff, _, _sum| ((x, y), Present),
-        Default::default(),
-        Default::default(),
-        Default::default(),
-    );
+    for (k, delta) in atoms.iter().enumerate() {
+        let mut bound = vec![false; 2 + property_list.len()];
+        let empty = (0, 0, vec![0; property_list.len()]);
+        let subject = delta.subject;
+        let mut d_key = match delta.property {
+            None => class.stream().map(move |x| with(&empty, subject, x)),
+            Some(i) => property_list[i]
+                .stream()
+                .map(move |(x, z)| with(&with(&empty, subject, x), key_z(i), z)),
+        };
+        for variable in delta.variables() {
+            bound[variable] = true;
+        }
+
+        let mut remaining = (0..atoms.len()).filter(|&j| j != k).collect::<Vec<_>>();
+        while !remaining.is_empty() {
+            // Checks come first; otherwise a key value binds one new variable.
+            let unbound = |j: &usize| atoms[*j].variables().filter(|&v| !bound[v]).count();
+            let position = remaining
+                .iter()
+                .position(|j| unbound(j) == 0)
+                .or_else(|| {
+                    remaining
+                        .iter()
+                        .position(|j| atoms[*j].property.is_some() && unbound(j) == 1)
+                })
+                .expect("every key atom shares a variable with a bound one");
+            let j = remaining.remove(position);
+            // Atoms after the delta join through the `neu` indexes, which hold
+            // back its timestamp's updates so that no pair of them is joined
+            // twice; atoms before it join through the `alt` ones, which do not.
+            d_key = extend_key(d_key, atoms[j], &bound, j > k, &property_list, class);
+            for variable in atoms[j].variables() {
+                bound[variable] = true;
+            }
+        }
+
+        same_as.add(d_key.map(|(x, y, _z)| (x, y)));
+    }
+}
+
+/// A partial binding of `?x`, `?y` and the key values `?z1, ..., ?zn` of
+/// `prp_key`; unbound variables hold a placeholder.
+type KeyBinding = (IRI, IRI, Vec<IRI>);
+
+const KEY_X: usize = 0;
+const KEY_Y: usize = 1;
+
+fn key_z(i: usize) -> usize {
+    2 + i
+}
+
+/// `T(subject, rdf:type, ?c)` when `property` is `None`, otherwise
+/// `T(subject, ?p_i, ?z_i)`.
+#[derive(Clone, Copy)]
+struct KeyAtom {
+    subject: usize,
+    property: Option<usize>,
+}
+
+impl KeyAtom {
+    fn variables(&self) -> impl Iterator<Item = usize> {
+        std::iter::once(self.subject).chain(self.property.map(key_z))
+    }
+}
+
+fn get(binding: &KeyBinding, variable: usize) -> IRI {
+    match variable {
+        KEY_X => binding.0,
+        KEY_Y => binding.1,
+        z => binding.2[z - 2],
+    }
+}
+
+fn with(binding: &KeyBinding, variable: usize, value: IRI) -> KeyBinding {
+    let mut binding = binding.clone();
+    match variable {
+        KEY_X => binding.0 = value,
+        KEY_Y => binding.1 = value,
+        z => binding.2[z - 2] = value,
+    }
+    binding
+}
+
+/// Joins `bindings` with one more atom, validating it if all its variables
+/// are bound and proposing the missing one otherwise.
+fn extend_key<G, T>(
+    bindings: Collection<G, KeyBinding, Diff>,
+    atom: KeyAtom,
+    bound: &[bool],
+    neu: bool,
+    property_list: &[&Property<G, T>],
+    class: &Class<G, T>,
+) -> Collection<G, KeyBinding, Diff>
+where
+    G: Scope,
+    G: ScopeParent<Timestamp = AltNeu<T>>,
+    T: Lattice + ExchangeData + Timestamp,
+{
+    let subject = atom.subject;
+    let property = match atom.property {
+        None => {
+            let index = if neu {
+                class.extender_neu()
+            } else {
+                class.extender_alt()
+            };
+            return bindings
+                .map(|binding| (binding, ()))
+                .validate_using(
+                    &mut index.extend_using(move |binding: &KeyBinding| get(binding, subject)),
+                )
+                .map(|(binding, ())| binding);
+        }
+        Some(i) => property_list[i],
+    };
+    let object = key_z(atom.property.unwrap());
+    match (bound[subject], bound[object]) {
+        (true, true) => {
+            let index = if neu {
+                property.by_s_neu()
+            } else {
+                property.by_s_alt()
+            };
+            bindings
+                .map(move |binding| {
+                    let z = get(&binding, object);
+                    (binding, z)
+                })
+                .validate_using(
+                    &mut index.extend_using(move |binding: &KeyBinding| get(binding, subject)),
+                )
+                .map(|(binding, _z)| binding)
+        }
+        (true, false) => {
+            let index = if neu {
+                property.by_s_neu()
+            } else {
+                property.by_s_alt()
+            };
+            bindings
+                .propose_using(
+                    &mut index.extend_using(move |binding: &KeyBinding| get(binding, subject)),
+                )
+                .map(move |(binding, z)| with(&binding, object, z))
+        }
+        (false, true) => {
+            let index = if neu {
+                property.by_o_neu()
+            } else {
+                property.by_o_alt()
+            };
+            bindings
+                .propose_using(
+                    &mut index.extend_using(move |binding: &KeyBinding| get(binding, object)),
+                )
+                .map(move |(binding, x)| with(&binding, subject, x))
+        }
+        (false, false) => unreachable!("a key value atom with no bound variable"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::owl2rl::testing::{run_rule, Fact};
+    use std::collections::HashSet;
+
+    /// Class members and `(i, x, z)` values of key property `i` to insert in
+    /// one step.
+    type Step = (Vec<IRI>, Vec<(usize, IRI, IRI)>);
+
+    /// The distinct `(x, y)` pairs derived by `prp_key` after each step.
+    fn run_prp_key(properties: usize, steps: Vec<Step>) -> Vec<HashSet<(IRI, IRI)>> {
+        let steps = steps
+            .into_iter()
+            .map(|(members, values)| {
+                let members = members.into_iter().map(|x| Fact::Member(0, x));
+                let values = values.into_iter().map(|(i, x, z)| Fact::Pair(i, x, z));
+                members.chain(values).collect()
+            })
+            .collect();
+        run_rule(1, properties, steps, |relations| {
+            prp_key(
+                relations.properties.iter().collect(),
+                &relations.classes[0],
+                &mut relations.same_as,
+            );
+            relations.same_as.feedback_.drain(..).collect()
+        })
+        .into_iter()
+        .map(|derived| derived.into_iter().filter(|(x, y)| x != y).collect())
+        .collect()
+    }
 
-    same_as.add(d_xc);
+    fn pairs(pairs: &[(IRI, IRI)]) -> HashSet<(IRI, IRI)> {
+        pairs.iter().cloned().collect()
+    }
+
+    #[test]
+    fn single_property_key() {
+        let results = run_prp_key(1, vec![(vec![1, 2, 3], vec![(0, 1, 10), (0, 2, 10)])]);
+        assert_eq!(results[0], pairs(&[(1, 2), (2, 1)]));
+    }
+
+    #[test]
+    fn two_property_key_waits_for_last_value() {
+        let results = run_prp_key(
+            2,
+            vec![
+                (
+                    vec![1, 2, 3],
+                    vec![(0, 1, 10), (0, 2, 10), (0, 3, 10), (1, 1, 20), (1, 3, 21)],
+                ),
+                (vec![], vec![(1, 2, 20)]),
+            ],
+        );
+        assert_eq!(results[0], pairs(&[]));
+        assert_eq!(results[1], pairs(&[(1, 2), (2, 1)]));
+    }
+
+    #[test]
+    fn three_property_key_requires_class_membership() {
+        let results = run_prp_key(
+            3,
+            vec![
+                (
+                    vec![1, 2],
+                    vec![
+                        (0, 1, 10),
+                        (0, 2, 10),
+                        (0, 4, 10),
+                        (1, 1, 20),
+                        (1, 2, 20),
+                        (1, 4, 20),
+                        (2, 1, 30),
+                        (2, 4, 30),
+                    ],
+                ),
+                (vec![], vec![(2, 2, 30)]),
+                (vec![4], vec![]),
+            ],
+        );
+        assert_eq!(results[0], pairs(&[]));
+        assert_eq!(results[1], pairs(&[(1, 2), (2, 1)]));
+        assert_eq!(
+            results[2],
+            pairs(&[(1, 2), (2, 1), (1, 4), (4, 1), (2, 4), (4, 2)])
+        );
+    }
 }
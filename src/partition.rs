@@ -0,0 +1,139 @@
+use crate::dictionary::Dictionary;
+use crate::materializations::EncodedTriple;
+use crate::vocabulary::ids::{
+    DOMAIN, INVERSE_OF, RANGE, RDF_TYPE, SUB_CLASS_OF, SUB_PROPERTY_OF, TRANSITIVE_PROPERTY,
+};
+use crate::vocabulary::*;
+use differential_dataflow::lattice::Lattice;
+use differential_dataflow::operators::reduce::Threshold;
+use differential_dataflow::operators::Join;
+use differential_dataflow::Collection;
+use timely::dataflow::Scope;
+
+/// Schema predicates outside the fixed vocabulary: the list vocabulary
+/// (including `owl:members`, even for `owl:AllDifferent`) and the OWL class
+/// and property axioms.
+const SCHEMA_PREDICATES: [&str; 20] = [
+    RDF_FIRST,
+    RDF_REST,
+    OWL_PROPERTY_CHAIN_AXIOM,
+    OWL_INTERSECTION_OF,
+    OWL_UNION_OF,
+    OWL_ONE_OF,
+    OWL_HAS_KEY,
+    OWL_MEMBERS,
+    OWL_EQUIVALENT_CLASS,
+    OWL_EQUIVALENT_PROPERTY,
+    OWL_ON_PROPERTY,
+    OWL_SOME_VALUES_FROM,
+    OWL_ALL_VALUES_FROM,
+    OWL_HAS_VALUE,
+    OWL_COMPLEMENT_OF,
+    OWL_DISJOINT_WITH,
+    OWL_PROPERTY_DISJOINT_WITH,
+    OWL_MAX_CARDINALITY,
+    OWL_MAX_QUALIFIED_CARDINALITY,
+    OWL_ON_CLASS,
+];
+
+/// Meta-classes outside the fixed vocabulary: `owl:AllDisjointClasses` and
+/// the classes of classes, properties and ontologies.
+const META_CLASSES: [&str; 14] = [
+    OWL_ALL_DISJOINT_CLASSES,
+    OWL_CLASS,
+    RDFS_CLASS,
+    OWL_RESTRICTION,
+    RDFS_DATATYPE,
+    RDF_PROPERTY,
+    OWL_OBJECT_PROPERTY,
+    OWL_DATATYPE_PROPERTY,
+    OWL_FUNCTIONAL_PROPERTY,
+    OWL_INVERSE_FUNCTIONAL_PROPERTY,
+    OWL_SYMMETRIC_PROPERTY,
+    OWL_ASYMMETRIC_PROPERTY,
+    OWL_IRREFLEXIVE_PROPERTY,
+    OWL_ONTOLOGY,
+];
+
+/// The ids that tell TBox from ABox triples: those of the schema predicates
+/// and of the meta-classes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schema {
+    predicates: Vec<usize>,
+    meta_classes: Vec<usize>,
+}
+
+impl Default for Schema {
+    /// The schema of the fixed vocabulary, the only ids encoded input shares:
+    /// the RDFS predicates, `owl:inverseOf` and `owl:TransitiveProperty`.
+    fn default() -> Self {
+        Schema {
+            predicates: vec![SUB_CLASS_OF, SUB_PROPERTY_OF, DOMAIN, RANGE, INVERSE_OF],
+            meta_classes: vec![TRANSITIVE_PROPERTY],
+        }
+    }
+}
+
+impl Schema {
+    /// The fixed schema and the OWL schema terms, with the ids `dictionary`
+    /// gives them. Terms it does not know yet are interned, so resolving
+    /// dictionaries built the same way yields the same schema.
+    pub fn resolve(dictionary: &mut Dictionary) -> Self {
+        let mut schema = Schema::default();
+        schema
+            .predicates
+            .extend(SCHEMA_PREDICATES.iter().map(|term| dictionary.encode(term)));
+        schema
+            .meta_classes
+            .extend(META_CLASSES.iter().map(|term| dictionary.encode(term)));
+        schema
+    }
+
+    /// Whether `triple` belongs to the TBox by itself: it has a schema
+    /// predicate, or types its subject with a meta-class.
+    pub fn is_schema(&self, triple: &EncodedTriple) -> bool {
+        self.predicates.contains(&triple.1) || self.declares(triple)
+    }
+
+    /// Whether `triple` declares a class, property or ontology.
+    fn declares(&self, triple: &EncodedTriple) -> bool {
+        let (_s, p, o) = triple;
+        *p == RDF_TYPE && self.meta_classes.contains(o)
+    }
+}
+
+/// Splits `triples` into TBox and ABox triples. The triples `is_schema` holds
+/// for go to the TBox, and so do all the others about the subjects they
+/// declare, such as the labels of classes and properties. Everything else is
+/// an assertion. Being a dataflow operator, the split follows later
+/// insertions and retractions.
+pub fn partition<G>(
+    triples: &Collection<G, EncodedTriple>,
+    schema: &Schema,
+) -> (Collection<G, EncodedTriple>, Collection<G, EncodedTriple>)
+where
+    G: Scope,
+    G::Timestamp: Lattice,
+{
+    let axioms = {
+        let schema = schema.clone();
+        triples.filter(move |triple| schema.is_schema(triple))
+    };
+    let declared = {
+        let schema = schema.clone();
+        axioms
+            .filter(move |triple| schema.declares(triple))
+            .map(|(s, _p, _o)| s)
+            .distinct()
+    };
+    let others = {
+        let schema = schema.clone();
+        triples
+            .filter(move |triple| !schema.is_schema(triple))
+            .map(|triple| (triple.0, triple))
+    };
+    (
+        axioms.concat(&others.semijoin(&declared).map(|(_s, triple)| triple)),
+        others.antijoin(&declared).map(|(_s, triple)| triple),
+    )
+}
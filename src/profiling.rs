@@ -0,0 +1,315 @@
+//! Per-region profiling. Every stage of the materializations runs in a named
+//! region ("Tbox transitive rules", "CAX-SCO", ...); a `Profiler` listens to
+//! timely's operator logs and differential's arrangement logs and attributes
+//! what they report to the top-level region each operator belongs to.
+
+use crate::error::{Error, Result};
+use crate::logging::{self, child, FEEDBACK};
+use differential_dataflow::logging::DifferentialEvent;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::Duration;
+use timely::communication::Allocate;
+use timely::logging::{StartStop, TimelyEvent};
+use timely::worker::Worker;
+
+/// Where operators that are not inside a named region are reported.
+pub const OUTSIDE_REGIONS: &str = "(outside regions)";
+
+/// What the operators of one region did.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegionProfile {
+    pub region: String,
+    /// Time spent scheduling the region's operators.
+    pub elapsed: Duration,
+    /// Records sent along the region's dataflow edges.
+    pub records: usize,
+    /// Records held by the region's arrangements.
+    pub arranged: isize,
+    /// Batches sent around the feedback edges of the region's iterative
+    /// scopes, that is rounds of its fixpoints summed over timestamps.
+    pub iterations: usize,
+}
+
+/// How a profiling report is printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Table,
+    Json,
+}
+
+impl ReportFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReportFormat::Table => "table",
+            ReportFormat::Json => "json",
+        }
+    }
+
+    /// Renders `profiles` in this format, in the given order.
+    pub fn render(&self, profiles: &[RegionProfile]) -> String {
+        match self {
+            ReportFormat::Table => table(profiles),
+            ReportFormat::Json => json(profiles),
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "table" => Ok(ReportFormat::Table),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(Error::Config(format!("unknown report format {:?}", name))),
+        }
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What the logs told about one worker's operators so far.
+#[derive(Default)]
+pub(crate) struct Log {
+    /// Operator id to its address and name.
+    operators: HashMap<usize, (Vec<usize>, String)>,
+    /// Channel id to the address of the operator sending along it.
+    channels: HashMap<usize, Vec<usize>>,
+    started: HashMap<usize, Duration>,
+    elapsed: HashMap<usize, Duration>,
+    /// Channel id to the batches and records sent along it.
+    sent: HashMap<usize, (usize, usize)>,
+    arranged: HashMap<usize, isize>,
+}
+
+impl Log {
+    pub(crate) fn operates(&mut self, id: usize, addr: Vec<usize>, name: String) {
+        self.operators.insert(id, (addr, name));
+    }
+
+    /// A channel inside the scope at `scope` from the output of its child
+    /// `source`; child 0 stands for the scope's own inputs.
+    pub(crate) fn channel(&mut self, id: usize, scope: &[usize], source: usize) {
+        if source != 0 {
+            self.channels.insert(id, child(scope, source));
+        }
+    }
+
+    pub(crate) fn schedule(&mut self, id: usize, at: Duration, start: bool) {
+        if start {
+            self.started.insert(id, at);
+        } else if let Some(started) = self.started.remove(&id) {
+            *self.elapsed.entry(id).or_default() += at.saturating_sub(started);
+        }
+    }
+
+    pub(crate) fn sent(&mut self, channel: usize, records: usize) {
+        let sent = self.sent.entry(channel).or_default();
+        sent.0 += 1;
+        sent.1 += records;
+    }
+
+    pub(crate) fn arranged(&mut self, operator: usize, records: isize) {
+        *self.arranged.entry(operator).or_default() += records;
+    }
+
+    /// The profile of every top-level region, sorted by region name.
+    pub(crate) fn report(&self) -> Vec<RegionProfile> {
+        let names: HashMap<&[usize], &str> = self
+            .operators
+            .values()
+            .map(|(addr, name)| (&addr[..], &name[..]))
+            .collect();
+        // Scopes: operators with children. Their schedules include those of
+        // their children, so only the children's are counted.
+        let scopes: HashSet<&[usize]> =
+            logging::scopes(self.operators.values().map(|(addr, _name)| addr)).collect();
+        // Operators are attributed to the region among the dataflow's children.
+        let region = |addr: &[usize]| -> String {
+            if addr.len() > 2 && scopes.contains(&addr[..2]) {
+                names
+                    .get(&addr[..2])
+                    .unwrap_or(&OUTSIDE_REGIONS)
+                    .to_string()
+            } else {
+                OUTSIDE_REGIONS.to_string()
+            }
+        };
+
+        let mut profiles = Vec::new();
+        for (id, (addr, _name)) in self.operators.iter() {
+            let profile = entry(&mut profiles, region(addr));
+            if !scopes.contains(&addr[..]) {
+                profile.elapsed += self.elapsed.get(id).cloned().unwrap_or_default();
+            }
+            profile.arranged += self.arranged.get(id).cloned().unwrap_or_default();
+        }
+        for (channel, (batches, records)) in self.sent.iter() {
+            if let Some(addr) = self.channels.get(channel) {
+                let profile = entry(&mut profiles, region(addr));
+                profile.records += records;
+                if names.get(&addr[..]) == Some(&FEEDBACK) {
+                    profile.iterations += batches;
+                }
+            }
+        }
+
+        merge(vec![profiles])
+    }
+}
+
+fn entry(profiles: &mut Vec<RegionProfile>, region: String) -> &mut RegionProfile {
+    match profiles.iter().position(|profile| profile.region == region) {
+        Some(index) => &mut profiles[index],
+        None => {
+            profiles.push(RegionProfile {
+                region,
+                ..Default::default()
+            });
+            profiles.last_mut().unwrap()
+        }
+    }
+}
+
+/// Collects the logs of a worker's dataflows into `RegionProfile`s.
+pub struct Profiler {
+    log: Rc<RefCell<Log>>,
+}
+
+impl Profiler {
+    /// Starts listening to `worker`'s logs. Operators are only logged when
+    /// they are built, so this has to come before the dataflows to profile.
+    pub fn install<A: Allocate>(worker: &mut Worker<A>) -> Self {
+        let log = Rc::new(RefCell::new(Log::default()));
+
+        let timely_log = Rc::clone(&log);
+        logging::listen(worker, move |at, event| {
+            let mut log = timely_log.borrow_mut();
+            match event {
+                TimelyEvent::Operates(event) => log.operates(event.id, event.addr, event.name),
+                TimelyEvent::Channels(event) => {
+                    log.channel(event.id, &event.scope_addr, event.source.0)
+                }
+                TimelyEvent::Schedule(event) => {
+                    log.schedule(event.id, at, matches!(event.start_stop, StartStop::Start))
+                }
+                TimelyEvent::Messages(event) if event.is_send => {
+                    log.sent(event.channel, event.length)
+                }
+                _ => {}
+            }
+        });
+
+        let differential_log = Rc::clone(&log);
+        worker.log_register().insert::<DifferentialEvent, _>(
+            "differential/arrange",
+            move |_time, events| {
+                let mut log = differential_log.borrow_mut();
+                for (_at, _worker, event) in events.drain(..) {
+                    match event {
+                        DifferentialEvent::Batch(event) => {
+                            log.arranged(event.operator, event.length as isize)
+                        }
+                        DifferentialEvent::Merge(event) => {
+                            if let Some(length) = event.complete {
+                                log.arranged(
+                                    event.operator,
+                                    length as isize - (event.length1 + event.length2) as isize,
+                                )
+                            }
+                        }
+                        DifferentialEvent::Drop(event) => {
+                            log.arranged(event.operator, -(event.length as isize))
+                        }
+                        _ => {}
+                    }
+                }
+            },
+        );
+
+        Profiler { log }
+    }
+
+    /// This worker's profile so far, sorted by region name.
+    pub fn report<A: Allocate>(&self, worker: &mut Worker<A>) -> Vec<RegionProfile> {
+        worker.log_register().flush();
+        self.log.borrow().report()
+    }
+}
+
+/// Adds up the profiles of the same region, e.g. from several workers, and
+/// sorts them by region name.
+pub fn merge(reports: Vec<Vec<RegionProfile>>) -> Vec<RegionProfile> {
+    let mut merged: Vec<RegionProfile> = Vec::new();
+    for profile in reports.into_iter().flatten() {
+        match merged
+            .iter_mut()
+            .find(|merged| merged.region == profile.region)
+        {
+            Some(merged) => {
+                merged.elapsed += profile.elapsed;
+                merged.records += profile.records;
+                merged.arranged += profile.arranged;
+                merged.iterations += profile.iterations;
+            }
+            None => merged.push(profile),
+        }
+    }
+    merged.sort_by(|a, b| a.region.cmp(&b.region));
+    merged
+}
+
+fn table(profiles: &[RegionProfile]) -> String {
+    let width = profiles
+        .iter()
+        .map(|profile| profile.region.len())
+        .chain(Some("region".len()))
+        .max()
+        .unwrap_or_default();
+    let mut table = format!(
+        "{:<width$}  {:>12}  {:>12}  {:>12}  {:>10}\n",
+        "region",
+        "elapsed",
+        "records",
+        "arranged",
+        "iterations",
+        width = width
+    );
+    for profile in profiles {
+        table.push_str(&format!(
+            "{:<width$}  {:>12}  {:>12}  {:>12}  {:>10}\n",
+            profile.region,
+            format!("{:.3?}", profile.elapsed),
+            profile.records,
+            profile.arranged,
+            profile.iterations,
+            width = width
+        ));
+    }
+    table
+}
+
+fn json(profiles: &[RegionProfile]) -> String {
+    let entries: Vec<String> = profiles
+        .iter()
+        .map(|profile| {
+            format!(
+                "{{\"region\": \"{}\", \"elapsed_secs\": {:.6}, \"records\": {}, \"arranged\": {}, \"iterations\": {}}}",
+                profile.region.replace('\\', "\\\\").replace('"', "\\\""),
+                profile.elapsed.as_secs_f64(),
+                profile.records,
+                profile.arranged,
+                profile.iterations
+            )
+        })
+        .collect();
+    format!("[{}]\n", entries.join(", "))
+}
@@ -0,0 +1,502 @@
+use crate::error::{Error, Result};
+use crate::graphs::{self, EncodedQuad, GraphMode, DEFAULT_GRAPH};
+use crate::materializations::{rdfs, rdfspp, EncodedTriple};
+use crate::partition::{partition, Schema};
+use differential_dataflow::input::{Input, InputSession};
+use differential_dataflow::operators::arrange::{ArrangeBySelf, TraceAgent};
+use differential_dataflow::trace::implementations::ord::OrdKeySpine;
+use differential_dataflow::trace::{Cursor, TraceReader};
+use differential_dataflow::Collection;
+use std::fmt;
+use std::str::FromStr;
+use timely::communication::Allocate;
+use timely::dataflow::operators::probe::Handle;
+use timely::progress::frontier::AntichainRef;
+use timely::worker::Worker;
+
+pub type TripleInput = InputSession<usize, EncodedTriple, isize>;
+pub type TripleTrace = TraceAgent<OrdKeySpine<EncodedTriple, usize, isize>>;
+
+/// A triple pattern; `None` matches any term.
+pub type Pattern = (Option<usize>, Option<usize>, Option<usize>);
+
+pub fn matches(pattern: &Pattern, triple: &EncodedTriple) -> bool {
+    pattern.0.map_or(true, |s| s == triple.0)
+        && pattern.1.map_or(true, |p| p == triple.1)
+        && pattern.2.map_or(true, |o| o == triple.2)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    Rdfs,
+    Rdfspp,
+}
+
+impl Profile {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Profile::Rdfs => "rdfs",
+            Profile::Rdfspp => "rdfspp",
+        }
+    }
+}
+
+impl FromStr for Profile {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name {
+            "rdfs" => Ok(Profile::Rdfs),
+            "rdfspp" => Ok(Profile::Rdfspp),
+            _ => Err(Error::Config(format!("unknown expressivity {:?}", name))),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One worker's materialization dataflow: its inputs, the probes on its
+/// outputs, and traces of both the base input and the materialization.
+pub struct Reasoner {
+    pub(crate) profile: Profile,
+    pub(crate) graphs: GraphMode,
+    pub(crate) time: usize,
+    pub(crate) tbox_input: TripleInput,
+    pub(crate) abox_input: TripleInput,
+    pub(crate) mixed_input: TripleInput,
+    pub(crate) tbox_probe: Handle<usize>,
+    pub(crate) abox_probe: Handle<usize>,
+    pub(crate) tbox_input_trace: TripleTrace,
+    pub(crate) abox_input_trace: TripleTrace,
+    pub(crate) tbox_trace: TripleTrace,
+    pub(crate) abox_trace: TripleTrace,
+    /// How many closed timestamps before the latest one stay readable.
+    pub(crate) history: usize,
+    /// A timestamp that stays readable whatever the history window.
+    pub(crate) hold: Option<usize>,
+    /// The timestamp the traces are compacted to.
+    pub(crate) compacted: usize,
+}
+
+impl Reasoner {
+    pub fn new<A: Allocate>(worker: &mut Worker<A>, profile: Profile) -> Self {
+        Self::with_graphs(worker, profile, GraphMode::Union)
+    }
+
+    /// A reasoner whose ABox may hold quads, materialized according to `graphs`.
+    /// Triples inserted without a graph are in the default graph.
+    pub fn with_graphs<A: Allocate>(
+        worker: &mut Worker<A>,
+        profile: Profile,
+        graphs: GraphMode,
+    ) -> Self {
+        Self::with_schema(worker, profile, graphs, Schema::default())
+    }
+
+    /// A reasoner that routes the triples of its mixed input to the TBox or
+    /// the ABox by `schema`.
+    pub fn with_schema<A: Allocate>(
+        worker: &mut Worker<A>,
+        profile: Profile,
+        graphs: GraphMode,
+        schema: Schema,
+    ) -> Self {
+        let mut tbox_probe = Handle::new();
+        let mut abox_probe = Handle::new();
+
+        let (
+            tbox_input,
+            abox_input,
+            mixed_input,
+            tbox_input_trace,
+            abox_input_trace,
+            tbox_trace,
+            abox_trace,
+        ) = worker.dataflow::<usize, _, _>(|outer| {
+            let (abox_in, abox) = outer.new_collection::<EncodedTriple, isize>();
+            let (tbox_in, tbox) = outer.new_collection::<EncodedTriple, isize>();
+            let (mixed_in, mixed) = outer.new_collection::<EncodedTriple, isize>();
+
+            let (axioms, assertions) = partition(&mixed, &schema);
+            let tbox = tbox.concat(&axioms.map(|triple| {
+                let (s, p, o, _graph) = graphs::unscope(triple);
+                (s, p, o)
+            }));
+            let abox = abox.concat(&assertions);
+
+            let tbox_input_arr = tbox.arrange_by_self();
+            let abox_input_arr = abox.arrange_by_self();
+
+            let materialize =
+                |abox: &Collection<_, EncodedTriple, isize>, outer: &mut _| match profile {
+                    Profile::Rdfs => rdfs(&tbox, abox, outer),
+                    Profile::Rdfspp => rdfspp(&tbox, abox, outer),
+                };
+
+            let (tbox, abox) = match graphs {
+                GraphMode::PerGraph => materialize(&abox, outer),
+                GraphMode::Union => {
+                    let union = abox.map(|triple| {
+                        let (s, p, o, _graph) = graphs::unscope(triple);
+                        (s, p, o)
+                    });
+                    let (tbox, materialization) = materialize(&union, outer);
+                    let named = abox.filter(|triple| graphs::graph(triple) != DEFAULT_GRAPH);
+                    (tbox, materialization.concat(&named))
+                }
+            };
+
+            tbox.probe_with(&mut tbox_probe);
+            abox.probe_with(&mut abox_probe);
+
+            let tbox_arr = tbox.arrange_by_self();
+            let abox_arr = abox.arrange_by_self();
+
+            (
+                tbox_in,
+                abox_in,
+                mixed_in,
+                tbox_input_arr.trace,
+                abox_input_arr.trace,
+                tbox_arr.trace,
+                abox_arr.trace,
+            )
+        });
+
+        Reasoner {
+            profile,
+            graphs,
+            time: 0,
+            tbox_input,
+            abox_input,
+            mixed_input,
+            tbox_probe,
+            abox_probe,
+            tbox_input_trace,
+            abox_input_trace,
+            tbox_trace,
+            abox_trace,
+            history: 0,
+            hold: None,
+            compacted: 0,
+        }
+    }
+
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    pub fn graphs(&self) -> GraphMode {
+        self.graphs
+    }
+
+    /// Keeps as-of reads exact for the `window` closed timestamps before the
+    /// latest one. Older history is compacted away as timestamps close, so
+    /// the traces stay the size of the materialization; the default window
+    /// is `0`, which only keeps the latest closed timestamp readable.
+    pub fn set_history(&mut self, window: usize) {
+        self.history = window;
+        self.compact_traces();
+    }
+
+    /// Keeps as-of reads at `time` and later exact, whatever the history
+    /// window, until released with `None`. Only timestamps that are still
+    /// readable can be held.
+    pub fn hold(&mut self, time: Option<usize>) {
+        self.hold = time;
+        self.compact_traces();
+    }
+
+    /// The earliest timestamp as-of reads are exact for.
+    pub fn earliest(&self) -> usize {
+        self.compacted
+    }
+
+    /// The timestamp at which updates are currently being inserted.
+    pub fn time(&self) -> usize {
+        self.time
+    }
+
+    pub fn insert_tbox(&mut self, triple: EncodedTriple) {
+        self.tbox_input.insert(triple);
+    }
+
+    pub fn insert_abox(&mut self, triple: EncodedTriple) {
+        self.abox_input.insert(triple);
+    }
+
+    /// Inserts (`diff > 0`) or retracts (`diff < 0`) a TBox axiom at the current
+    /// timestamp. Like ABox updates, TBox updates may arrive at any timestamp,
+    /// and the next `advance` updates both closures incrementally.
+    pub fn update_tbox(&mut self, triple: EncodedTriple, diff: isize) {
+        self.tbox_input.update(triple, diff);
+    }
+
+    pub fn update_abox(&mut self, triple: EncodedTriple, diff: isize) {
+        self.abox_input.update(triple, diff);
+    }
+
+    /// Updates an ABox triple at `time`, a timestamp at or after the current
+    /// one, which stays open until it is closed in turn.
+    pub fn update_abox_at(&mut self, triple: EncodedTriple, time: usize, diff: isize) {
+        self.abox_input.update_at(triple, time, diff);
+    }
+
+    /// Inserts an ABox quad; fails if its ids are too large to carry its
+    /// graph, see `graphs::scope`.
+    pub fn insert_abox_quad(&mut self, quad: EncodedQuad) -> Result<()> {
+        self.update_abox_quad(quad, 1)
+    }
+
+    pub fn update_abox_quad(&mut self, quad: EncodedQuad, diff: isize) -> Result<()> {
+        let triple = graphs::scope(quad)?;
+        self.abox_input.update(triple, diff);
+        Ok(())
+    }
+
+    /// Inserts a triple of either box; the dataflow routes it to the TBox or
+    /// the ABox by the reasoner's `Schema`, see `partition::partition`.
+    pub fn insert(&mut self, triple: EncodedTriple) {
+        self.mixed_input.insert(triple);
+    }
+
+    pub fn update(&mut self, triple: EncodedTriple, diff: isize) {
+        self.mixed_input.update(triple, diff);
+    }
+
+    /// Inserts a quad of either box. TBox triples lose their graph, as the
+    /// TBox is shared by all graphs. Fails as `insert_abox_quad` does.
+    pub fn insert_quad(&mut self, quad: EncodedQuad) -> Result<()> {
+        self.update_quad(quad, 1)
+    }
+
+    pub fn update_quad(&mut self, quad: EncodedQuad, diff: isize) -> Result<()> {
+        let triple = graphs::scope(quad)?;
+        self.mixed_input.update(triple, diff);
+        Ok(())
+    }
+
+    /// Closes the current timestamp and steps the worker until both
+    /// materializations reflect every update inserted so far.
+    pub fn advance<A: Allocate>(&mut self, worker: &mut Worker<A>) {
+        self.advance_to(worker, self.time + 1);
+    }
+
+    pub fn advance_to<A: Allocate>(&mut self, worker: &mut Worker<A>, time: usize) {
+        self.close_to(time);
+        let (tbox_probe, abox_probe) = (&self.tbox_probe, &self.abox_probe);
+        worker.step_while(|| tbox_probe.less_than(&time) || abox_probe.less_than(&time));
+    }
+
+    /// Closes the current timestamp without waiting for the dataflow to
+    /// process it, and returns the closed timestamp.
+    pub fn close(&mut self) -> usize {
+        let closed = self.time;
+        self.close_to(closed + 1);
+        closed
+    }
+
+    /// Closes every timestamp before `time` without waiting for the dataflow
+    /// to process them.
+    pub fn close_until(&mut self, time: usize) {
+        if time > self.time {
+            self.close_to(time);
+        }
+    }
+
+    fn close_to(&mut self, time: usize) {
+        self.time = time;
+        self.tbox_input.advance_to(time);
+        self.tbox_input.flush();
+        self.abox_input.advance_to(time);
+        self.abox_input.flush();
+        self.mixed_input.advance_to(time);
+        self.mixed_input.flush();
+        self.compact_traces();
+    }
+
+    /// Advances the traces' compaction frontiers to the earliest timestamp
+    /// that has to stay readable.
+    fn compact_traces(&mut self) {
+        let mut frontier = self.time.saturating_sub(1).saturating_sub(self.history);
+        if let Some(hold) = self.hold {
+            frontier = frontier.min(hold);
+        }
+        if frontier > self.compacted {
+            self.compacted = frontier;
+            for trace in [
+                &mut self.tbox_input_trace,
+                &mut self.abox_input_trace,
+                &mut self.tbox_trace,
+                &mut self.abox_trace,
+            ]
+            .iter_mut()
+            {
+                compact(trace, frontier);
+            }
+        }
+    }
+
+    /// Whether both materializations are final for `time`.
+    pub fn is_complete(&self, time: usize) -> bool {
+        !self.tbox_probe.less_equal(&time) && !self.abox_probe.less_equal(&time)
+    }
+
+    /// Fails if `time` is earlier than `earliest`, as reads as of it would
+    /// see the compacted traces.
+    fn retained(&self, time: usize) -> Result<()> {
+        if time < self.compacted {
+            return Err(Error::Timestamp(format!(
+                "timestamp {} is no longer retained; the earliest is {}",
+                time, self.compacted
+            )));
+        }
+        Ok(())
+    }
+
+    /// This worker's share of the materialized TBox triples matching
+    /// `pattern`, as of the complete timestamp `time`. Fails if `time` is
+    /// earlier than `earliest`.
+    pub fn query_tbox(&mut self, pattern: &Pattern, time: usize) -> Result<Vec<EncodedTriple>> {
+        self.retained(time)?;
+        let mut triples = positive(accumulate(&mut self.tbox_trace, time + 1));
+        triples.retain(|triple| matches(pattern, triple));
+        Ok(triples)
+    }
+
+    /// This worker's share of the materialized ABox triples matching
+    /// `pattern`, as of the complete timestamp `time`. Fails if `time` is
+    /// earlier than `earliest`.
+    pub fn query_abox(&mut self, pattern: &Pattern, time: usize) -> Result<Vec<EncodedTriple>> {
+        self.retained(time)?;
+        let mut triples = positive(accumulate(&mut self.abox_trace, time + 1));
+        triples.retain(|triple| matches(pattern, triple));
+        Ok(triples)
+    }
+
+    /// This worker's share of the changes to the materialized TBox triples
+    /// matching `pattern` between the complete timestamps `from` and `to`:
+    /// `1` for triples derived at `to` but not at `from`, `-1` for triples
+    /// no longer derived. Fails if either is earlier than `earliest`.
+    pub fn diff_tbox(
+        &mut self,
+        pattern: &Pattern,
+        from: usize,
+        to: usize,
+    ) -> Result<Vec<(EncodedTriple, isize)>> {
+        self.retained(from.min(to))?;
+        let mut changes = changes(&mut self.tbox_trace, from + 1, to + 1);
+        changes.retain(|(triple, _diff)| matches(pattern, triple));
+        Ok(changes)
+    }
+
+    /// Like `diff_tbox`, for the materialized ABox.
+    pub fn diff_abox(
+        &mut self,
+        pattern: &Pattern,
+        from: usize,
+        to: usize,
+    ) -> Result<Vec<(EncodedTriple, isize)>> {
+        self.retained(from.min(to))?;
+        let mut changes = changes(&mut self.abox_trace, from + 1, to + 1);
+        changes.retain(|(triple, _diff)| matches(pattern, triple));
+        Ok(changes)
+    }
+
+    /// This worker's share of the materialized TBox.
+    pub fn tbox(&mut self) -> Vec<EncodedTriple> {
+        positive(accumulate(&mut self.tbox_trace, self.time))
+    }
+
+    /// This worker's share of the materialized ABox. Triples of named graphs
+    /// are encoded as by `graphs::scope`.
+    pub fn abox(&mut self) -> Vec<EncodedTriple> {
+        positive(accumulate(&mut self.abox_trace, self.time))
+    }
+
+    /// This worker's share of the materialized ABox, with the graph of every
+    /// triple.
+    pub fn abox_quads(&mut self) -> Vec<EncodedQuad> {
+        self.abox().into_iter().map(graphs::unscope).collect()
+    }
+}
+
+/// Lets `trace` forget the difference between timestamps before `time`,
+/// and merge the batches holding them.
+pub(crate) fn compact<K>(trace: &mut TraceAgent<OrdKeySpine<K, usize, isize>>, time: usize)
+where
+    K: Ord + Clone + 'static,
+{
+    trace.set_logical_compaction(AntichainRef::new(&[time]));
+    trace.set_physical_compaction(AntichainRef::new(&[time]));
+}
+
+/// Sums the updates in `trace` at times before `time` and returns the triples
+/// with a non-zero multiplicity.
+pub(crate) fn accumulate<K>(
+    trace: &mut TraceAgent<OrdKeySpine<K, usize, isize>>,
+    time: usize,
+) -> Vec<(K, isize)>
+where
+    K: Ord + Clone + 'static,
+{
+    let (mut cursor, storage) = trace.cursor();
+    let mut triples = Vec::new();
+    while cursor.key_valid(&storage) {
+        let mut count = 0;
+        cursor.map_times(&storage, |t, diff| {
+            if *t < time {
+                count += diff;
+            }
+        });
+        if count != 0 {
+            triples.push((cursor.key(&storage).clone(), count));
+        }
+        cursor.step_key(&storage);
+    }
+    triples
+}
+
+/// The keys of `trace` present before `to` but not before `from`, with `1`,
+/// and those present before `from` but no longer before `to`, with `-1`.
+fn changes<K>(
+    trace: &mut TraceAgent<OrdKeySpine<K, usize, isize>>,
+    from: usize,
+    to: usize,
+) -> Vec<(K, isize)>
+where
+    K: Ord + Clone + 'static,
+{
+    let (mut cursor, storage) = trace.cursor();
+    let mut changes = Vec::new();
+    while cursor.key_valid(&storage) {
+        let (mut before, mut after) = (0, 0);
+        cursor.map_times(&storage, |t, diff| {
+            if *t < from {
+                before += diff;
+            }
+            if *t < to {
+                after += diff;
+            }
+        });
+        match (before > 0, after > 0) {
+            (false, true) => changes.push((cursor.key(&storage).clone(), 1)),
+            (true, false) => changes.push((cursor.key(&storage).clone(), -1)),
+            _ => {}
+        }
+        cursor.step_key(&storage);
+    }
+    changes
+}
+
+fn positive(triples: Vec<(EncodedTriple, isize)>) -> Vec<EncodedTriple> {
+    triples
+        .into_iter()
+        .filter(|(_triple, count)| *count > 0)
+        .map(|(triple, _count)| triple)
+        .collect()
+}
@@ -0,0 +1,203 @@
+//! Replaying timestamped update streams, for benchmarks. The updates of a
+//! stream file are grouped by their timestamp, each group is one timestamp of
+//! the dataflow, and the replay records how long every timestamp took from
+//! being closed until the probes passed it.
+
+use crate::error::{Error, Result};
+use crate::load_encode_triples::StreamUpdate;
+use crate::materializations::EncodedTriple;
+use crate::reasoner::Reasoner;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use timely::communication::Allocate;
+use timely::worker::Worker;
+
+/// The updates of a stream at one of its timestamps, in milliseconds.
+pub type Batch = (usize, Vec<(EncodedTriple, isize)>);
+
+/// Groups the updates of the stream file at `path` by timestamp. Fails on
+/// the first timestamp before the one of the previous update.
+pub fn batches(
+    path: &str,
+    updates: impl IntoIterator<Item = Result<StreamUpdate<EncodedTriple>>>,
+) -> Result<Vec<Batch>> {
+    let mut batches: Vec<Batch> = Vec::new();
+    for update in updates {
+        let (line, time, triple, diff) = update?;
+        match batches.last_mut() {
+            Some((last, batch)) if *last == time => batch.push((triple, diff)),
+            Some((last, _batch)) if *last > time => {
+                return Err(Error::parse(
+                    path,
+                    line,
+                    format!("timestamp {} is before the previous one, {}", time, last),
+                ))
+            }
+            _ => batches.push((time, vec![(triple, diff)])),
+        }
+    }
+    Ok(batches)
+}
+
+/// How fast a stream is replayed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pace {
+    /// Every timestamp is closed right after the previous one.
+    AsFastAsPossible,
+    /// Timestamps are closed as far apart as their stream timestamps, divided
+    /// by the rate: `1.0` replays in real time, `2.0` twice as fast.
+    Scaled(f64),
+}
+
+/// End-to-end latencies, in buckets of powers of two microseconds.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// Bucket `i` counts latencies up to `2^i` microseconds.
+    buckets: Vec<usize>,
+    total: Duration,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, latency: Duration) {
+        let micros = (latency.as_micros() as u64).max(1);
+        // The smallest `i` with `micros <= 2^i`.
+        let bucket = (64 - (micros - 1).leading_zeros()) as usize;
+        if self.buckets.len() <= bucket {
+            self.buckets.resize(bucket + 1, 0);
+        }
+        self.buckets[bucket] += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+    }
+
+    /// Adds the latencies of `other`.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        if self.buckets.len() < other.buckets.len() {
+            self.buckets.resize(other.buckets.len(), 0);
+        }
+        for (count, other) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *count += other;
+        }
+        self.total += other.total;
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn count(&self) -> usize {
+        self.buckets.iter().sum()
+    }
+
+    pub fn mean(&self) -> Duration {
+        match self.count() {
+            0 => Duration::default(),
+            count => self.total / count as u32,
+        }
+    }
+
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// The upper bound of the bucket holding the `q` quantile, `q` being
+    /// between 0 and 1.
+    pub fn quantile(&self, q: f64) -> Duration {
+        let rank = ((self.count() as f64 * q).ceil() as usize).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Duration::from_micros(1 << bucket);
+            }
+        }
+        Duration::default()
+    }
+
+    /// A summary line, then one line per bucket from the first to the last
+    /// non-empty one, with a bar scaled to the fullest bucket.
+    pub fn render(&self) -> String {
+        let mut text = format!(
+            "timestamps {}, mean {:?}, p50 <= {:?}, p99 <= {:?}, max {:?}\n",
+            self.count(),
+            self.mean(),
+            self.quantile(0.5),
+            self.quantile(0.99),
+            self.max
+        );
+        let first = self.buckets.iter().position(|count| *count > 0);
+        let last = self.buckets.iter().rposition(|count| *count > 0);
+        if let (Some(first), Some(last)) = (first, last) {
+            let fullest = self.buckets.iter().max().cloned().unwrap_or(1);
+            for bucket in first..=last {
+                let count = self.buckets[bucket];
+                text.push_str(&format!(
+                    "<= {:>12?} {:>8} {}\n",
+                    Duration::from_micros(1 << bucket),
+                    count,
+                    "#".repeat((count * 50 + fullest - 1) / fullest)
+                ));
+            }
+        }
+        text
+    }
+}
+
+/// Replays `batches` into `reasoner`, TBox and ABox updates alike, paced
+/// from `start`, and returns the latency of every timestamp. Every worker
+/// has to call this with the same batches and start, but only worker 0
+/// inserts the updates and records latencies.
+pub fn replay<A: Allocate>(
+    worker: &mut Worker<A>,
+    reasoner: &mut Reasoner,
+    batches: &[Batch],
+    pace: Pace,
+    start: Instant,
+) -> LatencyHistogram {
+    let mut histogram = LatencyHistogram::default();
+    // Closed timestamps whose latency is not known yet.
+    let mut pending: VecDeque<(usize, Instant)> = VecDeque::new();
+    let first = batches.first().map_or(0, |(time, _batch)| *time);
+    for (time, batch) in batches {
+        if let Pace::Scaled(rate) = pace {
+            let elapsed = time.saturating_sub(first) as f64 / 1000.0;
+            let due = start + Duration::from_secs_f64(elapsed / rate);
+            let mut now = Instant::now();
+            while now < due {
+                worker.step_or_park(Some(due - now));
+                record(reasoner, &mut pending, &mut histogram);
+                now = Instant::now();
+            }
+        }
+        if 0 == worker.index() {
+            for &(triple, diff) in batch.iter() {
+                reasoner.update(triple, diff);
+            }
+        }
+        pending.push_back((reasoner.close(), Instant::now()));
+        worker.step();
+        record(reasoner, &mut pending, &mut histogram);
+    }
+    while !pending.is_empty() {
+        worker.step_or_park(Some(Duration::from_millis(1)));
+        record(reasoner, &mut pending, &mut histogram);
+    }
+    if 0 == worker.index() {
+        histogram
+    } else {
+        LatencyHistogram::default()
+    }
+}
+
+/// Records the latencies of the pending timestamps the probes have passed.
+fn record(
+    reasoner: &Reasoner,
+    pending: &mut VecDeque<(usize, Instant)>,
+    histogram: &mut LatencyHistogram,
+) {
+    while let Some((time, closed)) = pending.front() {
+        if !reasoner.is_complete(*time) {
+            break;
+        }
+        histogram.record(closed.elapsed());
+        pending.pop_front();
+    }
+}
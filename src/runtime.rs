@@ -0,0 +1,628 @@
+//! A reasoner running on worker threads of its own. `Runtime::start` spawns
+//! the timely workers, and any thread holding a `ReasonerHandle` feeds them
+//! batches of updates, waits until a batch is materialized and reads the
+//! materializations, without living inside `timely::execute`.
+
+use crate::error::{Error, Result};
+use crate::graphs::GraphMode;
+use crate::materializations::EncodedTriple;
+use crate::partition::Schema;
+use crate::reasoner::{Pattern, Profile, Reasoner};
+use crate::snapshot::Manifest;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, SystemTime};
+use timely::communication::{Allocate, WorkerGuards};
+use timely::worker::Worker;
+
+pub struct RuntimeConfig {
+    pub profile: Profile,
+    pub workers: usize,
+    /// How many timestamps before the latest one stay readable.
+    pub history: usize,
+    /// Routes the triples of mixed batches to the TBox or the ABox.
+    pub schema: Schema,
+}
+
+/// Checks that there is at least one worker, which timely needs.
+pub fn check_workers(workers: usize) -> Result<usize> {
+    if workers == 0 {
+        return Err(Error::Config(
+            "WORKERS must be a positive integer".to_string(),
+        ));
+    }
+    Ok(workers)
+}
+
+enum Command {
+    Update {
+        tbox: Vec<(EncodedTriple, isize)>,
+        abox: Vec<(EncodedTriple, isize)>,
+        mixed: Vec<(EncodedTriple, isize)>,
+    },
+    Query(Query),
+    /// Writes this worker's share of the materialization to a snapshot
+    /// directory, once the batches submitted before are materialized, and
+    /// replies with the sizes of its TBox and ABox.
+    Snapshot {
+        dir: String,
+        reply: Sender<Result<(usize, usize)>>,
+    },
+    Shutdown,
+}
+
+struct Query {
+    time: usize,
+    /// Reads the changes since this timestamp instead of the triples.
+    since: Option<usize>,
+    tbox: bool,
+    pattern: Pattern,
+    reply: Sender<Result<Vec<(EncodedTriple, isize)>>>,
+}
+
+impl Query {
+    /// The earliest timestamp the query reads.
+    fn earliest(&self) -> usize {
+        self.since.map_or(self.time, |since| since.min(self.time))
+    }
+}
+
+/// Hands out timestamps and forwards commands to every worker in timestamp
+/// order, so all input sessions close the same timestamps.
+struct Sequencer {
+    next_time: usize,
+    senders: Vec<Sender<Command>>,
+    /// When the readable timestamps were assigned.
+    assigned_at: Vec<(usize, SystemTime)>,
+}
+
+struct Progress {
+    /// Every timestamp below this one is final in both materializations.
+    completed: usize,
+    stopped: bool,
+    /// Tasks waiting for a timestamp to complete.
+    wakers: Vec<(usize, Waker)>,
+}
+
+struct Shared {
+    sequencer: Mutex<Sequencer>,
+    progress: Mutex<Progress>,
+    /// Signalled whenever `progress` changes.
+    advanced: Condvar,
+    profile: Profile,
+    workers: usize,
+    history: usize,
+}
+
+impl Shared {
+    fn complete(&self, completed: usize) {
+        let mut progress = self.progress.lock().unwrap();
+        if completed > progress.completed {
+            progress.completed = completed;
+            let mut waiting = Vec::new();
+            for (time, waker) in progress.wakers.drain(..) {
+                if time < completed {
+                    waker.wake();
+                } else {
+                    waiting.push((time, waker));
+                }
+            }
+            progress.wakers = waiting;
+            self.advanced.notify_all();
+        }
+    }
+
+    fn stop(&self) {
+        let mut progress = self.progress.lock().unwrap();
+        if !progress.stopped {
+            progress.stopped = true;
+            for (_time, waker) in progress.wakers.drain(..) {
+                waker.wake();
+            }
+            self.advanced.notify_all();
+            drop(progress);
+            for sender in self.sequencer.lock().unwrap().senders.iter() {
+                let _ = sender.send(Command::Shutdown);
+            }
+        }
+    }
+}
+
+/// Marks the runtime stopped when a worker exits, even by panicking, so that
+/// nobody waits for timestamps that will never complete.
+struct StopOnExit<'a>(&'a Shared);
+
+impl Drop for StopOnExit<'_> {
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+/// The worker threads of a reasoner. Dropping the runtime stops them.
+pub struct Runtime {
+    handle: ReasonerHandle,
+    guards: Option<WorkerGuards<Result<()>>>,
+}
+
+impl Runtime {
+    pub fn start(config: RuntimeConfig) -> Result<Runtime> {
+        Runtime::launch(config, None)
+    }
+
+    /// Like `start`, resuming from the snapshot in `dir`, e.g. one written by
+    /// `ReasonerHandle::snapshot`: the next batch is assigned the timestamp
+    /// the snapshot was taken at, and the restored materialization is
+    /// readable as of the one before.
+    pub fn restore(config: RuntimeConfig, dir: &str) -> Result<Runtime> {
+        let manifest = Manifest::read(dir)?;
+        manifest.check(config.profile)?;
+        Runtime::launch(config, Some((dir.to_string(), manifest)))
+    }
+
+    fn launch(config: RuntimeConfig, snapshot: Option<(String, Manifest)>) -> Result<Runtime> {
+        check_workers(config.workers)?;
+
+        let (senders, receivers): (Vec<_>, Vec<_>) = (0..config.workers).map(|_| channel()).unzip();
+        let receivers = Arc::new(Mutex::new(
+            receivers.into_iter().map(Some).collect::<Vec<_>>(),
+        ));
+
+        let shared = Arc::new(Shared {
+            sequencer: Mutex::new(Sequencer {
+                next_time: snapshot
+                    .as_ref()
+                    .map_or(0, |(_dir, manifest)| manifest.time),
+                senders,
+                assigned_at: Vec::new(),
+            }),
+            progress: Mutex::new(Progress {
+                completed: 0,
+                stopped: false,
+                wakers: Vec::new(),
+            }),
+            advanced: Condvar::new(),
+            profile: config.profile,
+            workers: config.workers,
+            history: config.history,
+        });
+
+        let (profile, history, schema) = (config.profile, config.history, config.schema);
+        let worker_shared = shared.clone();
+        let guards = timely::execute(timely::Config::process(config.workers), move |worker| {
+            let _stop = StopOnExit(&worker_shared);
+            let receiver = receivers.lock().unwrap()[worker.index()]
+                .take()
+                .expect("worker started twice");
+            run_worker(
+                worker,
+                profile,
+                history,
+                schema.clone(),
+                snapshot.as_ref(),
+                receiver,
+                &worker_shared,
+            )
+        })
+        .map_err(Error::Worker)?;
+
+        Ok(Runtime {
+            handle: ReasonerHandle { shared },
+            guards: Some(guards),
+        })
+    }
+
+    pub fn handle(&self) -> ReasonerHandle {
+        self.handle.clone()
+    }
+
+    /// Stops the workers once they have taken the commands sent so far, and
+    /// waits for them to exit.
+    pub fn shutdown(self) -> Result<()> {
+        self.handle.shared.stop();
+        self.join()
+    }
+
+    /// Blocks until the workers exit, which they only do once stopped.
+    pub fn join(mut self) -> Result<()> {
+        if let Some(guards) = self.guards.take() {
+            for result in guards.join() {
+                result.map_err(Error::Worker)??;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Runtime {
+    fn drop(&mut self) {
+        self.handle.shared.stop();
+    }
+}
+
+/// Feeds and reads a running reasoner from any thread. Handles are cheap to
+/// clone; timestamps are assigned in the order batches are submitted, across
+/// all handles.
+#[derive(Clone)]
+pub struct ReasonerHandle {
+    shared: Arc<Shared>,
+}
+
+impl ReasonerHandle {
+    /// Assigns the next timestamp to a batch of TBox and ABox updates and
+    /// returns it. Only worker 0 receives the triples; the others just close
+    /// the timestamp.
+    pub fn submit(
+        &self,
+        tbox: Vec<(EncodedTriple, isize)>,
+        abox: Vec<(EncodedTriple, isize)>,
+    ) -> usize {
+        self.send_update(tbox, abox, Vec::new())
+    }
+
+    /// Like `submit`, for a batch of updates to either box, which the
+    /// dataflow routes by the runtime's `Schema`.
+    pub fn submit_mixed(&self, updates: Vec<(EncodedTriple, isize)>) -> usize {
+        self.send_update(Vec::new(), Vec::new(), updates)
+    }
+
+    fn send_update(
+        &self,
+        tbox: Vec<(EncodedTriple, isize)>,
+        abox: Vec<(EncodedTriple, isize)>,
+        mixed: Vec<(EncodedTriple, isize)>,
+    ) -> usize {
+        let mut sequencer = self.shared.sequencer.lock().unwrap();
+        let time = sequencer.next_time;
+        sequencer.next_time += 1;
+        let earliest = time.saturating_sub(self.shared.history);
+        sequencer
+            .assigned_at
+            .retain(|(time, _at)| *time >= earliest);
+        sequencer.assigned_at.push((time, SystemTime::now()));
+        let mut update = Some(Command::Update { tbox, abox, mixed });
+        for sender in sequencer.senders.iter() {
+            let command = update.take().unwrap_or_else(|| Command::Update {
+                tbox: Vec::new(),
+                abox: Vec::new(),
+                mixed: Vec::new(),
+            });
+            let _ = sender.send(command);
+        }
+        time
+    }
+
+    /// Submits a batch of ABox insertions.
+    pub fn insert_abox(&self, triples: impl IntoIterator<Item = EncodedTriple>) -> usize {
+        self.submit(Vec::new(), triples.into_iter().map(|t| (t, 1)).collect())
+    }
+
+    /// Submits a batch of ABox deletions.
+    pub fn delete_abox(&self, triples: impl IntoIterator<Item = EncodedTriple>) -> usize {
+        self.submit(Vec::new(), triples.into_iter().map(|t| (t, -1)).collect())
+    }
+
+    /// Submits a batch of TBox insertions.
+    pub fn insert_tbox(&self, triples: impl IntoIterator<Item = EncodedTriple>) -> usize {
+        self.submit(triples.into_iter().map(|t| (t, 1)).collect(), Vec::new())
+    }
+
+    /// Submits a batch of TBox deletions.
+    pub fn delete_tbox(&self, triples: impl IntoIterator<Item = EncodedTriple>) -> usize {
+        self.submit(triples.into_iter().map(|t| (t, -1)).collect(), Vec::new())
+    }
+
+    /// Writes the materialization as of the latest timestamp to the snapshot
+    /// directory `dir` once it is final, and returns the timestamp the
+    /// snapshot resumes at, the next one. Every worker writes its own shards,
+    /// and the manifest is written once all of them are done.
+    pub fn snapshot(&self, dir: &str) -> Result<usize> {
+        let (reply, replies) = channel();
+        let time = {
+            let sequencer = self.shared.sequencer.lock().unwrap();
+            for sender in sequencer.senders.iter() {
+                let _ = sender.send(Command::Snapshot {
+                    dir: dir.to_string(),
+                    reply: reply.clone(),
+                });
+            }
+            sequencer.next_time
+        };
+        drop(reply);
+        let shares: Vec<_> = replies.iter().take(self.shared.workers).collect();
+        if shares.len() < self.shared.workers {
+            return Err(stopped());
+        }
+        let (mut tbox_size, mut abox_size) = (0, 0);
+        for share in shares {
+            let (tbox, abox) = share?;
+            tbox_size += tbox;
+            abox_size += abox;
+        }
+        Manifest::new(
+            self.shared.profile,
+            time,
+            self.shared.workers,
+            tbox_size,
+            abox_size,
+        )
+        .write(dir)?;
+        Ok(time)
+    }
+
+    /// The latest assigned timestamp, if any.
+    pub fn latest(&self) -> Option<usize> {
+        self.shared
+            .sequencer
+            .lock()
+            .unwrap()
+            .next_time
+            .checked_sub(1)
+    }
+
+    /// The first timestamp that is not final yet.
+    pub fn completed(&self) -> usize {
+        self.shared.progress.lock().unwrap().completed
+    }
+
+    /// Blocks until `time` is final in both materializations.
+    pub fn wait(&self, time: usize) -> Result<()> {
+        self.assigned(time)?;
+        let mut progress = self.shared.progress.lock().unwrap();
+        while progress.completed <= time {
+            if progress.stopped {
+                return Err(stopped());
+            }
+            progress = self.shared.advanced.wait(progress).unwrap();
+        }
+        Ok(())
+    }
+
+    /// Like `wait`, as a future, for callers running an async executor.
+    pub fn materialized(&self, time: usize) -> Materialized {
+        Materialized {
+            shared: self.shared.clone(),
+            time,
+            unassigned: self.assigned(time).err(),
+        }
+    }
+
+    /// The latest timestamp assigned at or before `instant`, if it is still
+    /// readable.
+    pub fn timestamp_at(&self, instant: SystemTime) -> Option<usize> {
+        let sequencer = self.shared.sequencer.lock().unwrap();
+        sequencer
+            .assigned_at
+            .iter()
+            .rev()
+            .find(|(_time, at)| *at <= instant)
+            .map(|(time, _at)| *time)
+    }
+
+    /// The triples of the materialized TBox, or ABox, matching `pattern` as
+    /// of `time`, gathered from every worker once `time` is final. Fails if
+    /// `time` is no longer retained.
+    pub fn query(&self, tbox: bool, pattern: Pattern, time: usize) -> Result<Vec<EncodedTriple>> {
+        let triples = self.read(tbox, pattern, None, time, false)?;
+        Ok(triples.into_iter().map(|(triple, _diff)| triple).collect())
+    }
+
+    /// Like `query`, as of `after` or, if it is no longer retained, of the
+    /// earliest timestamp that is: a read that sees at least the batches up
+    /// to `after`, however many were submitted since.
+    pub fn query_after(
+        &self,
+        tbox: bool,
+        pattern: Pattern,
+        after: usize,
+    ) -> Result<Vec<EncodedTriple>> {
+        let triples = self.read(tbox, pattern, None, after, true)?;
+        Ok(triples.into_iter().map(|(triple, _diff)| triple).collect())
+    }
+
+    /// The changes to the triples matching `pattern` from `since` to `time`:
+    /// those derived with `1` and those retracted with `-1`.
+    pub fn diff(
+        &self,
+        tbox: bool,
+        pattern: Pattern,
+        since: usize,
+        time: usize,
+    ) -> Result<Vec<(EncodedTriple, isize)>> {
+        self.read(tbox, pattern, Some(since), time, false)
+    }
+
+    /// Like `diff`, up to `after` or, as `query_after`, a later timestamp.
+    pub fn diff_after(
+        &self,
+        tbox: bool,
+        pattern: Pattern,
+        since: usize,
+        after: usize,
+    ) -> Result<Vec<(EncodedTriple, isize)>> {
+        self.read(tbox, pattern, Some(since), after, true)
+    }
+
+    fn assigned(&self, time: usize) -> Result<()> {
+        match self.latest() {
+            Some(latest) if time <= latest => Ok(()),
+            latest => Err(Error::Timestamp(format!(
+                "timestamp {} has not been assigned yet; the latest is {}",
+                time,
+                latest.map_or("none".to_string(), |latest| latest.to_string())
+            ))),
+        }
+    }
+
+    /// Reads as of `time` or, if `at_least` is set and `time` is no longer
+    /// retained, of the earliest retained timestamp.
+    fn read(
+        &self,
+        tbox: bool,
+        pattern: Pattern,
+        since: Option<usize>,
+        time: usize,
+        at_least: bool,
+    ) -> Result<Vec<(EncodedTriple, isize)>> {
+        for time in since.iter().chain(Some(&time)) {
+            self.assigned(*time)?;
+        }
+        let (reply, replies) = channel();
+        {
+            let sequencer = self.shared.sequencer.lock().unwrap();
+            // Workers compact at most up to `history` timestamps before the
+            // latest one, and hold the query's timestamps once they receive it.
+            let earliest = (sequencer.next_time - 1).saturating_sub(self.shared.history);
+            let time = if at_least { time.max(earliest) } else { time };
+            for time in since.iter().chain(Some(&time)) {
+                if *time < earliest {
+                    return Err(Error::Timestamp(format!(
+                        "timestamp {} is no longer retained; the earliest is {}",
+                        time, earliest
+                    )));
+                }
+            }
+            for sender in sequencer.senders.iter() {
+                let _ = sender.send(Command::Query(Query {
+                    time,
+                    since,
+                    tbox,
+                    pattern,
+                    reply: reply.clone(),
+                }));
+            }
+        }
+        drop(reply);
+        let shares: Vec<_> = replies.iter().take(self.shared.workers).collect();
+        if shares.len() < self.shared.workers {
+            return Err(stopped());
+        }
+        let mut triples = Vec::new();
+        for share in shares {
+            triples.extend(share?);
+        }
+        triples.sort_unstable();
+        Ok(triples)
+    }
+}
+
+fn stopped() -> Error {
+    Error::Worker("the workers have stopped".to_string())
+}
+
+/// Resolves once a timestamp is final in both materializations, or fails if
+/// the workers stop first.
+pub struct Materialized {
+    shared: Arc<Shared>,
+    time: usize,
+    unassigned: Option<Error>,
+}
+
+impl Future for Materialized {
+    type Output = Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Result<()>> {
+        if let Some(error) = self.unassigned.take() {
+            return Poll::Ready(Err(error));
+        }
+        let time = self.time;
+        let mut progress = self.shared.progress.lock().unwrap();
+        if progress.completed > time {
+            Poll::Ready(Ok(()))
+        } else if progress.stopped {
+            Poll::Ready(Err(stopped()))
+        } else {
+            progress.wakers.push((time, context.waker().clone()));
+            Poll::Pending
+        }
+    }
+}
+
+fn run_worker<A: Allocate>(
+    worker: &mut Worker<A>,
+    profile: Profile,
+    history: usize,
+    schema: Schema,
+    snapshot: Option<&(String, Manifest)>,
+    receiver: Receiver<Command>,
+    shared: &Shared,
+) -> Result<()> {
+    let mut reasoner = Reasoner::with_schema(worker, profile, GraphMode::Union, schema);
+    reasoner.set_history(history);
+    if let Some((dir, manifest)) = snapshot {
+        // Worker 0 loads every shard, as it receives every batch.
+        if worker.index() == 0 {
+            reasoner.restore(dir, manifest)?;
+        }
+        reasoner.close_until(manifest.time);
+    }
+    let mut pending: Vec<Query> = Vec::new();
+    let mut completed = 0;
+    loop {
+        loop {
+            match receiver.try_recv() {
+                Ok(Command::Update { tbox, abox, mixed }) => {
+                    for (triple, diff) in tbox {
+                        reasoner.update_tbox(triple, diff);
+                    }
+                    for (triple, diff) in abox {
+                        reasoner.update_abox(triple, diff);
+                    }
+                    for (triple, diff) in mixed {
+                        reasoner.update(triple, diff);
+                    }
+                    reasoner.close();
+                }
+                Ok(Command::Query(query)) => {
+                    pending.push(query);
+                    reasoner.hold(pending.iter().map(Query::earliest).min());
+                }
+                Ok(Command::Snapshot { dir, reply }) => {
+                    // Commands arrive in timestamp order, so every batch
+                    // submitted before the snapshot is in.
+                    let time = reasoner.time();
+                    reasoner.advance_to(worker, time);
+                    let written = reasoner
+                        .snapshot(&dir, worker.index())
+                        .map(|()| (reasoner.tbox().len(), reasoner.abox().len()));
+                    let _ = reply.send(written);
+                }
+                Ok(Command::Shutdown) | Err(TryRecvError::Disconnected) => return Ok(()),
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        worker.step_or_park(Some(Duration::from_millis(1)));
+
+        if worker.index() == 0 {
+            while completed < reasoner.time() && reasoner.is_complete(completed) {
+                completed += 1;
+            }
+            shared.complete(completed);
+        }
+
+        let mut index = 0;
+        while index < pending.len() {
+            let query = &pending[index];
+            if reasoner.is_complete(query.time.max(query.since.unwrap_or_default())) {
+                let query = pending.swap_remove(index);
+                let triples = match (query.since, query.tbox) {
+                    (Some(since), true) => reasoner.diff_tbox(&query.pattern, since, query.time),
+                    (Some(since), false) => reasoner.diff_abox(&query.pattern, since, query.time),
+                    (None, true) => reasoner.query_tbox(&query.pattern, query.time).map(present),
+                    (None, false) => reasoner.query_abox(&query.pattern, query.time).map(present),
+                };
+                let _ = query.reply.send(triples);
+            } else {
+                index += 1;
+            }
+        }
+        reasoner.hold(pending.iter().map(Query::earliest).min());
+    }
+}
+
+fn present(triples: Vec<EncodedTriple>) -> Vec<(EncodedTriple, isize)> {
+    triples.into_iter().map(|triple| (triple, 1)).collect()
+}
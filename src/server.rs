@@ -0,0 +1,526 @@
+use crate::dictionary::Dictionary;
+use crate::error::{Error, Result};
+use crate::load_encode_triples::{load3nt, parse3nt, term_end};
+use crate::materializations::EncodedTriple;
+use crate::partition::Schema;
+use crate::reasoner::{Pattern, Profile};
+use crate::runtime::{ReasonerHandle, Runtime, RuntimeConfig};
+use crate::sparql::parse_update;
+use std::collections::HashSet;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// How many connections are handled at once; further ones wait in a queue of
+/// `BACKLOG` connections, and the listener stops accepting while it is full.
+const HANDLERS: usize = 16;
+const BACKLOG: usize = 64;
+/// The largest request body accepted, in bytes; larger ones answer `413`.
+const MAX_BODY: usize = 64 << 20;
+/// How long a connection may take to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct ServerConfig {
+    /// Address to listen on; port `0` picks a free port.
+    pub address: String,
+    pub profile: Profile,
+    pub workers: usize,
+    /// N-Triples files loaded at timestamp `0`.
+    pub tbox_path: Option<String>,
+    pub abox_path: Option<String>,
+    /// How many timestamps before the latest one stay readable with `at` and
+    /// `since`; the diff endpoints need it to cover the `since` they are asked
+    /// for, so `0` leaves them nothing to read. `serve` defaults it to 16.
+    pub history: usize,
+}
+
+struct Shared {
+    dictionary: Mutex<Dictionary>,
+    /// The input triples of the TBox and of the ABox, so that updates only
+    /// insert absent triples and only delete present ones.
+    inputs: Mutex<Inputs>,
+    reasoner: ReasonerHandle,
+    stopped: AtomicBool,
+}
+
+#[derive(Default)]
+struct Inputs {
+    tbox: HashSet<EncodedTriple>,
+    abox: HashSet<EncodedTriple>,
+}
+
+/// A reasoner kept running behind an HTTP API.
+///
+/// * `POST /tbox/insert`, `/tbox/delete`, `/abox/insert`, `/abox/delete` take
+///   an N-Triples body;
+/// * `POST /update` takes a SPARQL Update with `INSERT DATA` and `DELETE DATA`,
+///   applied to the ABox unless `?box=tbox` is given;
+/// * `GET /tbox` and `GET /abox` return the materialized triples matching the
+///   optional `s`, `p` and `o` parameters, as N-Triples;
+/// * `GET /tbox/diff` and `GET /abox/diff` return the triples derived since
+///   the `since` timestamp prefixed with `+`, and those retracted with `-`,
+///   as long as `since` is within the `history` window;
+/// * `GET /status` reports the last assigned and the completed timestamps.
+///
+/// Updates answer with the timestamp they were assigned. A read with
+/// `after=<time>` waits until that timestamp is final and reads the
+/// materialization as of it or, once it is compacted away, as of the earliest
+/// retained timestamp, so it always sees at least the updates up to `time`.
+/// `at=<seconds>` reads it as of exactly the latest timestamp assigned by that
+/// Unix time. Without either, it waits for the latest update. Only the latest
+/// `history` timestamps before the latest one can be read exactly; older ones
+/// are compacted away.
+pub struct Server {
+    local_addr: SocketAddr,
+    shared: Arc<Shared>,
+    listener: Option<JoinHandle<()>>,
+    handlers: Vec<JoinHandle<()>>,
+    runtime: Option<Runtime>,
+}
+
+impl Server {
+    pub fn start(config: ServerConfig) -> Result<Server> {
+        let mut dictionary = Dictionary::new();
+        let tbox = load_initial(config.tbox_path.as_deref(), &mut dictionary)?;
+        let abox = load_initial(config.abox_path.as_deref(), &mut dictionary)?;
+
+        let listener = TcpListener::bind(&config.address)
+            .map_err(|source| Error::io(&config.address, source))?;
+        let local_addr = listener
+            .local_addr()
+            .map_err(|source| Error::io(&config.address, source))?;
+
+        let runtime = Runtime::start(RuntimeConfig {
+            profile: config.profile,
+            workers: config.workers,
+            history: config.history,
+            schema: Schema::default(),
+        })?;
+        let shared = Arc::new(Shared {
+            dictionary: Mutex::new(dictionary),
+            inputs: Mutex::new(Inputs::default()),
+            reasoner: runtime.handle(),
+            stopped: AtomicBool::new(false),
+        });
+        {
+            let mut inputs = shared.inputs.lock().unwrap();
+            let tbox = retain_changes(&mut inputs.tbox, tbox);
+            let abox = retain_changes(&mut inputs.abox, abox);
+            shared.reasoner.submit(tbox, abox);
+        }
+
+        let (sender, receiver) = mpsc::sync_channel(BACKLOG);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let handlers = (0..HANDLERS)
+            .map(|_| {
+                let (receiver, shared) = (receiver.clone(), shared.clone());
+                std::thread::spawn(move || handle_connections(&receiver, &shared))
+            })
+            .collect();
+        let listener_shared = shared.clone();
+        let listener = std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if listener_shared.stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    if sender.send(stream).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Server {
+            local_addr,
+            shared,
+            listener: Some(listener),
+            handlers,
+            runtime: Some(runtime),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Blocks until the server is shut down.
+    pub fn wait(mut self) -> Result<()> {
+        if let Some(listener) = self.listener.take() {
+            let _ = listener.join();
+        }
+        self.join_workers()
+    }
+
+    /// Stops accepting connections and waits for the workers to exit.
+    pub fn shutdown(mut self) -> Result<()> {
+        self.stop();
+        if let Some(listener) = self.listener.take() {
+            let _ = listener.join();
+        }
+        self.join_workers()
+    }
+
+    fn stop(&self) {
+        if !self.shared.stopped.swap(true, Ordering::SeqCst) {
+            // Wakes the listener up so it notices the flag.
+            let _ = TcpStream::connect(self.local_addr);
+        }
+    }
+
+    /// Shuts the runtime down, which fails the reads still waiting for it,
+    /// then waits for the connection handlers, which exit once the listener
+    /// has.
+    fn join_workers(&mut self) -> Result<()> {
+        let result = match self.runtime.take() {
+            Some(runtime) => runtime.shutdown(),
+            None => Ok(()),
+        };
+        for handler in self.handlers.drain(..) {
+            let _ = handler.join();
+        }
+        result
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn load_initial(
+    path: Option<&str>,
+    dictionary: &mut Dictionary,
+) -> Result<Vec<(EncodedTriple, isize)>> {
+    let mut triples = Vec::new();
+    if let Some(path) = path {
+        for triple in load3nt(path)? {
+            triples.push((dictionary.encode_triple(&triple?), 1));
+        }
+    }
+    Ok(triples)
+}
+
+struct Request {
+    method: String,
+    path: String,
+    params: Vec<(String, String)>,
+    body: String,
+}
+
+/// Handles the connections the listener queues until it exits.
+fn handle_connections(receiver: &Mutex<Receiver<TcpStream>>, shared: &Shared) {
+    loop {
+        let stream = match receiver.lock().unwrap().recv() {
+            Ok(stream) => stream,
+            Err(_) => return,
+        };
+        handle_connection(stream, shared);
+    }
+}
+
+fn handle_connection(stream: TcpStream, shared: &Shared) {
+    if stream.set_read_timeout(Some(READ_TIMEOUT)).is_err() {
+        return;
+    }
+    let mut reader = BufReader::new(match stream.try_clone() {
+        Ok(stream) => stream,
+        Err(_) => return,
+    });
+    let (status, body) = match read_request(&mut reader) {
+        Ok(request) => route(&request, shared),
+        Err(rejection) => rejection,
+    };
+    let mut stream = stream;
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    );
+}
+
+/// Reads a request, or the status and message to reject it with.
+fn read_request(reader: &mut impl BufRead) -> std::result::Result<Request, (u16, String)> {
+    let bad = |message: String| (400, message);
+    let mut line = String::new();
+    reader
+        .read_line(&mut line)
+        .map_err(|e| bad(e.to_string()))?;
+    let mut parts = line.split_whitespace();
+    let method = parts
+        .next()
+        .ok_or_else(|| bad("empty request".to_string()))?
+        .to_string();
+    let target = parts
+        .next()
+        .ok_or_else(|| bad("missing request target".to_string()))?;
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader
+            .read_line(&mut header)
+            .map_err(|e| bad(e.to_string()))?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| bad("invalid Content-Length".to_string()))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY {
+        return Err((413, format!("the body must not exceed {} bytes", MAX_BODY)));
+    }
+    let mut body = vec![0; content_length];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| bad(e.to_string()))?;
+
+    let params = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((percent_decode(key)?, percent_decode(value)?))
+        })
+        .collect::<std::result::Result<_, String>>()
+        .map_err(bad)?;
+
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        params,
+        body: String::from_utf8(body).map_err(|_| bad("body is not UTF-8".to_string()))?,
+    })
+}
+
+impl Request {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn route(request: &Request, shared: &Shared) -> (u16, String) {
+    let result = match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/tbox/insert") => update_nt(shared, &request.body, true, 1),
+        ("POST", "/tbox/delete") => update_nt(shared, &request.body, true, -1),
+        ("POST", "/abox/insert") => update_nt(shared, &request.body, false, 1),
+        ("POST", "/abox/delete") => update_nt(shared, &request.body, false, -1),
+        ("POST", "/update") => {
+            update_sparql(shared, &request.body, request.param("box") == Some("tbox"))
+        }
+        ("GET", "/tbox") => query(shared, true, false, request),
+        ("GET", "/abox") => query(shared, false, false, request),
+        ("GET", "/tbox/diff") => query(shared, true, true, request),
+        ("GET", "/abox/diff") => query(shared, false, true, request),
+        ("GET", "/status") => Ok(format!(
+            "{{\"time\":{},\"completed\":{}}}",
+            shared.reasoner.latest().unwrap_or_default(),
+            shared.reasoner.completed()
+        )),
+        _ => return (404, "not found".to_string()),
+    };
+    match result {
+        Ok(body) => (200, body),
+        Err(message) => (400, message),
+    }
+}
+
+fn update_nt(
+    shared: &Shared,
+    body: &str,
+    tbox: bool,
+    diff: isize,
+) -> std::result::Result<String, String> {
+    let mut triples = Vec::new();
+    for (index, line) in body.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let triple =
+            parse3nt(line).map_err(|message| format!("line {}: {}", index + 1, message))?;
+        triples.push((triple, diff));
+    }
+    Ok(submit(shared, triples, tbox))
+}
+
+fn update_sparql(shared: &Shared, body: &str, tbox: bool) -> std::result::Result<String, String> {
+    Ok(submit(shared, parse_update(body)?, tbox))
+}
+
+fn submit(shared: &Shared, triples: Vec<((String, String, String), isize)>, tbox: bool) -> String {
+    let encoded = {
+        let mut dictionary = shared.dictionary.lock().unwrap();
+        triples
+            .iter()
+            .map(|(triple, diff)| (dictionary.encode_triple(triple), *diff))
+            .collect()
+    };
+    // Submitting under the lock keeps the inputs in the order of the
+    // timestamps the updates are assigned.
+    let mut inputs = shared.inputs.lock().unwrap();
+    let time = if tbox {
+        let encoded = retain_changes(&mut inputs.tbox, encoded);
+        shared.reasoner.submit(encoded, Vec::new())
+    } else {
+        let encoded = retain_changes(&mut inputs.abox, encoded);
+        shared.reasoner.submit(Vec::new(), encoded)
+    };
+    format!("{{\"time\":{}}}", time)
+}
+
+/// Applies `updates` in order to the set `input`, and keeps those that change
+/// it: insertions of absent triples and deletions of present ones. This gives
+/// the input set semantics, as SPARQL Update has, where the reasoner's input
+/// would count multiplicities.
+fn retain_changes(
+    input: &mut HashSet<EncodedTriple>,
+    updates: Vec<(EncodedTriple, isize)>,
+) -> Vec<(EncodedTriple, isize)> {
+    updates
+        .into_iter()
+        .filter(|(triple, diff)| {
+            if *diff > 0 {
+                input.insert(*triple)
+            } else {
+                input.remove(triple)
+            }
+        })
+        .collect()
+}
+
+fn query(
+    shared: &Shared,
+    tbox: bool,
+    changes: bool,
+    request: &Request,
+) -> std::result::Result<String, String> {
+    let timestamp = |time: &str| -> std::result::Result<usize, String> {
+        time.parse()
+            .map_err(|_| format!("invalid timestamp {:?}", time))
+    };
+    // `after` and the default only bound the timestamp from below, so they
+    // may read a later one than was compacted away; `at` names an exact one.
+    let (time, exact) = match (request.param("after"), request.param("at")) {
+        (Some(_), Some(_)) => return Err("after and at are mutually exclusive".to_string()),
+        (Some(time), None) => (timestamp(time)?, false),
+        (None, Some(seconds)) => {
+            let seconds: u64 = seconds
+                .parse()
+                .map_err(|_| format!("invalid Unix time {:?}", seconds))?;
+            let time = shared
+                .reasoner
+                .timestamp_at(UNIX_EPOCH + Duration::from_secs(seconds))
+                .ok_or_else(|| {
+                    format!("no timestamp assigned by {} is retained anymore", seconds)
+                })?;
+            (time, true)
+        }
+        (None, None) => (shared.reasoner.latest().unwrap_or_default(), false),
+    };
+    let since = match request.param("since") {
+        Some(since) if changes => Some(timestamp(since)?),
+        None if changes => return Err("missing since".to_string()),
+        _ => None,
+    };
+
+    let mut pattern: Pattern = (None, None, None);
+    {
+        let dictionary = shared.dictionary.lock().unwrap();
+        for (name, slot) in [
+            ("s", &mut pattern.0),
+            ("p", &mut pattern.1),
+            ("o", &mut pattern.2),
+        ] {
+            if let Some(term) = request.param(name) {
+                let term = term.trim();
+                if term_end(term)? != term.len() {
+                    return Err(format!("{} must be a single N-Triples term", name));
+                }
+                match dictionary.get(term) {
+                    Some(id) => *slot = Some(id),
+                    // A term that was never inserted matches nothing.
+                    None => return Ok(String::new()),
+                }
+            }
+        }
+    }
+
+    let reasoner = &shared.reasoner;
+    let present = |triples: Vec<EncodedTriple>| -> Vec<(EncodedTriple, isize)> {
+        triples.into_iter().map(|triple| (triple, 1)).collect()
+    };
+    let triples = match (since, exact) {
+        (Some(since), true) => reasoner.diff(tbox, pattern, since, time),
+        (Some(since), false) => reasoner.diff_after(tbox, pattern, since, time),
+        (None, true) => reasoner.query(tbox, pattern, time).map(present),
+        (None, false) => reasoner.query_after(tbox, pattern, time).map(present),
+    }
+    .map_err(|error| error.to_string())?;
+    let dictionary = shared.dictionary.lock().unwrap();
+    let mut body = String::new();
+    for ((s, p, o), diff) in triples {
+        let sign = match (since, diff > 0) {
+            (None, _) => "",
+            (Some(_), true) => "+ ",
+            (Some(_), false) => "- ",
+        };
+        body.push_str(&format!(
+            "{}{} {} {} .\n",
+            sign,
+            dictionary.decode(s).unwrap_or("_:unknown"),
+            dictionary.decode(p).unwrap_or("_:unknown"),
+            dictionary.decode(o).unwrap_or("_:unknown")
+        ));
+    }
+    Ok(body)
+}
+
+fn percent_decode(text: &str) -> std::result::Result<String, String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut input = text.bytes();
+    while let Some(byte) = input.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex: Vec<u8> = input.by_ref().take(2).collect();
+                if hex.len() != 2 || !hex.iter().all(u8::is_ascii_hexdigit) {
+                    return Err(format!("invalid escape in {:?}", text));
+                }
+                let hex = std::str::from_utf8(&hex).unwrap();
+                bytes.push(u8::from_str_radix(hex, 16).unwrap());
+            }
+            byte => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| format!("{:?} is not UTF-8", text))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        _ => "Error",
+    }
+}
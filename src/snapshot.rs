@@ -0,0 +1,186 @@
+use crate::error::{Error, Result};
+use crate::load_encode_triples::{load3enc, read_file};
+use crate::materializations::EncodedTriple;
+use crate::reasoner::{accumulate, Profile, Reasoner};
+use crate::vocabulary::VOCABULARY;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+const MANIFEST: &str = "manifest";
+pub const DICTIONARY: &str = "dictionary.kv";
+
+/// Describes a snapshot directory. It is written last, so a directory without
+/// one holds an incomplete snapshot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    pub profile: Profile,
+    pub time: usize,
+    pub workers: usize,
+    pub tbox_size: usize,
+    pub abox_size: usize,
+    pub vocabulary: Vec<String>,
+}
+
+impl Manifest {
+    pub fn new(
+        profile: Profile,
+        time: usize,
+        workers: usize,
+        tbox_size: usize,
+        abox_size: usize,
+    ) -> Self {
+        Manifest {
+            profile,
+            time,
+            workers,
+            tbox_size,
+            abox_size,
+            vocabulary: VOCABULARY.iter().map(|term| term.to_string()).collect(),
+        }
+    }
+
+    pub fn write(&self, dir: &str) -> Result<()> {
+        let path = Path::new(dir).join(MANIFEST);
+        let mut contents = format!(
+            "profile {}\ntime {}\nworkers {}\ntbox_size {}\nabox_size {}\n",
+            self.profile, self.time, self.workers, self.tbox_size, self.abox_size
+        );
+        for term in self.vocabulary.iter() {
+            contents.push_str(&format!("vocabulary {}\n", term));
+        }
+        fs::write(&path, contents).map_err(|source| Error::io(&path, source))
+    }
+
+    pub fn read(dir: &str) -> Result<Self> {
+        let path = Path::new(dir).join(MANIFEST).display().to_string();
+        let mut manifest = Manifest::new(Profile::Rdfspp, 0, 0, 0, 0);
+        manifest.vocabulary.clear();
+        let mut profile = None;
+        for line in read_file(&path)? {
+            let (number, line) = line?;
+            let (key, value) = line.split_once(' ').unwrap_or((&line, ""));
+            let value = value.trim();
+            let number_value = || {
+                value.parse().map_err(|_| {
+                    Error::parse(&path, number, format!("invalid {} {:?}", key, value))
+                })
+            };
+            match key {
+                "profile" => {
+                    profile =
+                        Some(value.parse().map_err(|error: Error| {
+                            Error::parse(&path, number, error.to_string())
+                        })?)
+                }
+                "time" => manifest.time = number_value()?,
+                "workers" => manifest.workers = number_value()?,
+                "tbox_size" => manifest.tbox_size = number_value()?,
+                "abox_size" => manifest.abox_size = number_value()?,
+                "vocabulary" => manifest.vocabulary.push(value.to_string()),
+                _ => {
+                    return Err(Error::parse(
+                        &path,
+                        number,
+                        format!("unknown entry {:?}", key),
+                    ))
+                }
+            }
+        }
+        manifest.profile =
+            profile.ok_or_else(|| Error::Snapshot(format!("{}: missing profile", path)))?;
+        Ok(manifest)
+    }
+
+    /// Fails unless the snapshot can be resumed by a reasoner running `profile`
+    /// with the current vocabulary encoding.
+    pub fn check(&self, profile: Profile) -> Result<()> {
+        if self.profile != profile {
+            return Err(Error::Snapshot(format!(
+                "taken with expressivity {}, but {} was requested",
+                self.profile, profile
+            )));
+        }
+        if self
+            .vocabulary
+            .iter()
+            .map(|term| &term[..])
+            .ne(VOCABULARY.iter().cloned())
+        {
+            return Err(Error::Snapshot(
+                "vocabulary encoding differs from this version of the reasoner".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn shard_path(dir: &str, name: &str, worker: usize) -> String {
+    Path::new(dir)
+        .join(format!("{}.{}.ntenc", name, worker))
+        .display()
+        .to_string()
+}
+
+fn write_shard(path: &str, triples: Vec<(EncodedTriple, isize)>) -> Result<()> {
+    let file = File::create(path).map_err(|source| Error::io(path, source))?;
+    let mut writer = BufWriter::new(file);
+    for ((s, p, o), count) in triples {
+        if count < 0 {
+            return Err(Error::Snapshot(format!(
+                "{}: {} {} {} has a negative multiplicity",
+                path, s, p, o
+            )));
+        }
+        for _ in 0..count {
+            writeln!(writer, "{} {} {}", s, p, o).map_err(|source| Error::io(path, source))?;
+        }
+    }
+    writer.flush().map_err(|source| Error::io(path, source))
+}
+
+impl Reasoner {
+    /// Writes this worker's share of the base input and of the materialization
+    /// to `dir`. Every worker writes its own shards; the manifest is written
+    /// once all of them are done.
+    pub fn snapshot(&mut self, dir: &str, worker: usize) -> Result<()> {
+        fs::create_dir_all(dir).map_err(|source| Error::io(dir, source))?;
+        let time = self.time;
+        write_shard(
+            &shard_path(dir, "tbox.input", worker),
+            accumulate(&mut self.tbox_input_trace, time),
+        )?;
+        write_shard(
+            &shard_path(dir, "abox.input", worker),
+            accumulate(&mut self.abox_input_trace, time),
+        )?;
+        write_shard(
+            &shard_path(dir, "tbox", worker),
+            accumulate(&mut self.tbox_trace, time),
+        )?;
+        write_shard(
+            &shard_path(dir, "abox", worker),
+            accumulate(&mut self.abox_trace, time),
+        )
+    }
+
+    /// Resumes from the snapshot in `dir`: the base input of every shard is
+    /// inserted into the rules' inputs at the current timestamp, so that once
+    /// it closes the rules hold the same state as the reasoner the snapshot
+    /// was taken from, and every later update costs what it would have cost
+    /// there. The rules' arrangements can only be rebuilt by running them, so
+    /// the restore timestamp derives the closure of the base input anew; its
+    /// size can then be checked against the manifest.
+    pub fn restore(&mut self, dir: &str, manifest: &Manifest) -> Result<()> {
+        manifest.check(self.profile)?;
+        for worker in 0..manifest.workers {
+            for triple in load3enc(&shard_path(dir, "tbox.input", worker))? {
+                self.tbox_input.insert(triple?);
+            }
+            for triple in load3enc(&shard_path(dir, "abox.input", worker))? {
+                self.abox_input.insert(triple?);
+            }
+        }
+        Ok(())
+    }
+}
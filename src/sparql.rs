@@ -0,0 +1,177 @@
+use crate::load_encode_triples::term_end;
+use crate::vocabulary::RDF_TYPE;
+use std::collections::HashMap;
+
+/// A triple, in N-Triples lexical form, together with whether it is inserted
+/// (`1`) or deleted (`-1`).
+pub type TripleUpdate = ((String, String, String), isize);
+
+/// Parses a SPARQL 1.1 Update request made of `PREFIX` declarations and
+/// `INSERT DATA` / `DELETE DATA` operations separated by `;`.
+pub fn parse_update(text: &str) -> Result<Vec<TripleUpdate>, String> {
+    let mut prefixes = HashMap::new();
+    let mut updates = Vec::new();
+    let mut rest = skip_space(text);
+    while !rest.is_empty() {
+        let (keyword, tail) = next_word(rest);
+        match &keyword.to_ascii_uppercase()[..] {
+            ";" => rest = tail,
+            "PREFIX" => {
+                let (name, tail) = next_word(tail);
+                let name = name
+                    .strip_suffix(':')
+                    .ok_or_else(|| format!("invalid prefix name {:?}", name))?;
+                let tail = skip_space(tail);
+                if !tail.starts_with('<') {
+                    return Err(format!("expected an IRI after PREFIX {}:", name));
+                }
+                let end = term_end(tail)?;
+                prefixes.insert(name.to_string(), tail[1..end - 1].to_string());
+                rest = &tail[end..];
+            }
+            "INSERT" | "DELETE" => {
+                let diff = if keyword.eq_ignore_ascii_case("INSERT") {
+                    1
+                } else {
+                    -1
+                };
+                let (data, tail) = next_word(tail);
+                if !data.eq_ignore_ascii_case("DATA") {
+                    return Err(format!("only {} DATA is supported", keyword));
+                }
+                rest = parse_block(tail, &prefixes, diff, &mut updates)?;
+            }
+            _ => return Err(format!("unexpected {:?}", keyword)),
+        }
+        rest = skip_space(rest);
+    }
+    Ok(updates)
+}
+
+fn parse_block<'a>(
+    text: &'a str,
+    prefixes: &HashMap<String, String>,
+    diff: isize,
+    updates: &mut Vec<TripleUpdate>,
+) -> Result<&'a str, String> {
+    let mut rest = skip_space(text)
+        .strip_prefix('{')
+        .ok_or_else(|| "expected '{'".to_string())?;
+    loop {
+        rest = skip_space(rest);
+        if let Some(tail) = rest.strip_prefix('}') {
+            return Ok(tail);
+        }
+        if let Some(tail) = rest.strip_prefix('.') {
+            rest = tail;
+            continue;
+        }
+        let (s, tail) = next_term(rest, prefixes)?;
+        rest = parse_predicates(tail, &s, prefixes, &mut |triple| {
+            updates.push((triple, diff))
+        })?;
+    }
+}
+
+/// Parses the predicate-object list of `subject` up to the `.` or `}` that
+/// ends it, which is left in place. Predicate-object lists (`;`) and object
+/// lists (`,`) share the subject.
+pub(crate) fn parse_predicates<'a>(
+    text: &'a str,
+    subject: &str,
+    prefixes: &HashMap<String, String>,
+    emit: &mut impl FnMut((String, String, String)),
+) -> Result<&'a str, String> {
+    let mut rest = text;
+    'predicates: loop {
+        let (p, tail) = next_term(rest, prefixes)?;
+        rest = tail;
+        loop {
+            let (o, tail) = next_term(rest, prefixes)?;
+            emit((subject.to_string(), p.clone(), o));
+            rest = skip_space(tail);
+            if let Some(tail) = rest.strip_prefix(',') {
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix(';') {
+                rest = skip_space(tail);
+                if rest.starts_with('.') || rest.starts_with('}') {
+                    return Ok(rest);
+                }
+                continue 'predicates;
+            } else {
+                return Ok(rest);
+            }
+        }
+    }
+}
+
+pub(crate) fn next_term<'a>(
+    text: &'a str,
+    prefixes: &HashMap<String, String>,
+) -> Result<(String, &'a str), String> {
+    let text = skip_space(text);
+    match text.chars().next() {
+        None => Err("unexpected end of input".to_string()),
+        Some('<') | Some('_') => {
+            let end = term_end(text)?;
+            Ok((text[..end].to_string(), &text[end..]))
+        }
+        Some('"') => {
+            // Literals may carry a prefixed datatype, which `term_end` does not know.
+            let end = term_end(text)?;
+            let (literal, tail) = text.split_at(end);
+            match literal.rsplit_once("^^") {
+                Some((lexical, datatype)) if !datatype.starts_with('<') => Ok((
+                    format!("{}^^{}", lexical, expand(datatype, prefixes)?),
+                    tail,
+                )),
+                _ => Ok((literal.to_string(), tail)),
+            }
+        }
+        Some(_) => {
+            let end = text
+                .find(|c: char| c.is_whitespace() || "{};,".contains(c))
+                .unwrap_or(text.len());
+            let mut name = &text[..end];
+            let mut tail = &text[end..];
+            if let Some(stripped) = name.strip_suffix('.') {
+                name = stripped;
+                tail = &text[end - 1..];
+            }
+            if name == "a" {
+                Ok((RDF_TYPE.to_string(), tail))
+            } else {
+                Ok((expand(name, prefixes)?, tail))
+            }
+        }
+    }
+}
+
+fn expand(name: &str, prefixes: &HashMap<String, String>) -> Result<String, String> {
+    let (prefix, local) = name
+        .split_once(':')
+        .ok_or_else(|| format!("invalid term {:?}", name))?;
+    prefixes
+        .get(prefix)
+        .map(|namespace| format!("<{}{}>", namespace, local))
+        .ok_or_else(|| format!("undeclared prefix {:?}", prefix))
+}
+
+fn next_word(text: &str) -> (&str, &str) {
+    let text = skip_space(text);
+    if let Some(tail) = text.strip_prefix(';') {
+        return (";", tail);
+    }
+    let end = text
+        .find(|c: char| c.is_whitespace() || c == '{' || c == '<')
+        .unwrap_or(text.len());
+    (&text[..end], &text[end..])
+}
+
+pub(crate) fn skip_space(text: &str) -> &str {
+    let mut text = text.trim_start();
+    while text.starts_with('#') {
+        text = text.find('\n').map_or("", |end| &text[end..]).trim_start();
+    }
+    text
+}
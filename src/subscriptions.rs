@@ -0,0 +1,172 @@
+//! Subscriptions to the materializations. A caller registers a triple pattern
+//! with a callback, or asks for a channel, and is told which matching triples
+//! were derived or retracted whenever a timestamp closes, instead of reading
+//! the traces after every update.
+
+use crate::changes::gather;
+use crate::materializations::EncodedTriple;
+use crate::reasoner::{matches, Pattern, Reasoner};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::mpsc::{channel, Receiver};
+use timely::communication::Allocate;
+use timely::dataflow::channels::pact::Pipeline;
+use timely::dataflow::operators::generic::operator::Operator;
+use timely::worker::Worker;
+
+/// The triples matching a subscription's pattern that changed at `time`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub time: usize,
+    pub added: Vec<EncodedTriple>,
+    pub removed: Vec<EncodedTriple>,
+}
+
+/// Identifies a subscription, to cancel it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SubscriptionId(usize);
+
+struct Subscriber {
+    id: SubscriptionId,
+    tbox: bool,
+    pattern: Pattern,
+    callback: Box<dyn FnMut(&Notification)>,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: usize,
+    subscribers: Vec<Subscriber>,
+}
+
+impl Registry {
+    /// Calls back every subscriber whose pattern matches some of `changes`,
+    /// which are all at `time`. `gather` thresholds the materializations, so
+    /// every change adds a triple to a box or removes it, whatever the number
+    /// of its derivations.
+    fn notify(&mut self, time: usize, changes: &[((bool, EncodedTriple), isize)]) {
+        for subscriber in self.subscribers.iter_mut() {
+            let mut notification = Notification {
+                time,
+                added: Vec::new(),
+                removed: Vec::new(),
+            };
+            for ((tbox, triple), diff) in changes {
+                if *tbox == subscriber.tbox && matches(&subscriber.pattern, triple) {
+                    if *diff > 0 {
+                        notification.added.push(*triple);
+                    } else {
+                        notification.removed.push(*triple);
+                    }
+                }
+            }
+            if !notification.added.is_empty() || !notification.removed.is_empty() {
+                notification.added.sort_unstable();
+                notification.removed.sort_unstable();
+                (subscriber.callback)(&notification);
+            }
+        }
+    }
+}
+
+/// The subscriptions to a reasoner's materializations. Changes are gathered
+/// on worker 0, so only subscribers registered there are called back.
+pub struct Subscriptions {
+    registry: Rc<RefCell<Registry>>,
+    /// Every timestamp below this one has been notified.
+    notified: Rc<Cell<usize>>,
+}
+
+impl Subscriptions {
+    /// Follows the materializations of `reasoner` in a dataflow of its own,
+    /// which calls subscribers back as soon as its frontier passes a
+    /// timestamp, while the worker steps.
+    pub fn install<A: Allocate>(worker: &mut Worker<A>, reasoner: &mut Reasoner) -> Self {
+        let registry = Rc::new(RefCell::new(Registry::default()));
+        let notified = Rc::new(Cell::new(0));
+
+        let (sink_registry, sink_notified) = (Rc::clone(&registry), Rc::clone(&notified));
+        worker.dataflow::<usize, _, _>(|scope| {
+            let mut buffer = Vec::new();
+            // Changes by timestamp, until the frontier passes it.
+            let mut pending: BTreeMap<usize, Vec<_>> = BTreeMap::new();
+            gather(scope, reasoner).sink(Pipeline, "Subscriptions", move |input| {
+                input.for_each(|_time, data| {
+                    data.swap(&mut buffer);
+                    for (change, time, diff) in buffer.drain(..) {
+                        pending.entry(time).or_default().push((change, diff));
+                    }
+                });
+                let frontier = input
+                    .frontier()
+                    .frontier()
+                    .iter()
+                    .min()
+                    .cloned()
+                    .unwrap_or(usize::MAX);
+                if frontier > sink_notified.get() {
+                    let open = pending.split_off(&frontier);
+                    let mut registry = sink_registry.borrow_mut();
+                    for (time, changes) in std::mem::replace(&mut pending, open) {
+                        registry.notify(time, &changes);
+                    }
+                    sink_notified.set(frontier);
+                }
+            });
+        });
+
+        Subscriptions { registry, notified }
+    }
+
+    /// Calls `callback` with the changes to the triples of the materialized
+    /// TBox, or ABox, matching `pattern`, from the next timestamp to close on.
+    /// Triples of named graphs are encoded as by `graphs::scope`. Callbacks
+    /// run while the worker steps and must not subscribe or unsubscribe.
+    pub fn subscribe(
+        &mut self,
+        tbox: bool,
+        pattern: Pattern,
+        callback: impl FnMut(&Notification) + 'static,
+    ) -> SubscriptionId {
+        let mut registry = self.registry.borrow_mut();
+        let id = SubscriptionId(registry.next_id);
+        registry.next_id += 1;
+        registry.subscribers.push(Subscriber {
+            id,
+            tbox,
+            pattern,
+            callback: Box::new(callback),
+        });
+        id
+    }
+
+    /// Like `subscribe`, but sends the notifications to a channel, for
+    /// instance to a thread outside the dataflow.
+    pub fn subscribe_channel(
+        &mut self,
+        tbox: bool,
+        pattern: Pattern,
+    ) -> (SubscriptionId, Receiver<Notification>) {
+        let (sender, receiver) = channel();
+        let id = self.subscribe(tbox, pattern, move |notification| {
+            // The receiver may be gone; the subscription just stays idle then.
+            let _ = sender.send(notification.clone());
+        });
+        (id, receiver)
+    }
+
+    pub fn unsubscribe(&mut self, id: SubscriptionId) {
+        self.registry
+            .borrow_mut()
+            .subscribers
+            .retain(|subscriber| subscriber.id != id);
+    }
+
+    /// Steps `worker` until the subscribers have been notified of every
+    /// change at a timestamp before `time`.
+    pub fn wait<A: Allocate>(&self, worker: &mut Worker<A>, time: usize) {
+        let notified = &self.notified;
+        worker.step_while(|| notified.get() < time);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::error::{Error, Result};
+use crate::load_encode_triples::term_end;
+use crate::sparql::{next_term, parse_predicates, skip_space};
+use std::collections::HashMap;
+use std::fs;
+
+/// A triple, in N-Triples lexical form, with the graph it belongs to; `None`
+/// is the default graph.
+pub type GraphTriple = ((String, String, String), Option<String>);
+
+/// Parses a TriG document: `@prefix`/`PREFIX` declarations, graph blocks
+/// (`GRAPH <g> { … }`, `<g> { … }` or `{ … }`) and triples outside of any
+/// block, which belong to the default graph. On failure, returns the line of
+/// the error with its message.
+pub fn parse_trig(text: &str) -> std::result::Result<Vec<GraphTriple>, (usize, String)> {
+    let mut prefixes = HashMap::new();
+    let mut triples = Vec::new();
+    let mut rest = skip_space(text);
+    while !rest.is_empty() {
+        rest = parse_statement(rest, &mut prefixes, &mut triples).map_err(|(at, message)| {
+            let offset = text.len() - at.len();
+            (text[..offset].matches('\n').count() + 1, message)
+        })?;
+        rest = skip_space(rest);
+    }
+    Ok(triples)
+}
+
+pub fn load_trig(filename: &str) -> Result<Vec<GraphTriple>> {
+    let text = fs::read_to_string(filename).map_err(|source| Error::io(filename, source))?;
+    parse_trig(&text).map_err(|(line, message)| Error::parse(filename, line, message))
+}
+
+type Failure<'a> = (&'a str, String);
+
+fn parse_statement<'a>(
+    text: &'a str,
+    prefixes: &mut HashMap<String, String>,
+    triples: &mut Vec<GraphTriple>,
+) -> std::result::Result<&'a str, Failure<'a>> {
+    let word_end = text
+        .find(|c: char| c.is_whitespace() || c == '<' || c == '{')
+        .unwrap_or(text.len());
+    let word = &text[..word_end];
+    if word.eq_ignore_ascii_case("PREFIX") || word == "@prefix" {
+        let tail = skip_space(&text[word_end..]);
+        let name_end = tail
+            .find(':')
+            .ok_or((tail, "expected a prefix name".to_string()))?;
+        let name = &tail[..name_end];
+        let tail = skip_space(&tail[name_end + 1..]);
+        if !tail.starts_with('<') {
+            return Err((tail, format!("expected an IRI after prefix {}:", name)));
+        }
+        let end = term_end(tail).map_err(|message| (tail, message))?;
+        prefixes.insert(name.to_string(), tail[1..end - 1].to_string());
+        let tail = skip_space(&tail[end..]);
+        return match tail.strip_prefix('.') {
+            Some(tail) => Ok(tail),
+            None if word == "@prefix" => Err((tail, "expected '.'".to_string())),
+            None => Ok(tail),
+        };
+    }
+    if word.starts_with('@') {
+        return Err((text, format!("unsupported directive {:?}", word)));
+    }
+
+    let (graph, rest) = if word.eq_ignore_ascii_case("GRAPH") {
+        let (graph, tail) = next_term(&text[word_end..], prefixes).map_err(|e| (text, e))?;
+        (Some(graph), skip_space(tail))
+    } else if text.starts_with('{') {
+        (None, text)
+    } else {
+        let (subject, tail) = next_term(text, prefixes).map_err(|e| (text, e))?;
+        let tail = skip_space(tail);
+        if tail.starts_with('{') {
+            (Some(subject), tail)
+        } else {
+            // A triple of the default graph.
+            let tail = parse_predicates(tail, &subject, prefixes, &mut |triple| {
+                triples.push((triple, None))
+            })
+            .map_err(|e| (tail, e))?;
+            return tail
+                .strip_prefix('.')
+                .ok_or((tail, "expected '.'".to_string()));
+        }
+    };
+
+    let mut rest = rest
+        .strip_prefix('{')
+        .ok_or((rest, "expected '{'".to_string()))?;
+    loop {
+        rest = skip_space(rest);
+        if let Some(tail) = rest.strip_prefix('}') {
+            return Ok(tail);
+        }
+        if let Some(tail) = rest.strip_prefix('.') {
+            rest = tail;
+            continue;
+        }
+        let (subject, tail) = next_term(rest, prefixes).map_err(|e| (rest, e))?;
+        rest = parse_predicates(tail, &subject, prefixes, &mut |triple| {
+            triples.push((triple, graph.clone()))
+        })
+        .map_err(|e| (tail, e))?;
+    }
+}
@@ -0,0 +1,81 @@
+pub const RDFS_SUB_CLASS_OF: &str = "<http://www.w3.org/2000/01/rdf-schema#subClassOf>";
+pub const RDFS_SUB_PROPERTY_OF: &str = "<http://www.w3.org/2000/01/rdf-schema#subPropertyOf>";
+pub const RDFS_DOMAIN: &str = "<http://www.w3.org/2000/01/rdf-schema#domain>";
+pub const RDFS_RANGE: &str = "<http://www.w3.org/2000/01/rdf-schema#range>";
+pub const RDF_TYPE: &str = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#type>";
+pub const OWL_TRANSITIVE_PROPERTY: &str = "<http://www.w3.org/2002/07/owl#TransitiveProperty>";
+pub const OWL_INVERSE_OF: &str = "<http://www.w3.org/2002/07/owl#inverseOf>";
+
+pub const OWL_THING: &str = "<http://www.w3.org/2002/07/owl#Thing>";
+pub const RDFS_COMMENT: &str = "<http://www.w3.org/2000/01/rdf-schema#comment>";
+pub const RDF_REST: &str = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#rest>";
+pub const RDF_FIRST: &str = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#first>";
+pub const OWL_MAX_QUALIFIED_CARDINALITY: &str =
+    "<http://www.w3.org/2002/07/owl#maxQualifiedCardinality>";
+pub const OWL_SOME_VALUES_FROM: &str = "<http://www.w3.org/2002/07/owl#someValuesFrom>";
+pub const OWL_EQUIVALENT_CLASS: &str = "<http://www.w3.org/2002/07/owl#equivalentClass>";
+pub const OWL_INTERSECTION_OF: &str = "<http://www.w3.org/2002/07/owl#intersectionOf>";
+pub const OWL_MEMBERS: &str = "<http://www.w3.org/2002/07/owl#members>";
+pub const OWL_EQUIVALENT_PROPERTY: &str = "<http://www.w3.org/2002/07/owl#equivalentProperty>";
+pub const OWL_ON_PROPERTY: &str = "<http://www.w3.org/2002/07/owl#onProperty>";
+pub const OWL_PROPERTY_CHAIN_AXIOM: &str = "<http://www.w3.org/2002/07/owl#propertyChainAxiom>";
+pub const OWL_DISJOINT_WITH: &str = "<http://www.w3.org/2002/07/owl#disjointWith>";
+pub const OWL_PROPERTY_DISJOINT_WITH: &str = "<http://www.w3.org/2002/07/owl#propertyDisjointWith>";
+pub const OWL_UNION_OF: &str = "<http://www.w3.org/2002/07/owl#unionOf>";
+pub const OWL_ONE_OF: &str = "<http://www.w3.org/2002/07/owl#oneOf>";
+pub const RDFS_LABEL: &str = "<http://www.w3.org/2000/01/rdf-schema#label>";
+pub const OWL_HAS_KEY: &str = "<http://www.w3.org/2002/07/owl#hasKey>";
+pub const OWL_ALL_VALUES_FROM: &str = "<http://www.w3.org/2002/07/owl#allValuesFrom>";
+pub const OWL_COMPLEMENT_OF: &str = "<http://www.w3.org/2002/07/owl#complementOf>";
+pub const OWL_ON_CLASS: &str = "<http://www.w3.org/2002/07/owl#onClass>";
+pub const OWL_DISTINCT_MEMBERS: &str = "<http://www.w3.org/2002/07/owl#distinctMembers>";
+pub const OWL_FUNCTIONAL_PROPERTY: &str = "<http://www.w3.org/2002/07/owl#FunctionalProperty>";
+pub const OWL_NAMED_INDIVIDUAL: &str = "<http://www.w3.org/2002/07/owl#NamedIndividual>";
+pub const OWL_OBJECT_PROPERTY: &str = "<http://www.w3.org/2002/07/owl#ObjectProperty>";
+pub const RDF_NIL: &str = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#nil>";
+pub const OWL_CLASS: &str = "<http://www.w3.org/2002/07/owl#Class>";
+pub const XSD_ONE_NON_NEGATIVE_INTEGER: &str =
+    "\"1\"^^<http://www.w3.org/2001/XMLSchema#nonNegativeInteger>";
+pub const XSD_ZERO_NON_NEGATIVE_INTEGER: &str =
+    "\"0\"^^<http://www.w3.org/2001/XMLSchema#nonNegativeInteger>";
+pub const OWL_ALL_DISJOINT_CLASSES: &str = "<http://www.w3.org/2002/07/owl#AllDisjointClasses>";
+pub const OWL_RESTRICTION: &str = "<http://www.w3.org/2002/07/owl#Restriction>";
+pub const OWL_DATATYPE_PROPERTY: &str = "<http://www.w3.org/2002/07/owl#DatatypeProperty>";
+pub const RDFS_LITERAL: &str = "<http://www.w3.org/2000/01/rdf-schema#Literal>";
+pub const OWL_ONTOLOGY: &str = "<http://www.w3.org/2002/07/owl#Ontology>";
+pub const OWL_ASYMMETRIC_PROPERTY: &str = "<http://www.w3.org/2002/07/owl#AsymmetricProperty>";
+pub const OWL_SYMMETRIC_PROPERTY: &str = "<http://www.w3.org/2002/07/owl#SymmetricProperty>";
+pub const OWL_IRREFLEXIVE_PROPERTY: &str = "<http://www.w3.org/2002/07/owl#IrreflexiveProperty>";
+pub const OWL_ALL_DIFFERENT: &str = "<http://www.w3.org/2002/07/owl#AllDifferent>";
+pub const OWL_INVERSE_FUNCTIONAL_PROPERTY: &str =
+    "<http://www.w3.org/2002/07/owl#InverseFunctionalProperty>";
+pub const OWL_SAME_AS: &str = "<http://www.w3.org/2002/07/owl#sameAs>";
+pub const OWL_DIFFERENT_FROM: &str = "<http://www.w3.org/2002/07/owl#differentFrom>";
+pub const RDFS_DATATYPE: &str = "<http://www.w3.org/2000/01/rdf-schema#Datatype>";
+pub const OWL_HAS_VALUE: &str = "<http://www.w3.org/2002/07/owl#hasValue>";
+pub const OWL_MAX_CARDINALITY: &str = "<http://www.w3.org/2002/07/owl#maxCardinality>";
+pub const RDFS_CLASS: &str = "<http://www.w3.org/2000/01/rdf-schema#Class>";
+pub const RDF_PROPERTY: &str = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#Property>";
+
+/// Terms with a fixed encoding; `materializations` relies on their ids being
+/// their position in this list, see `ids`.
+pub const VOCABULARY: [&str; 7] = [
+    RDFS_SUB_CLASS_OF,
+    RDFS_SUB_PROPERTY_OF,
+    RDFS_DOMAIN,
+    RDFS_RANGE,
+    RDF_TYPE,
+    OWL_TRANSITIVE_PROPERTY,
+    OWL_INVERSE_OF,
+];
+
+/// The ids of the terms of `VOCABULARY`.
+pub mod ids {
+    pub const SUB_CLASS_OF: usize = 0;
+    pub const SUB_PROPERTY_OF: usize = 1;
+    pub const DOMAIN: usize = 2;
+    pub const RANGE: usize = 3;
+    pub const RDF_TYPE: usize = 4;
+    pub const TRANSITIVE_PROPERTY: usize = 5;
+    pub const INVERSE_OF: usize = 6;
+}
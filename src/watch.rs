@@ -0,0 +1,275 @@
+//! Applying delta files dropped into a directory. Every new `.nt` file, or
+//! `.ntenc` file for encoded input, holds `+`/`-` prefixed triples and is
+//! moved to `processed/`, then applied as one timestamp. The names the
+//! applied files were moved under are recorded in `processed/progress`, and
+//! after a poll the materialization is snapshotted to `processed/snapshot`,
+//! so that a restart resumes from the snapshot and only replays the files
+//! applied after it.
+
+use crate::dictionary::Dictionary;
+use crate::error::{Error, Result};
+use crate::load_encode_triples::{load3enc_delta, load3nt_delta, read_file};
+use crate::materializations::EncodedTriple;
+use crate::runtime::ReasonerHandle;
+use crate::snapshot::{Manifest, DICTIONARY};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const PROCESSED: &str = "processed";
+const PROGRESS: &str = "progress";
+pub const SNAPSHOT: &str = "snapshot";
+/// Where a snapshot is written before it replaces the previous one.
+const NEW_SNAPSHOT: &str = "snapshot.new";
+
+/// A directory of delta files, and the batches applied from it.
+pub struct DeltaDirectory {
+    dir: PathBuf,
+    /// N-Triples deltas are encoded with it; without one, deltas are encoded.
+    dictionary: Option<Dictionary>,
+    /// The applied files under `processed/`, in the order they were applied.
+    applied: Vec<String>,
+    /// How many of `applied` the snapshot holds, if there is one.
+    snapshotted: Option<usize>,
+    /// Files moved to `processed/` by an earlier run that stopped before
+    /// recording them.
+    pending: Vec<String>,
+}
+
+impl DeltaDirectory {
+    /// Opens `dir`, creating its `processed/` subdirectory if needed, and
+    /// reads the progress and snapshot recorded there. The dictionary of the
+    /// snapshot replaces `dictionary`, if there is one.
+    pub fn open(dir: impl AsRef<Path>, dictionary: Option<Dictionary>) -> Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        let processed = dir.join(PROCESSED);
+        fs::create_dir_all(&processed).map_err(|source| Error::io(&processed, source))?;
+        let applied = read_progress(&processed)?;
+
+        // A run that stopped while writing a snapshot leaves either an
+        // incomplete new one, or a complete one that did not replace the old.
+        let (snapshot, new_snapshot) = (processed.join(SNAPSHOT), processed.join(NEW_SNAPSHOT));
+        if new_snapshot.exists() {
+            if snapshot.exists() || Manifest::read(&new_snapshot.display().to_string()).is_err() {
+                fs::remove_dir_all(&new_snapshot)
+                    .map_err(|source| Error::io(&new_snapshot, source))?;
+            } else {
+                fs::rename(&new_snapshot, &snapshot)
+                    .map_err(|source| Error::io(&new_snapshot, source))?;
+            }
+        }
+        let (mut dictionary, mut snapshotted) = (dictionary, None);
+        if snapshot.exists() {
+            let covered = read_progress(&snapshot)?;
+            if !applied.starts_with(&covered) {
+                return Err(Error::Snapshot(format!(
+                    "{} holds delta files that {} does not record",
+                    snapshot.display(),
+                    processed.join(PROGRESS).display()
+                )));
+            }
+            snapshotted = Some(covered.len());
+            if dictionary.is_some() {
+                let path = snapshot.join(DICTIONARY).display().to_string();
+                dictionary = Some(Dictionary::load(&path)?);
+            }
+        }
+
+        let mut pending = Vec::new();
+        let entries = fs::read_dir(&processed).map_err(|source| Error::io(&processed, source))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|source| Error::io(&processed, source))?
+                .path();
+            if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                if path.is_file() && name != PROGRESS && !applied.iter().any(|other| other == name)
+                {
+                    pending.push(name.to_string());
+                }
+            }
+        }
+        pending.sort_unstable();
+
+        Ok(DeltaDirectory {
+            dir,
+            dictionary,
+            applied,
+            snapshotted,
+            pending,
+        })
+    }
+
+    /// The extension of the delta files read.
+    pub fn extension(&self) -> &'static str {
+        if self.dictionary.is_some() {
+            "nt"
+        } else {
+            "ntenc"
+        }
+    }
+
+    pub fn dictionary(&self) -> Option<&Dictionary> {
+        self.dictionary.as_ref()
+    }
+
+    pub fn dictionary_mut(&mut self) -> Option<&mut Dictionary> {
+        self.dictionary.as_mut()
+    }
+
+    /// The snapshot to start the reasoner from, if an earlier run took one;
+    /// `recover` then only replays the files applied after it.
+    pub fn snapshot(&self) -> Option<PathBuf> {
+        self.snapshotted
+            .map(|_covered| self.dir.join(PROCESSED).join(SNAPSHOT))
+    }
+
+    /// Replays the batches an earlier run applied after its snapshot, or all
+    /// of them if it took none, in order, and returns how many there were.
+    /// A file moved just before the earlier run stopped is applied and
+    /// recorded now.
+    pub fn recover(&mut self, reasoner: &ReasonerHandle) -> Result<usize> {
+        let processed = self.dir.join(PROCESSED);
+        let covered = self.snapshotted.unwrap_or(0);
+        for name in self.applied[covered..].to_vec() {
+            let path = processed.join(&name);
+            if !path.exists() {
+                return Err(Error::Config(format!(
+                    "delta file {} is recorded as applied but is missing from {}",
+                    name,
+                    processed.display()
+                )));
+            }
+            self.apply(&path, reasoner)?;
+        }
+        for name in std::mem::take(&mut self.pending) {
+            self.apply(&processed.join(&name), reasoner)?;
+            self.record(&name)?;
+        }
+        Ok(self.applied.len() - covered)
+    }
+
+    /// Snapshots the materialization of everything applied so far to
+    /// `processed/snapshot`, replacing the previous snapshot only once the
+    /// new one is complete.
+    pub fn checkpoint(&mut self, reasoner: &ReasonerHandle) -> Result<()> {
+        let processed = self.dir.join(PROCESSED);
+        let (snapshot, new_snapshot) = (processed.join(SNAPSHOT), processed.join(NEW_SNAPSHOT));
+        if new_snapshot.exists() {
+            fs::remove_dir_all(&new_snapshot).map_err(|source| Error::io(&new_snapshot, source))?;
+        }
+        fs::create_dir_all(&new_snapshot).map_err(|source| Error::io(&new_snapshot, source))?;
+        if let Some(dictionary) = &self.dictionary {
+            dictionary.save(&new_snapshot.join(DICTIONARY).display().to_string())?;
+        }
+        let progress = new_snapshot.join(PROGRESS);
+        let names: String = self
+            .applied
+            .iter()
+            .map(|name| format!("{}\n", name))
+            .collect();
+        fs::write(&progress, names).map_err(|source| Error::io(&progress, source))?;
+        // The manifest is written last.
+        reasoner.snapshot(&new_snapshot.display().to_string())?;
+
+        if snapshot.exists() {
+            fs::remove_dir_all(&snapshot).map_err(|source| Error::io(&snapshot, source))?;
+        }
+        fs::rename(&new_snapshot, &snapshot).map_err(|source| Error::io(&new_snapshot, source))?;
+        self.snapshotted = Some(self.applied.len());
+        Ok(())
+    }
+
+    /// Applies the delta files that appeared since the last poll, in the
+    /// order of their names, and returns them with the timestamps they were
+    /// assigned. A file reusing the name of one applied before is moved to
+    /// `processed/` under a numbered name. Producers should write files under
+    /// another extension and rename them once complete, so that no
+    /// half-written file is read.
+    pub fn poll(&mut self, reasoner: &ReasonerHandle) -> Result<Vec<(String, usize)>> {
+        let mut names = Vec::new();
+        let entries = fs::read_dir(&self.dir).map_err(|source| Error::io(&self.dir, source))?;
+        for entry in entries {
+            let path = entry.map_err(|source| Error::io(&self.dir, source))?.path();
+            if path.is_file()
+                && path
+                    .extension()
+                    .map_or(false, |ext| ext == self.extension())
+            {
+                if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        names.sort_unstable();
+
+        let mut applied = Vec::new();
+        for name in names {
+            let path = self.dir.join(&name);
+            let processed = self.processed_name(&name);
+            let moved = self.dir.join(PROCESSED).join(&processed);
+            fs::rename(&path, &moved).map_err(|source| Error::io(&path, source))?;
+            let time = self.apply(&moved, reasoner)?;
+            self.record(&processed)?;
+            applied.push((name, time));
+        }
+        Ok(applied)
+    }
+
+    /// Submits the delta file at `path` as one mixed batch, which the
+    /// runtime splits into TBox and ABox updates, and waits until it is
+    /// materialized.
+    fn apply(&mut self, path: &Path, reasoner: &ReasonerHandle) -> Result<usize> {
+        let path = path.display().to_string();
+        let updates: Vec<(EncodedTriple, isize)> = match self.dictionary.as_mut() {
+            Some(dictionary) => load3nt_delta(&path)?
+                .map(|update| {
+                    let (triple, diff) = update?;
+                    Ok((dictionary.encode_triple(&triple), diff))
+                })
+                .collect::<Result<_>>()?,
+            None => load3enc_delta(&path)?.collect::<Result<_>>()?,
+        };
+        let time = reasoner.submit_mixed(updates);
+        reasoner.wait(time)?;
+        Ok(time)
+    }
+
+    /// A name under `processed/` for the delta file `name` that no applied
+    /// file has: `name` itself, or `name` followed by a number.
+    fn processed_name(&self, name: &str) -> String {
+        let processed = self.dir.join(PROCESSED);
+        std::iter::once(name.to_string())
+            .chain((1..).map(|number| format!("{}.{}", name, number)))
+            .find(|candidate| !processed.join(candidate).exists())
+            .unwrap()
+    }
+
+    /// Appends `name` to the progress file, and syncs it before the next file
+    /// is moved.
+    fn record(&mut self, name: &str) -> Result<()> {
+        let progress = self.dir.join(PROCESSED).join(PROGRESS);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&progress)
+            .map_err(|source| Error::io(&progress, source))?;
+        writeln!(file, "{}", name)
+            .and_then(|()| file.sync_all())
+            .map_err(|source| Error::io(&progress, source))?;
+        self.applied.push(name.to_string());
+        Ok(())
+    }
+}
+
+/// The names listed in the progress file in `dir`, if there is one.
+fn read_progress(dir: &Path) -> Result<Vec<String>> {
+    let progress = dir.join(PROGRESS);
+    let mut names = Vec::new();
+    if progress.exists() {
+        for line in read_file(&progress.display().to_string())? {
+            let (_number, name) = line?;
+            names.push(name.trim().to_string());
+        }
+    }
+    Ok(names)
+}
@@ -0,0 +1,220 @@
+mod common;
+
+use common::{is_tbox, reference, Rng, Triples};
+use differential_reasoner::changes::{Change, ChangeCapture, ChangeFormat};
+use differential_reasoner::dictionary::Dictionary;
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::reasoner::{Profile, Reasoner};
+use std::collections::HashSet;
+
+const SUB_CLASS_OF: usize = 0;
+const TYPE: usize = 4;
+const SEEDS: u64 = 10;
+const STEPS: usize = 6;
+
+/// Random updates for each timestamp; only absent triples are inserted and
+/// only present ones deleted.
+fn updates(seed: u64) -> Vec<Vec<(EncodedTriple, isize)>> {
+    let mut rng = Rng::new(seed);
+    let mut input = Triples::new();
+    let mut steps = Vec::new();
+    for step in 0..STEPS {
+        let mut updates = Vec::new();
+        let inserts = if step == 0 { 16 } else { rng.below(5) };
+        for _ in 0..inserts {
+            let triple = rng.triple();
+            if input.insert(triple) {
+                updates.push((triple, 1));
+            }
+        }
+        if step > 0 {
+            let mut present: Vec<EncodedTriple> = input.iter().cloned().collect();
+            present.sort_unstable();
+            for _ in 0..rng.below(4) {
+                if present.is_empty() {
+                    break;
+                }
+                let triple = present.swap_remove(rng.below(present.len()));
+                input.remove(&triple);
+                updates.push((triple, -1));
+            }
+        }
+        steps.push(updates);
+    }
+    steps
+}
+
+/// The changes captured after each timestamp, by worker.
+fn capture(steps: &[Vec<(EncodedTriple, isize)>]) -> Vec<Vec<Vec<Change>>> {
+    let steps = steps.to_vec();
+    timely::execute(timely::Config::process(2), move |worker| {
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfspp);
+        let mut capture = ChangeCapture::install(worker, &mut reasoner);
+        let mut batches = Vec::new();
+        for updates in steps.iter() {
+            if worker.index() == 0 {
+                for &(triple, diff) in updates.iter() {
+                    if is_tbox(&triple) {
+                        reasoner.update_tbox(triple, diff);
+                    } else {
+                        reasoner.update_abox(triple, diff);
+                    }
+                }
+            }
+            reasoner.advance(worker);
+            batches.push(capture.closed(worker, reasoner.time()));
+        }
+        batches
+    })
+    .unwrap()
+    .join()
+    .into_iter()
+    .map(|share| share.unwrap())
+    .collect()
+}
+
+#[test]
+fn emits_each_timestamp_as_one_batch() {
+    for seed in 0..SEEDS {
+        let steps = updates(seed);
+        let shares = capture(&steps);
+        assert!(shares[1].iter().all(|batch| batch.is_empty()));
+
+        let (mut tbox, mut abox) = (Triples::new(), Triples::new());
+        let mut previous: HashSet<(bool, EncodedTriple)> = HashSet::new();
+        for (time, (updates, batch)) in steps.iter().zip(&shares[0]).enumerate() {
+            for &(triple, diff) in updates.iter() {
+                let input = if is_tbox(&triple) {
+                    &mut tbox
+                } else {
+                    &mut abox
+                };
+                if diff > 0 {
+                    input.insert(triple);
+                } else {
+                    input.remove(&triple);
+                }
+            }
+            let (tbox_closure, abox_closure) = reference(Profile::Rdfspp, &tbox, &abox);
+            let current: HashSet<(bool, EncodedTriple)> = tbox_closure
+                .into_iter()
+                .map(|triple| (true, triple))
+                .chain(abox_closure.into_iter().map(|triple| (false, triple)))
+                .collect();
+            let mut expected: Vec<Change> = current
+                .difference(&previous)
+                .map(|(tbox, triple)| (*tbox, *triple, 1))
+                .chain(
+                    previous
+                        .difference(&current)
+                        .map(|(tbox, triple)| (*tbox, *triple, -1)),
+                )
+                .map(|(tbox, triple, diff)| Change {
+                    time,
+                    tbox,
+                    triple,
+                    diff,
+                })
+                .collect();
+            expected.sort_unstable();
+            assert_eq!(
+                batch, &expected,
+                "changes differ for seed {} at time {}",
+                seed, time
+            );
+            previous = current;
+        }
+    }
+}
+
+#[test]
+fn reports_triples_derived_twice_once() {
+    let (a, b, x) = (10, 11, 30);
+    let steps = vec![
+        vec![((a, SUB_CLASS_OF, b), 1), ((x, TYPE, a), 1)],
+        // `x : b` is now asserted as well as derived.
+        vec![((x, TYPE, b), 1)],
+        vec![((x, TYPE, b), -1)],
+        vec![((a, SUB_CLASS_OF, b), -1)],
+    ];
+    let shares = capture(&steps);
+    let batch = |time: usize| -> Vec<(EncodedTriple, isize)> {
+        shares[0][time]
+            .iter()
+            .filter(|change| !change.tbox)
+            .map(|change| (change.triple, change.diff))
+            .collect()
+    };
+    assert_eq!(batch(0), vec![((x, TYPE, a), 1), ((x, TYPE, b), 1)]);
+    assert_eq!(batch(1), vec![]);
+    assert_eq!(batch(2), vec![]);
+    assert_eq!(batch(3), vec![((x, TYPE, b), -1)]);
+}
+
+#[test]
+fn writes_json_lines_and_signed_triples() {
+    let mut dictionary = Dictionary::new();
+    let triple = |dictionary: &mut Dictionary, s: &str, p: &str, o: &str| {
+        dictionary.encode_triple(&(s.to_string(), p.to_string(), o.to_string()))
+    };
+    let type_ = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#type>";
+    let changes = vec![
+        Change {
+            time: 1,
+            tbox: false,
+            triple: triple(&mut dictionary, "<http://ex/x>", type_, "<http://ex/B>"),
+            diff: 1,
+        },
+        Change {
+            time: 1,
+            tbox: false,
+            triple: triple(
+                &mut dictionary,
+                "<http://ex/x>",
+                "<http://ex/name>",
+                "\"x \\\"1\\\"\"",
+            ),
+            diff: -1,
+        },
+    ];
+
+    let mut json = Vec::new();
+    ChangeFormat::JsonLines
+        .write(&mut json, &changes, Some(&dictionary))
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(json).unwrap(),
+        format!(
+            "{{\"time\":1,\"box\":\"abox\",\"s\":\"<http://ex/x>\",\"p\":\"{}\",\"o\":\"<http://ex/B>\",\"diff\":1}}\n\
+             {{\"time\":1,\"box\":\"abox\",\"s\":\"<http://ex/x>\",\"p\":\"<http://ex/name>\",\"o\":\"\\\"x \\\\\\\"1\\\\\\\"\\\"\",\"diff\":-1}}\n",
+            type_
+        )
+    );
+
+    let mut nt = Vec::new();
+    ChangeFormat::NTriples
+        .write(&mut nt, &changes, Some(&dictionary))
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(nt).unwrap(),
+        format!(
+            "# time 1 abox\n+ <http://ex/x> {} <http://ex/B> .\n- <http://ex/x> <http://ex/name> \"x \\\"1\\\"\" .\n",
+            type_
+        )
+    );
+
+    let mut encoded = Vec::new();
+    ChangeFormat::NTriples
+        .write(&mut encoded, &changes[..1], None)
+        .unwrap();
+    let (s, p, o) = changes[0].triple;
+    assert_eq!(
+        String::from_utf8(encoded).unwrap(),
+        format!("# time 1 abox\n+ {} {} {}\n", s, p, o)
+    );
+    assert_eq!(
+        "nt".parse::<ChangeFormat>().unwrap(),
+        ChangeFormat::NTriples
+    );
+    assert!("csv".parse::<ChangeFormat>().is_err());
+}
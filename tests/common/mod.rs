@@ -0,0 +1,152 @@
+//! A naive forward-chaining implementation of the `rdfs` and `rdfspp`
+//! materializations, and a generator of small random inputs to compare the
+//! dataflows against.
+// Every test binary uses only part of this module.
+#![allow(dead_code)]
+
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::reasoner::Profile;
+use std::collections::HashSet;
+
+pub type Triples = HashSet<EncodedTriple>;
+
+const SUB_CLASS_OF: usize = 0;
+const SUB_PROPERTY_OF: usize = 1;
+const DOMAIN: usize = 2;
+const RANGE: usize = 3;
+const TYPE: usize = 4;
+const TRANSITIVE_PROPERTY: usize = 5;
+const INVERSE_OF: usize = 6;
+
+/// The triples one application of every rule of `profile` derives from
+/// `tbox` and `abox`, as TBox and ABox triples.
+fn apply_rules(profile: Profile, tbox: &Triples, abox: &Triples) -> (Triples, Triples) {
+    let schema = |predicate: usize| tbox.iter().filter(move |t| t.1 == predicate);
+    let mut new_tbox = Triples::new();
+    let mut new_abox = Triples::new();
+
+    // scm-sco and scm-spo.
+    for predicate in [SUB_CLASS_OF, SUB_PROPERTY_OF].iter().copied() {
+        for &(a, _, b) in schema(predicate) {
+            for &(b2, _, c) in schema(predicate) {
+                if b == b2 {
+                    new_tbox.insert((a, predicate, c));
+                }
+            }
+        }
+    }
+
+    for &(x, p, y) in abox.iter() {
+        if p == TYPE {
+            // cax-sco.
+            for &(c, _, d) in schema(SUB_CLASS_OF) {
+                if c == y {
+                    new_abox.insert((x, TYPE, d));
+                }
+            }
+            continue;
+        }
+        // prp-spo1, prp-dom and prp-rng.
+        for &(q, _, r) in schema(SUB_PROPERTY_OF) {
+            if q == p {
+                new_abox.insert((x, r, y));
+            }
+        }
+        for &(q, _, c) in schema(DOMAIN) {
+            if q == p {
+                new_abox.insert((x, TYPE, c));
+            }
+        }
+        for &(q, _, c) in schema(RANGE) {
+            if q == p {
+                new_abox.insert((y, TYPE, c));
+            }
+        }
+        if profile != Profile::Rdfspp {
+            continue;
+        }
+        // prp-inv1, prp-inv2 and prp-trp.
+        for &(q, _, r) in schema(INVERSE_OF) {
+            if q == p {
+                new_abox.insert((y, r, x));
+            }
+            if r == p {
+                new_abox.insert((y, q, x));
+            }
+        }
+        if tbox.contains(&(p, TYPE, TRANSITIVE_PROPERTY)) {
+            for &(y2, p2, z) in abox.iter() {
+                if p2 == p && y2 == y {
+                    new_abox.insert((x, p, z));
+                }
+            }
+        }
+    }
+    (new_tbox, new_abox)
+}
+
+/// The materialized TBox and ABox of `profile` over `tbox` and `abox`: every
+/// rule is applied to everything derived so far until nothing changes.
+pub fn reference(profile: Profile, tbox: &Triples, abox: &Triples) -> (Triples, Triples) {
+    let mut tbox = tbox.clone();
+    let mut abox = abox.clone();
+    loop {
+        let (new_tbox, new_abox) = apply_rules(profile, &tbox, &abox);
+        let (tbox_size, abox_size) = (tbox.len(), abox.len());
+        tbox.extend(new_tbox);
+        abox.extend(new_abox);
+        if tbox.len() == tbox_size && abox.len() == abox_size {
+            return (tbox, abox);
+        }
+    }
+}
+
+/// Whether `triple` belongs in the TBox input.
+pub fn is_tbox(triple: &EncodedTriple) -> bool {
+    matches!(
+        triple.1,
+        SUB_CLASS_OF | SUB_PROPERTY_OF | DOMAIN | RANGE | INVERSE_OF
+    ) || (triple.1 == TYPE && triple.2 == TRANSITIVE_PROPERTY)
+}
+
+/// A xorshift generator, so failures can be reproduced from their seed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    fn pick(&mut self, first: usize, count: usize) -> usize {
+        first + self.below(count)
+    }
+
+    /// A random triple over a handful of classes, properties and individuals.
+    /// None of them collide with the fixed vocabulary ids.
+    pub fn triple(&mut self) -> EncodedTriple {
+        let class = |rng: &mut Rng| rng.pick(10, 5);
+        let property = |rng: &mut Rng| rng.pick(20, 5);
+        let individual = |rng: &mut Rng| rng.pick(30, 8);
+        match self.below(9) {
+            0 => (class(self), SUB_CLASS_OF, class(self)),
+            1 => (property(self), SUB_PROPERTY_OF, property(self)),
+            2 => (property(self), DOMAIN, class(self)),
+            3 => (property(self), RANGE, class(self)),
+            4 => (property(self), INVERSE_OF, property(self)),
+            5 => (property(self), TYPE, TRANSITIVE_PROPERTY),
+            6 => (individual(self), TYPE, class(self)),
+            _ => (individual(self), property(self), individual(self)),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use differential_reasoner::error::Error;
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::reasoner::{Pattern, Profile, Reasoner};
+
+const SUB_CLASS_OF: usize = 0;
+const TYPE: usize = 4;
+
+const TYPES: Pattern = (None, Some(TYPE), Some(11));
+
+/// One update per timestamp: `20`, `21` and `22` are typed with `10`, a
+/// subclass of `11`, in turn, and `20` is removed again at timestamp 3.
+fn updates() -> Vec<(EncodedTriple, isize)> {
+    vec![
+        ((10, SUB_CLASS_OF, 11), 1),
+        ((20, TYPE, 10), 1),
+        ((21, TYPE, 10), 1),
+        ((20, TYPE, 10), -1),
+        ((22, TYPE, 10), 1),
+    ]
+}
+
+/// The individuals typed `11` as of each timestamp.
+fn expected(time: usize) -> Vec<EncodedTriple> {
+    let individuals: &[usize] = match time {
+        0 => &[],
+        1 => &[20],
+        2 => &[20, 21],
+        3 => &[21],
+        _ => &[21, 22],
+    };
+    individuals.iter().map(|s| (*s, TYPE, 11)).collect()
+}
+
+#[test]
+fn keeps_the_history_window_readable() {
+    timely::execute(timely::Config::thread(), |worker| {
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfs);
+        reasoner.set_history(2);
+        for (time, (triple, diff)) in updates().into_iter().enumerate() {
+            if triple.1 == SUB_CLASS_OF {
+                reasoner.update_tbox(triple, diff);
+            } else {
+                reasoner.update_abox(triple, diff);
+            }
+            reasoner.advance(worker);
+            assert_eq!(reasoner.earliest(), time.saturating_sub(2));
+            for read in reasoner.earliest()..=time {
+                let mut triples = reasoner.query_abox(&TYPES, read).unwrap();
+                triples.sort_unstable();
+                assert_eq!(triples, expected(read), "read at {} after {}", read, time);
+            }
+        }
+    })
+    .unwrap();
+}
+
+#[test]
+fn holds_keep_timestamps_from_being_compacted() {
+    timely::execute(timely::Config::thread(), |worker| {
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfs);
+        for (time, (triple, diff)) in updates().into_iter().enumerate() {
+            if triple.1 == SUB_CLASS_OF {
+                reasoner.update_tbox(triple, diff);
+            } else {
+                reasoner.update_abox(triple, diff);
+            }
+            reasoner.advance(worker);
+            if time == 1 {
+                reasoner.hold(Some(1));
+            }
+            assert_eq!(reasoner.earliest(), time.min(1));
+        }
+        let mut triples = reasoner.query_abox(&TYPES, 1).unwrap();
+        triples.sort_unstable();
+        assert_eq!(triples, expected(1));
+
+        reasoner.hold(None);
+        assert_eq!(reasoner.earliest(), 4);
+        assert_eq!(reasoner.query_abox(&TYPES, 4).unwrap(), expected(4));
+        assert!(matches!(
+            reasoner.query_abox(&TYPES, 1),
+            Err(Error::Timestamp(_))
+        ));
+    })
+    .unwrap();
+}
@@ -0,0 +1,124 @@
+#!/usr/bin/env python3
+"""Converts W3C OWL 2 test cases to the layout of this directory.
+
+    python3 tests/conformance/convert.py [--out DIR] CASES.rdf...
+
+Every argument is an RDF/XML file of the W3C OWL 2 test suite, either the
+whole suite or single exported cases. The cases with `test:profile
+test:RL` are kept; rejected ones, and those whose conclusion has a blank
+node, are skipped. Each kept case gets `<identifier>.premise.nt` and, if it
+has one, `<identifier>.conclusion.nt`, and its description is appended to
+`manifest.nt` unless a case with its identifier is there already.
+
+W3C cases do not name the OWL 2 RL rule they exercise; add a
+`<urn:differential-reasoner:rule>` triple to the manifest by hand to have
+them reported per rule. Needs rdflib.
+"""
+
+import argparse
+import os
+import re
+import sys
+
+from rdflib import Graph, Literal, Namespace, RDF, URIRef
+from rdflib.term import BNode
+
+TEST = Namespace("http://www.w3.org/2007/OWL/testOntology#")
+KINDS = [
+    TEST.PositiveEntailmentTest,
+    TEST.NegativeEntailmentTest,
+    TEST.ConsistencyTest,
+    TEST.InconsistencyTest,
+]
+
+
+def ontology(text):
+    graph = Graph()
+    graph.parse(data=str(text), format="xml")
+    return graph
+
+
+def write_ntriples(graph, path):
+    with open(path, "w", encoding="utf-8") as out:
+        for triple in sorted(graph):
+            out.write(" ".join(term.n3() for term in triple) + " .\n")
+
+
+def known_identifiers(manifest):
+    if not os.path.exists(manifest):
+        return set()
+    pattern = re.compile(r'testOntology#identifier> "([^"]*)"')
+    with open(manifest, encoding="utf-8") as lines:
+        return {match.group(1) for match in map(pattern.search, lines) if match}
+
+
+def convert(suite, out, known):
+    """Writes the RL cases of `suite` to `out`, and returns their manifest
+    triples."""
+    manifest = []
+    for kind in KINDS:
+        for case in suite.subjects(RDF.type, kind):
+            if (case, TEST.profile, TEST.RL) not in suite:
+                continue
+            if (case, TEST.status, TEST.Rejected) in suite:
+                continue
+            identifier = str(suite.value(case, TEST.identifier))
+            if identifier in known:
+                continue
+            name = re.sub(r"[^A-Za-z0-9_.-]", "_", identifier)
+
+            conclusion_property = {
+                TEST.PositiveEntailmentTest: TEST.rdfXmlConclusionOntology,
+                TEST.NegativeEntailmentTest: TEST.rdfXmlNonConclusionOntology,
+            }.get(kind)
+            conclusion = None
+            if conclusion_property is not None:
+                conclusion = ontology(suite.value(case, conclusion_property))
+                if any(isinstance(term, BNode) for triple in conclusion for term in triple):
+                    print("skipping {}: its conclusion has a blank node".format(identifier),
+                          file=sys.stderr)
+                    continue
+
+            premise = ontology(suite.value(case, TEST.rdfXmlPremiseOntology))
+            write_ntriples(premise, os.path.join(out, name + ".premise.nt"))
+            subject = URIRef(str(case))
+            manifest += [
+                (subject, RDF.type, kind),
+                (subject, TEST.identifier, Literal(identifier)),
+                (subject, TEST.profile, TEST.RL),
+                (subject, TEST.premiseOntology, Literal(name + ".premise.nt")),
+            ]
+            if conclusion is not None:
+                write_ntriples(conclusion, os.path.join(out, name + ".conclusion.nt"))
+                property = {
+                    TEST.rdfXmlConclusionOntology: TEST.conclusionOntology,
+                    TEST.rdfXmlNonConclusionOntology: TEST.nonConclusionOntology,
+                }[conclusion_property]
+                manifest.append((subject, property, Literal(name + ".conclusion.nt")))
+            known.add(identifier)
+    return manifest
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("--out", default=os.path.dirname(os.path.abspath(__file__)))
+    parser.add_argument("suites", nargs="+")
+    arguments = parser.parse_args()
+
+    manifest_path = os.path.join(arguments.out, "manifest.nt")
+    known = known_identifiers(manifest_path)
+    added = []
+    for path in arguments.suites:
+        suite = Graph()
+        suite.parse(path, format="xml")
+        added += convert(suite, arguments.out, known)
+
+    with open(manifest_path, "a", encoding="utf-8") as manifest:
+        for triple in added:
+            manifest.write(" ".join(term.n3() for term in triple) + " .\n")
+    cases = sum(1 for (_s, p, _o) in added if p == TEST.identifier)
+    print("added {} cases to {}".format(cases, manifest_path), file=sys.stderr)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,230 @@
+mod common;
+
+use common::is_tbox;
+use differential_reasoner::dictionary::Dictionary;
+use differential_reasoner::load_encode_triples::load3nt;
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::owl2rl::clashes;
+use differential_reasoner::reasoner::{Profile, Reasoner};
+use differential_reasoner::vocabulary::{OWL_COMPLEMENT_OF, OWL_DISJOINT_WITH};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+
+const DIR: &str = "./tests/conformance";
+const TEST: &str = "http://www.w3.org/2007/OWL/testOntology#";
+const RULE: &str = "<urn:differential-reasoner:rule>";
+const RDF_TYPE: &str = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#type>";
+
+#[derive(Debug, PartialEq)]
+enum Kind {
+    PositiveEntailment,
+    NegativeEntailment,
+    Consistency,
+    Inconsistency,
+}
+
+struct Case {
+    identifier: String,
+    kind: Kind,
+    rules: Vec<String>,
+    profiles: Vec<String>,
+    premise: String,
+    conclusion: Option<String>,
+}
+
+fn test_term(name: &str) -> String {
+    format!("<{}{}>", TEST, name)
+}
+
+fn literal(term: &str) -> String {
+    term.trim_matches('"').to_string()
+}
+
+fn load_manifest() -> Vec<Case> {
+    let mut subjects: BTreeMap<String, Vec<(String, String)>> = BTreeMap::new();
+    for triple in load3nt(&format!("{}/manifest.nt", DIR)).unwrap() {
+        let (s, p, o) = triple.unwrap();
+        subjects.entry(s).or_default().push((p, o));
+    }
+
+    let mut cases = Vec::new();
+    for (subject, properties) in subjects {
+        let values = |name: &str| -> Vec<String> {
+            properties
+                .iter()
+                .filter(|(p, _)| *p == name)
+                .map(|(_, o)| o.clone())
+                .collect()
+        };
+        let kind = match values(RDF_TYPE)
+            .iter()
+            .find_map(|class| class.strip_prefix(&format!("<{}", TEST)))
+        {
+            Some("PositiveEntailmentTest>") => Kind::PositiveEntailment,
+            Some("NegativeEntailmentTest>") => Kind::NegativeEntailment,
+            Some("ConsistencyTest>") => Kind::Consistency,
+            Some("InconsistencyTest>") => Kind::Inconsistency,
+            _ => panic!("{} has no supported test type", subject),
+        };
+        let conclusion = values(&test_term("conclusionOntology"))
+            .into_iter()
+            .chain(values(&test_term("nonConclusionOntology")))
+            .next()
+            .map(|path| literal(&path));
+        cases.push(Case {
+            identifier: literal(&values(&test_term("identifier"))[0]),
+            kind,
+            rules: values(RULE).iter().map(|rule| literal(rule)).collect(),
+            profiles: values(&test_term("profile")),
+            premise: literal(&values(&test_term("premiseOntology"))[0]),
+            conclusion,
+        });
+    }
+    cases
+}
+
+/// The materialization of `premise`, and the individuals it clashes on.
+fn materialize(
+    profile: Profile,
+    premise: Vec<EncodedTriple>,
+    disjoint_with: usize,
+    complement_of: usize,
+) -> (HashSet<EncodedTriple>, Vec<usize>) {
+    timely::execute_directly(move |worker| {
+        let mut reasoner = Reasoner::new(worker, profile);
+        for triple in premise.iter() {
+            if is_tbox(triple) {
+                reasoner.insert_tbox(*triple);
+            } else {
+                reasoner.insert_abox(*triple);
+            }
+        }
+        reasoner.advance(worker);
+        let materialization: Vec<EncodedTriple> =
+            reasoner.tbox().into_iter().chain(reasoner.abox()).collect();
+        let clashing = clashes(worker, &materialization, disjoint_with, complement_of);
+        (materialization.into_iter().collect(), clashing)
+    })
+}
+
+fn load_ontology(path: &str, dictionary: &mut Dictionary) -> Vec<EncodedTriple> {
+    load3nt(&format!("{}/{}", DIR, path))
+        .unwrap()
+        .map(|triple| dictionary.encode_triple(&triple.unwrap()))
+        .collect()
+}
+
+fn run(case: &Case, profile: Profile) -> bool {
+    let mut dictionary = Dictionary::new();
+    let disjoint_with = dictionary.encode(OWL_DISJOINT_WITH);
+    let complement_of = dictionary.encode(OWL_COMPLEMENT_OF);
+    let premise = load_ontology(&case.premise, &mut dictionary);
+    let conclusion = case
+        .conclusion
+        .as_ref()
+        .map(|path| load_ontology(path, &mut dictionary));
+    let (materialization, clashing) = materialize(profile, premise, disjoint_with, complement_of);
+    let entailed = || {
+        conclusion
+            .as_ref()
+            .unwrap()
+            .iter()
+            .all(|triple| materialization.contains(triple))
+    };
+    match case.kind {
+        Kind::PositiveEntailment => entailed(),
+        Kind::NegativeEntailment => !entailed(),
+        Kind::Consistency => clashing.is_empty(),
+        Kind::Inconsistency => !clashing.is_empty(),
+    }
+}
+
+#[test]
+fn owl2rl_conformance() {
+    let passing: HashSet<(String, String)> = fs::read_to_string(format!("{}/passing.txt", DIR))
+        .unwrap()
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(profile, identifier)| (profile.to_string(), identifier.to_string()))
+        .collect();
+
+    let cases: Vec<Case> = load_manifest()
+        .into_iter()
+        .filter(|case| case.profiles.contains(&test_term("RL")))
+        .collect();
+    assert!(!cases.is_empty());
+
+    let mut regressions = Vec::new();
+    for profile in [Profile::Rdfs, Profile::Rdfspp].iter() {
+        let mut rules: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        println!("{}:", profile);
+        for case in cases.iter() {
+            let passed = run(case, *profile);
+            println!(
+                "  {} {} ({:?})",
+                if passed { "pass" } else { "FAIL" },
+                case.identifier,
+                case.kind
+            );
+            for rule in case.rules.iter() {
+                let counts = rules.entry(rule.as_str()).or_default();
+                counts.1 += 1;
+                if passed {
+                    counts.0 += 1;
+                }
+            }
+            let expected = passing.contains(&(profile.to_string(), case.identifier.clone()));
+            if expected && !passed {
+                regressions.push(format!("{} {}", profile, case.identifier));
+            }
+        }
+        println!("  per rule:");
+        for (rule, (passed, total)) in rules {
+            println!("    {:<10} {}/{}", rule, passed, total);
+        }
+    }
+
+    let unknown: Vec<_> = passing
+        .iter()
+        .filter(|(_, identifier)| !cases.iter().any(|case| &case.identifier == identifier))
+        .collect();
+    assert!(
+        unknown.is_empty(),
+        "unknown cases in passing.txt: {:?}",
+        unknown
+    );
+    assert!(regressions.is_empty(), "regressed: {:?}", regressions);
+}
+
+#[test]
+fn clashes_are_found_across_workers() {
+    let (disjoint_with, complement_of, rdf_type) = (100, 101, 4);
+    // The axioms and the types they clash on are split over the two workers.
+    let triples = vec![
+        (10, disjoint_with, 11),
+        (1, rdf_type, 10),
+        (12, complement_of, 13),
+        (2, rdf_type, 13),
+        (1, rdf_type, 11),
+        (2, rdf_type, 12),
+        (3, rdf_type, 10),
+    ];
+    let shares = timely::execute(timely::Config::process(2), move |worker| {
+        let share: Vec<EncodedTriple> = triples
+            .iter()
+            .skip(worker.index())
+            .step_by(2)
+            .cloned()
+            .collect();
+        let clashing = clashes(worker, &share, disjoint_with, complement_of);
+        // Nothing is left behind for the next call.
+        assert!(worker.installed_dataflows().is_empty());
+        clashing
+    })
+    .unwrap()
+    .join()
+    .into_iter()
+    .map(|share| share.unwrap())
+    .collect::<Vec<_>>();
+    assert_eq!(shares, vec![vec![1, 2], vec![]]);
+}
@@ -0,0 +1,137 @@
+use differential_reasoner::datatypes::{
+    canonical, cardinality, compare, contains, datatypes, datum, Literal, XSD,
+};
+use differential_reasoner::dictionary::Dictionary;
+use differential_reasoner::vocabulary::XSD_ONE_NON_NEGATIVE_INTEGER;
+use std::cmp::Ordering;
+
+fn typed(lexical: &str, datatype: &str) -> String {
+    format!("\"{}\"^^<{}{}>", lexical, XSD, datatype)
+}
+
+fn value_of(term: &str) -> differential_reasoner::datatypes::Value {
+    Literal::parse(term).unwrap().value().unwrap()
+}
+
+#[test]
+fn canonical_lexical_forms() {
+    assert_eq!(canonical(&typed("+007", "integer")), typed("7", "integer"));
+    assert_eq!(canonical(&typed("-0", "int")), typed("0", "int"));
+    assert_eq!(canonical(&typed("1", "decimal")), typed("1.0", "decimal"));
+    assert_eq!(
+        canonical(&typed("01.50", "decimal")),
+        typed("1.5", "decimal")
+    );
+    assert_eq!(
+        canonical(&typed(".05", "decimal")),
+        typed("0.05", "decimal")
+    );
+    assert_eq!(canonical(&typed("1", "boolean")), typed("true", "boolean"));
+    assert_eq!(canonical(&typed("150", "double")), typed("1.5E2", "double"));
+    assert_eq!(canonical(&typed("1", "float")), typed("1.0E0", "float"));
+    assert_eq!(canonical(&typed("-INF", "double")), typed("-INF", "double"));
+    assert_eq!(canonical(&typed("x", "string")), "\"x\"");
+}
+
+#[test]
+fn other_terms_are_unchanged() {
+    for term in [
+        "<http://example.org/a>",
+        "_:b0",
+        "\"plain\"",
+        "\"chat\"@fr",
+        "\"x\"^^<http://example.org/unknown>",
+        // Invalid for the datatype, so kept as written.
+        "\"1.5\"^^<http://www.w3.org/2001/XMLSchema#integer>",
+        "\"-1\"^^<http://www.w3.org/2001/XMLSchema#nonNegativeInteger>",
+        "\"inf\"^^<http://www.w3.org/2001/XMLSchema#double>",
+    ]
+    .iter()
+    {
+        assert_eq!(canonical(term), *term);
+    }
+}
+
+#[test]
+fn dictionary_shares_ids_of_equal_lexical_forms() {
+    let mut dictionary = Dictionary::new();
+    let one = dictionary.encode(&typed("1", "integer"));
+    assert_eq!(dictionary.encode(&typed("01", "integer")), one);
+    assert_eq!(dictionary.get(&typed("+1", "integer")), Some(one));
+    assert_eq!(dictionary.decode(one), Some(typed("1", "integer").as_str()));
+    assert_ne!(dictionary.encode(&typed("1", "decimal")), one);
+}
+
+#[test]
+fn numbers_compare_by_value() {
+    let integer = value_of(&typed("2", "integer"));
+    let decimal = value_of(&typed("2.50", "decimal"));
+    let big = value_of(&typed("100000000000000000000", "integer"));
+    assert_eq!(compare(&integer, &decimal), Some(Ordering::Less));
+    assert_eq!(compare(&big, &decimal), Some(Ordering::Greater));
+    assert_eq!(
+        compare(&value_of(&typed("2.0", "decimal")), &integer),
+        Some(Ordering::Equal)
+    );
+    assert_eq!(
+        compare(
+            &value_of(&typed("1e1", "double")),
+            &value_of(&typed("2", "double"))
+        ),
+        Some(Ordering::Greater)
+    );
+    // Doubles and decimals have disjoint value spaces.
+    assert_eq!(compare(&integer, &value_of(&typed("2", "double"))), None);
+    assert_eq!(compare(&integer, &value_of("\"2\"")), None);
+}
+
+#[test]
+fn cardinalities_are_recognized_by_value() {
+    assert_eq!(cardinality(XSD_ONE_NON_NEGATIVE_INTEGER), Some(1));
+    assert_eq!(cardinality(&typed("01", "nonNegativeInteger")), Some(1));
+    assert_eq!(cardinality(&typed("1", "integer")), Some(1));
+    assert_eq!(cardinality(&typed("0", "int")), Some(0));
+    assert_eq!(cardinality(&typed("-1", "integer")), None);
+    assert_eq!(cardinality(&typed("1", "double")), None);
+    assert_eq!(cardinality("\"1\""), None);
+
+    let mut dictionary = Dictionary::new();
+    let one = dictionary.encode(&typed("1", "positiveInteger"));
+    assert_eq!(dictionary.cardinality(one), Some(1));
+}
+
+#[test]
+fn equal_values_share_a_datum() {
+    let one = datum(&typed("1", "integer")).unwrap();
+    assert_eq!(datum(&typed("1.0", "decimal")), Some(one.clone()));
+    assert_eq!(datum(&typed("+01", "byte")), Some(one.clone()));
+    assert_ne!(datum(&typed("1", "double")), Some(one.clone()));
+    assert_ne!(datum("\"1\""), Some(one));
+    assert_eq!(datum("\"chat\"@FR"), datum("\"chat\"@fr"));
+    assert_eq!(datum(&typed("1.5", "integer")), None);
+    assert_eq!(datum("<http://example.org/a>"), None);
+}
+
+#[test]
+fn value_spaces_contain_data_by_value() {
+    let datatypes = datatypes();
+    let position = |name: &str| {
+        datatypes
+            .iter()
+            .position(|datatype| *datatype == format!("<{}{}>", XSD, name))
+            .unwrap()
+    };
+    let one = datum(&typed("1.0", "decimal")).unwrap();
+    assert!(contains(position("byte"), &one));
+    assert!(contains(position("positiveInteger"), &one));
+    assert!(!contains(position("negativeInteger"), &one));
+    assert!(!contains(position("double"), &one));
+    let half = datum(&typed("0.5", "decimal")).unwrap();
+    assert!(contains(position("decimal"), &half));
+    assert!(!contains(position("integer"), &half));
+    let big = datum(&typed("300", "integer")).unwrap();
+    assert!(!contains(position("byte"), &big));
+    assert!(contains(position("short"), &big));
+    assert!(contains(position("string"), &datum("\"x\"").unwrap()));
+    assert!(!contains(position("string"), &datum("\"x\"@en").unwrap()));
+}
@@ -0,0 +1,43 @@
+use differential_reasoner::dot::OperatorGraph;
+use differential_reasoner::reasoner::{Profile, Reasoner};
+
+fn dot(profile: Profile) -> String {
+    timely::execute(timely::Config::thread(), move |worker| {
+        let graph = OperatorGraph::install(worker);
+        let _reasoner = Reasoner::new(worker, profile);
+        graph.render(worker)
+    })
+    .unwrap()
+    .join()
+    .pop()
+    .unwrap()
+    .unwrap()
+}
+
+#[test]
+fn draws_regions_arrangements_and_feedback() {
+    for profile in [Profile::Rdfs, Profile::Rdfspp].iter() {
+        let dot = dot(*profile);
+        assert!(dot.starts_with("digraph dataflow {\n"));
+        assert!(dot.ends_with("}\n"));
+        for region in [
+            "Tbox transitive rules",
+            "CAX-SCO",
+            "Domain and Range type rules",
+            "Concatenating all rules",
+        ]
+        .iter()
+        {
+            assert!(
+                dot.contains(&format!("label=\"{}\";", region)),
+                "{} is missing",
+                region
+            );
+        }
+        assert!(dot.contains("shape=cylinder"));
+        assert!(dot.contains("[style=dashed, label=\"feedback\"]"));
+        // Every cluster is closed.
+        assert_eq!(dot.matches('{').count(), dot.matches('}').count());
+    }
+    assert!(dot(Profile::Rdfspp).contains("label=\"Abox transitive property rules\";"));
+}
@@ -0,0 +1,119 @@
+use differential_reasoner::graphs::{scope, unscope, EncodedQuad, GraphMode, DEFAULT_GRAPH};
+use differential_reasoner::reasoner::{Profile, Reasoner};
+use std::collections::BTreeSet;
+
+const G: usize = 100;
+const H: usize = 101;
+
+/// Property 20 is transitive and has domain 10; each graph holds one step of
+/// a chain 30 -> 31 -> 32.
+fn materialize(graphs: GraphMode, workers: usize) -> Vec<BTreeSet<EncodedQuad>> {
+    let shares = timely::execute(timely::Config::process(workers), move |worker| {
+        let mut reasoner = Reasoner::with_graphs(worker, Profile::Rdfspp, graphs);
+        let mut steps = Vec::new();
+        if worker.index() == 0 {
+            reasoner.insert_tbox((20, 4, 5));
+            reasoner.insert_tbox((20, 2, 10));
+            reasoner.insert_abox_quad((30, 20, 31, G)).unwrap();
+            reasoner.insert_abox_quad((31, 20, 32, H)).unwrap();
+        }
+        reasoner.advance(worker);
+        steps.push(reasoner.abox_quads());
+        if worker.index() == 0 {
+            reasoner.update_abox_quad((31, 20, 32, H), -1).unwrap();
+        }
+        reasoner.advance(worker);
+        steps.push(reasoner.abox_quads());
+        steps
+    })
+    .unwrap()
+    .join();
+
+    let mut steps = vec![BTreeSet::new(); 2];
+    for share in shares {
+        for (step, quads) in steps.iter_mut().zip(share.unwrap()) {
+            step.extend(quads);
+        }
+    }
+    steps
+}
+
+#[test]
+fn scoping_round_trips() {
+    for quad in [
+        (30, 20, 31, G),
+        (30, 4, 10, G),
+        (0, 1, 2, 0),
+        (30, 20, 31, DEFAULT_GRAPH),
+    ]
+    .iter()
+    {
+        assert_eq!(unscope(scope(*quad).unwrap()), *quad);
+    }
+    assert_eq!(scope((30, 20, 31, DEFAULT_GRAPH)).unwrap(), (30, 20, 31));
+    // Classes are shared with the TBox.
+    assert_eq!(scope((30, 4, 10, G)).unwrap().2, 10);
+}
+
+#[test]
+fn rejects_ids_that_would_overlap_the_graph() {
+    let large = 1 << (std::mem::size_of::<usize>() * 4);
+    for quad in [
+        (large, 20, 31, G),
+        (30, 20, large, G),
+        (30, 4, large, G),
+        (30, 20, 31, large),
+        (large, 20, 31, DEFAULT_GRAPH),
+    ]
+    .iter()
+    {
+        assert!(scope(*quad).is_err(), "{:?}", quad);
+    }
+    assert!(scope((large - 1, 20, 31, large - 2)).is_ok());
+}
+
+#[test]
+fn inferences_stay_in_their_graph() {
+    for workers in [1, 2].iter() {
+        let steps = materialize(GraphMode::PerGraph, *workers);
+        let expected: BTreeSet<EncodedQuad> = [
+            (30, 20, 31, G),
+            (30, 4, 10, G),
+            (31, 20, 32, H),
+            (31, 4, 10, H),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(steps[0], expected);
+        let expected: BTreeSet<EncodedQuad> =
+            [(30, 20, 31, G), (30, 4, 10, G)].iter().cloned().collect();
+        assert_eq!(steps[1], expected);
+    }
+}
+
+#[test]
+fn union_reasons_across_graphs() {
+    for workers in [1, 2].iter() {
+        let steps = materialize(GraphMode::Union, *workers);
+        let d = DEFAULT_GRAPH;
+        let expected: BTreeSet<EncodedQuad> = [
+            (30, 20, 31, G),
+            (31, 20, 32, H),
+            (30, 20, 31, d),
+            (31, 20, 32, d),
+            (30, 20, 32, d),
+            (30, 4, 10, d),
+            (31, 4, 10, d),
+        ]
+        .iter()
+        .cloned()
+        .collect();
+        assert_eq!(steps[0], expected);
+        let expected: BTreeSet<EncodedQuad> = [(30, 20, 31, G), (30, 20, 31, d), (30, 4, 10, d)]
+            .iter()
+            .cloned()
+            .collect();
+        assert_eq!(steps[1], expected);
+    }
+}
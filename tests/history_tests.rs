@@ -0,0 +1,183 @@
+mod common;
+
+use common::{is_tbox, reference, Rng, Triples};
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::reasoner::{Pattern, Profile, Reasoner};
+use std::collections::HashSet;
+
+const SEEDS: u64 = 10;
+const STEPS: usize = 6;
+
+const ALL: Pattern = (None, None, None);
+const TBOX: usize = 0;
+const ABOX: usize = 1;
+
+/// Triples of the materialized TBox or ABox, tagged with their box.
+type Boxed = HashSet<(usize, EncodedTriple)>;
+/// Changes to them.
+type BoxedChanges = HashSet<((usize, EncodedTriple), isize)>;
+
+fn tag(
+    box_: usize,
+    triples: impl IntoIterator<Item = EncodedTriple>,
+) -> impl Iterator<Item = (usize, EncodedTriple)> {
+    triples.into_iter().map(move |triple| (box_, triple))
+}
+
+fn tag_changes(
+    box_: usize,
+    changes: Vec<(EncodedTriple, isize)>,
+) -> impl Iterator<Item = ((usize, EncodedTriple), isize)> {
+    changes
+        .into_iter()
+        .map(move |(triple, diff)| ((box_, triple), diff))
+}
+
+/// Random batches of updates; only absent triples are inserted and only
+/// present ones deleted.
+fn batches(seed: u64) -> Vec<Vec<(EncodedTriple, isize)>> {
+    let mut rng = Rng::new(seed);
+    let mut input = Triples::new();
+    let mut batches = Vec::new();
+    for step in 0..STEPS {
+        let mut updates = Vec::new();
+        let inserts = if step == 0 { 16 } else { rng.below(5) };
+        for _ in 0..inserts {
+            let triple = rng.triple();
+            if input.insert(triple) {
+                updates.push((triple, 1));
+            }
+        }
+        if step > 0 {
+            let mut present: Vec<EncodedTriple> = input.iter().cloned().collect();
+            present.sort_unstable();
+            for _ in 0..rng.below(4) {
+                if present.is_empty() {
+                    break;
+                }
+                let triple = present.swap_remove(rng.below(present.len()));
+                input.remove(&triple);
+                updates.push((triple, -1));
+            }
+        }
+        batches.push(updates);
+    }
+    batches
+}
+
+/// The reference closures after each batch.
+fn closures(batches: &[Vec<(EncodedTriple, isize)>]) -> Vec<Boxed> {
+    let (mut tbox, mut abox) = (Triples::new(), Triples::new());
+    batches
+        .iter()
+        .map(|batch| {
+            for &(triple, diff) in batch.iter() {
+                let input = if is_tbox(&triple) {
+                    &mut tbox
+                } else {
+                    &mut abox
+                };
+                if diff > 0 {
+                    input.insert(triple);
+                } else {
+                    input.remove(&triple);
+                }
+            }
+            let (tbox, abox) = reference(Profile::Rdfspp, &tbox, &abox);
+            tag(TBOX, tbox).chain(tag(ABOX, abox)).collect()
+        })
+        .collect()
+}
+
+/// Applies every batch, then reads the closure as of every batch and its
+/// changes between every pair of batches, gathered from all workers.
+fn read_history(batches: &[Vec<(EncodedTriple, isize)>]) -> (Vec<Boxed>, Vec<Vec<BoxedChanges>>) {
+    let batches = batches.to_vec();
+    let shares = timely::execute(timely::Config::process(2), move |worker| {
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfspp);
+        reasoner.set_history(STEPS);
+        for batch in batches.iter() {
+            if worker.index() == 0 {
+                for &(triple, diff) in batch.iter() {
+                    if is_tbox(&triple) {
+                        reasoner.update_tbox(triple, diff);
+                    } else {
+                        reasoner.update_abox(triple, diff);
+                    }
+                }
+            }
+            reasoner.advance(worker);
+        }
+        let states: Vec<Boxed> = (0..STEPS)
+            .map(|time| {
+                tag(TBOX, reasoner.query_tbox(&ALL, time).unwrap())
+                    .chain(tag(ABOX, reasoner.query_abox(&ALL, time).unwrap()))
+                    .collect()
+            })
+            .collect();
+        let diffs: Vec<Vec<BoxedChanges>> = (0..STEPS)
+            .map(|from| {
+                (0..STEPS)
+                    .map(|to| {
+                        tag_changes(TBOX, reasoner.diff_tbox(&ALL, from, to).unwrap())
+                            .chain(tag_changes(
+                                ABOX,
+                                reasoner.diff_abox(&ALL, from, to).unwrap(),
+                            ))
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+        (states, diffs)
+    })
+    .unwrap()
+    .join();
+
+    let mut states = vec![Boxed::new(); STEPS];
+    let mut diffs = vec![vec![HashSet::new(); STEPS]; STEPS];
+    for share in shares {
+        let (share_states, share_diffs) = share.unwrap();
+        for (state, share) in states.iter_mut().zip(share_states) {
+            state.extend(share);
+        }
+        for (row, share) in diffs.iter_mut().zip(share_diffs) {
+            for (diff, share) in row.iter_mut().zip(share) {
+                diff.extend(share);
+            }
+        }
+    }
+    (states, diffs)
+}
+
+#[test]
+fn reads_and_diffs_past_materializations() {
+    for seed in 0..SEEDS {
+        let batches = batches(seed);
+        let expected = closures(&batches);
+        let (states, diffs) = read_history(&batches);
+        for from in 0..STEPS {
+            assert_eq!(
+                states[from], expected[from],
+                "as-of read differs for seed {} at batch {}",
+                seed, from
+            );
+            for to in 0..STEPS {
+                let expected_diff: BoxedChanges = expected[to]
+                    .difference(&expected[from])
+                    .map(|triple| (*triple, 1))
+                    .chain(
+                        expected[from]
+                            .difference(&expected[to])
+                            .map(|triple| (*triple, -1)),
+                    )
+                    .collect();
+                assert_eq!(
+                    diffs[from][to], expected_diff,
+                    "diff differs for seed {} from batch {} to {}",
+                    seed, from, to
+                );
+            }
+        }
+    }
+}
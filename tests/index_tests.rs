@@ -0,0 +1,85 @@
+use differential_dataflow::input::Input;
+use differential_reasoner::dot::OperatorGraph;
+use differential_reasoner::indexes::partition_by_predicate;
+use differential_reasoner::reasoner::{Profile, Reasoner};
+use std::sync::{Arc, Mutex};
+
+/// The labels of the operator graph of `profile`'s dataflow.
+fn labels(profile: Profile) -> Vec<String> {
+    let dot = timely::execute(timely::Config::thread(), move |worker| {
+        let graph = OperatorGraph::install(worker);
+        let _reasoner = Reasoner::new(worker, profile);
+        graph.render(worker)
+    })
+    .unwrap()
+    .join()
+    .pop()
+    .unwrap()
+    .unwrap();
+    dot.split("label=\"")
+        .skip(1)
+        .map(|label| label[..label.find('"').unwrap()].to_string())
+        .collect()
+}
+
+#[test]
+fn schema_views_are_arranged_once() {
+    for profile in [Profile::Rdfs, Profile::Rdfspp].iter() {
+        let labels = labels(*profile);
+        let count = |name: &str| labels.iter().filter(|label| *label == name).count();
+        for view in [
+            "Shared index rdfs:subClassOf",
+            "Shared index rdfs:subPropertyOf",
+            "Shared index rdfs:domain",
+            "Shared index rdfs:range",
+            "Shared index (class, s)",
+            "Shared index (p, s)",
+            "Shared index (p, o)",
+        ]
+        .iter()
+        {
+            assert_eq!(count(view), 1, "{} for {}", view, profile);
+        }
+        // PRP-DOM and PRP-RNG import the shared views of the assertions.
+        assert!(!labels.iter().any(|label| label.starts_with("Arrange (p, ")));
+        let (inverse_of, transitive) = (
+            count("Shared index owl:inverseOf"),
+            count("Shared index owl:TransitiveProperty"),
+        );
+        match profile {
+            // Views no rule joins with are never arranged.
+            Profile::Rdfs => assert_eq!((inverse_of, transitive), (0, 0)),
+            Profile::Rdfspp => assert_eq!((inverse_of, transitive), (1, 1)),
+        }
+    }
+}
+
+#[test]
+fn partitions_by_predicate_in_one_pass() {
+    let captured = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&captured);
+    timely::execute(timely::Config::thread(), move |worker| {
+        let mut input = worker.dataflow::<usize, _, _>(|scope| {
+            let (input, triples) = scope.new_collection::<(usize, (usize, usize)), isize>();
+            let parts = partition_by_predicate(&triples, &[1, 0]);
+            for (index, part) in parts.into_iter().enumerate() {
+                let sink = Arc::clone(&sink);
+                part.inspect(move |((s, o), _time, diff)| {
+                    sink.lock().unwrap().push((index, *s, *o, *diff))
+                });
+            }
+            input
+        });
+        for triple in [(10, (0, 11)), (20, (1, 21)), (30, (2, 31)), (12, (0, 13))].iter() {
+            input.insert(*triple);
+        }
+    })
+    .unwrap();
+
+    let mut captured = captured.lock().unwrap().clone();
+    captured.sort_unstable();
+    assert_eq!(
+        captured,
+        vec![(0, 20, 21, 1), (1, 10, 11, 1), (1, 12, 13, 1)]
+    );
+}
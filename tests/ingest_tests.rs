@@ -0,0 +1,264 @@
+use differential_reasoner::dictionary::Dictionary;
+use differential_reasoner::error::Error;
+use differential_reasoner::ingest::{
+    Batching, Endpoint, Feed, FeedInput, Late, Listener, Watermarks,
+};
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::reasoner::{Profile, Reasoner};
+use std::io::Write;
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const SUB_CLASS_OF: usize = 0;
+const TYPE: usize = 4;
+const PERSON: usize = 10;
+const PROFESSOR: usize = 11;
+
+/// Follows `inputs` with two workers and returns how many batches each
+/// applied, and the materialized ABox.
+fn follow(inputs: Vec<FeedInput>, tbox: Vec<EncodedTriple>) -> (Vec<usize>, Vec<EncodedTriple>) {
+    let inputs = Arc::new(Mutex::new(inputs.into_iter().map(Some).collect::<Vec<_>>()));
+    let shares = timely::execute(timely::Config::process(2), move |worker| {
+        let input = inputs.lock().unwrap()[worker.index()].take().unwrap();
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfs);
+        if worker.index() == 0 {
+            tbox.iter().for_each(|triple| reasoner.insert_tbox(*triple));
+        }
+        reasoner.advance(worker);
+        let batches = input.follow(worker, &mut reasoner).batches;
+        (batches, reasoner.abox())
+    })
+    .unwrap()
+    .join();
+
+    let (mut batches, mut abox) = (Vec::new(), Vec::new());
+    for share in shares {
+        let (share_batches, share_abox) = share.unwrap();
+        batches.push(share_batches);
+        abox.extend(share_abox);
+    }
+    abox.sort_unstable();
+    (batches, abox)
+}
+
+fn start(
+    endpoint: &str,
+    batching: Batching,
+    dictionary: Option<Dictionary>,
+    connections: usize,
+) -> (Endpoint, Feed, Vec<FeedInput>) {
+    let listener = Listener::bind(&endpoint.parse().unwrap()).unwrap();
+    let endpoint = listener.endpoint().clone();
+    let (feed, inputs) = listener.start(batching, 2, dictionary, Some(connections));
+    (endpoint, feed, inputs)
+}
+
+#[test]
+fn applies_encoded_lines_in_batches_of_a_size() {
+    let batching = Batching {
+        size: 4,
+        interval: None,
+        max_lag: 0,
+    };
+    let (endpoint, feed, inputs) = start("127.0.0.1:0", batching, None, 2);
+    let address = endpoint.to_string();
+    let producer = std::thread::spawn(move || {
+        // Ten updates: batches of 4, 4 and the 2 left when the producer
+        // disconnects.
+        let mut stream = TcpStream::connect(&address).unwrap();
+        for individual in 20..29 {
+            writeln!(stream, "{} {} {}", individual, TYPE, PROFESSOR).unwrap();
+        }
+        writeln!(stream, "# retractions are prefixed with -").unwrap();
+        writeln!(stream, "-20 {} {}", TYPE, PROFESSOR).unwrap();
+        drop(stream);
+
+        // A second producer, whose only line has no line break.
+        let mut stream = TcpStream::connect(&address).unwrap();
+        write!(stream, "+30 {} {}", TYPE, PROFESSOR).unwrap();
+    });
+
+    let (batches, abox) = follow(inputs, vec![(PROFESSOR, SUB_CLASS_OF, PERSON)]);
+    producer.join().unwrap();
+    assert!(feed.join().unwrap().dictionary.is_none());
+
+    assert_eq!(batches, vec![4, 4]);
+    let mut expected = Vec::new();
+    for individual in (21..29).chain(Some(30)) {
+        expected.push((individual, TYPE, PERSON));
+        expected.push((individual, TYPE, PROFESSOR));
+    }
+    expected.sort_unstable();
+    assert_eq!(abox, expected);
+}
+
+#[cfg(unix)]
+#[test]
+fn closes_batches_after_an_interval_on_a_unix_socket() {
+    use std::os::unix::net::UnixStream;
+
+    let path = std::env::temp_dir().join(format!("ingest-test-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let batching = Batching {
+        size: 1000,
+        interval: Some(Duration::from_millis(20)),
+        max_lag: 1,
+    };
+    let mut dictionary = Dictionary::new();
+    let professor = dictionary.encode("<http://ex/Professor>");
+    let person = dictionary.encode("<http://ex/Person>");
+    let (endpoint, feed, inputs) = start(
+        &format!("unix:{}", path.display()),
+        batching,
+        Some(dictionary),
+        1,
+    );
+    assert_eq!(endpoint.to_string(), format!("unix:{}", path.display()));
+
+    let socket = path.clone();
+    let producer = std::thread::spawn(move || {
+        let mut stream = UnixStream::connect(&socket).unwrap();
+        let typed = |name: &str| {
+            format!(
+                "<http://ex/{}> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://ex/Professor> .",
+                name
+            )
+        };
+        writeln!(stream, "{}", typed("alice")).unwrap();
+        writeln!(stream, "{}", typed("bob")).unwrap();
+        // Long enough for the batch to close before the retraction.
+        std::thread::sleep(Duration::from_millis(200));
+        writeln!(stream, "- {}", typed("alice")).unwrap();
+    });
+
+    let (batches, abox) = follow(inputs, vec![(professor, SUB_CLASS_OF, person)]);
+    producer.join().unwrap();
+    let dictionary = feed.join().unwrap().dictionary.unwrap();
+    assert!(!path.exists(), "the socket is removed once the feed ends");
+
+    assert_eq!(batches, vec![2, 2]);
+    let bob = dictionary.get("<http://ex/bob>").unwrap();
+    let mut expected = vec![(bob, TYPE, person), (bob, TYPE, professor)];
+    expected.sort_unstable();
+    assert_eq!(abox, expected);
+}
+
+#[test]
+fn skips_malformed_lines() {
+    let batching = Batching {
+        size: 1,
+        interval: None,
+        max_lag: 0,
+    };
+    let (endpoint, feed, inputs) = start("127.0.0.1:0", batching, None, 1);
+    let mut stream = TcpStream::connect(endpoint.to_string()).unwrap();
+    writeln!(stream, "20 {} {}", TYPE, PERSON).unwrap();
+    writeln!(stream, "20 {}", TYPE).unwrap();
+    stream.write_all(b"21 4 \xff\n").unwrap();
+    writeln!(stream, "22 {} {}", TYPE, PERSON).unwrap();
+    drop(stream);
+
+    let (_batches, abox) = follow(inputs, Vec::new());
+    let fed = feed.join().unwrap();
+    assert!(fed.dictionary.is_none());
+    assert_eq!(abox, vec![(20, TYPE, PERSON), (22, TYPE, PERSON)]);
+    let lines: Vec<_> = fed
+        .skipped
+        .iter()
+        .map(|error| match error {
+            Error::Parse { line, .. } => *line,
+            _ => panic!("unexpected {}", error),
+        })
+        .collect();
+    assert_eq!(lines, vec![2, 3]);
+}
+
+/// Sends out-of-order events with a lateness of 2 and returns the people as
+/// of timestamps 1 to 5, and how many events were late.
+fn follow_events(late: Late) -> (Vec<Vec<usize>>, usize) {
+    let watermarks = Watermarks {
+        lateness: 2,
+        late,
+        size: 100,
+        max_lag: 1,
+    };
+    let listener = Listener::bind(&"127.0.0.1:0".parse().unwrap()).unwrap();
+    let address = listener.endpoint().to_string();
+    let (feed, inputs) = listener.start_events(watermarks, 2, None, Some(1));
+    let mut stream = TcpStream::connect(address).unwrap();
+    for line in [
+        "3 20 4 11",
+        "1 21 4 11",
+        "5 22 4 11",
+        "2 23 4 11",
+        "4 -20 4 11",
+    ]
+    .iter()
+    {
+        writeln!(stream, "{}", line).unwrap();
+    }
+    drop(stream);
+
+    let inputs = Arc::new(Mutex::new(inputs.into_iter().map(Some).collect::<Vec<_>>()));
+    let shares = timely::execute(timely::Config::process(2), move |worker| {
+        let input = inputs.lock().unwrap()[worker.index()].take().unwrap();
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfs);
+        reasoner.set_history(10);
+        if worker.index() == 0 {
+            reasoner.insert_tbox((PROFESSOR, SUB_CLASS_OF, PERSON));
+        }
+        reasoner.advance(worker);
+        let followed = input.follow(worker, &mut reasoner);
+        assert_eq!(followed.batches, 4);
+        let people: Vec<Vec<EncodedTriple>> = (1..=5)
+            .map(|time| {
+                reasoner
+                    .query_abox(&(None, Some(TYPE), Some(PERSON)), time)
+                    .unwrap()
+            })
+            .collect();
+        (people, followed.late)
+    })
+    .unwrap()
+    .join();
+    feed.join().unwrap();
+
+    let mut people = vec![Vec::new(); 5];
+    let mut late = 0;
+    for share in shares {
+        let (share_people, share_late) = share.unwrap();
+        for (people, share) in people.iter_mut().zip(share_people) {
+            people.extend(share.into_iter().map(|(s, _p, _o)| s));
+        }
+        late += share_late;
+    }
+    for people in people.iter_mut() {
+        people.sort_unstable();
+    }
+    (people, late)
+}
+
+#[test]
+fn inserts_events_at_their_timestamps() {
+    // 23, at timestamp 2, arrives once the watermark has passed 3.
+    let (people, late) = follow_events(Late::Drop);
+    assert_eq!(late, 1);
+    assert_eq!(
+        people,
+        vec![vec![21], vec![21], vec![20, 21], vec![21], vec![21, 22]]
+    );
+
+    let (people, late) = follow_events(Late::Correct);
+    assert_eq!(late, 1);
+    assert_eq!(
+        people,
+        vec![
+            vec![21],
+            vec![21],
+            vec![20, 21, 23],
+            vec![21, 23],
+            vec![21, 22, 23]
+        ]
+    );
+}
@@ -0,0 +1,232 @@
+use differential_dataflow::input::Input;
+use differential_reasoner::dictionary::Dictionary;
+use differential_reasoner::lists::{decode, list_axioms, ListAxiom, ListTerms};
+use differential_reasoner::load_encode_triples::parse3nt;
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::owl2rl::list_rules;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+use timely::dataflow::operators::probe::Handle;
+
+const ONTOLOGY: &str = r#"
+<http://ex/uncle> <http://www.w3.org/2002/07/owl#propertyChainAxiom> _:c0 .
+_:c0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> <http://ex/parent> .
+_:c0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> _:c1 .
+_:c1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> <http://ex/brother> .
+_:c1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> <http://www.w3.org/1999/02/22-rdf-syntax-ns#nil> .
+<http://ex/Employee> <http://www.w3.org/2002/07/owl#hasKey> _:k0 .
+_:k0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> <http://ex/employer> .
+_:k0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> _:k1 .
+_:k1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> <http://ex/employeeId> .
+_:k1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> <http://www.w3.org/1999/02/22-rdf-syntax-ns#nil> .
+_:d <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://www.w3.org/2002/07/owl#AllDisjointClasses> .
+_:d <http://www.w3.org/2002/07/owl#members> _:k1 .
+<http://ex/Loop> <http://www.w3.org/2002/07/owl#unionOf> _:l0 .
+_:l0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> <http://ex/A> .
+_:l0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> _:l0 .
+"#;
+
+/// Inserts `ontology`, then applies each of `changes`, and returns the list
+/// axioms after every step.
+fn decode_steps(
+    terms: ListTerms,
+    ontology: Vec<EncodedTriple>,
+    changes: Vec<Vec<(EncodedTriple, isize)>>,
+) -> Vec<Vec<ListAxiom>> {
+    timely::execute_directly(move |worker| {
+        let axioms = Rc::new(RefCell::new(BTreeMap::new()));
+        let sink = axioms.clone();
+        let mut probe = Handle::new();
+
+        let mut input = worker.dataflow::<usize, _, _>(|scope| {
+            let (input, tbox) = scope.new_collection::<EncodedTriple, isize>();
+            list_axioms(&tbox, terms, scope)
+                .inspect(move |(axiom, _time, diff)| {
+                    *sink.borrow_mut().entry(axiom.clone()).or_insert(0) += diff;
+                })
+                .probe_with(&mut probe);
+            input
+        });
+
+        let mut steps = Vec::new();
+        let updates = std::iter::once(ontology.into_iter().map(|t| (t, 1)).collect())
+            .chain(changes.into_iter());
+        for (time, updates) in updates.enumerate() {
+            for (triple, diff) in updates {
+                input.update(triple, diff);
+            }
+            input.advance_to(time + 1);
+            input.flush();
+            worker.step_while(|| probe.less_than(input.time()));
+            steps.push(
+                axioms
+                    .borrow()
+                    .iter()
+                    .filter(|(_axiom, count)| **count > 0)
+                    .map(|(axiom, _count)| axiom.clone())
+                    .collect(),
+            );
+        }
+        steps
+    })
+}
+
+fn encode(dictionary: &mut Dictionary, text: &str) -> Vec<EncodedTriple> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| dictionary.encode_triple(&parse3nt(line).unwrap()))
+        .collect()
+}
+
+#[test]
+fn decodes_list_axioms() {
+    let mut dictionary = Dictionary::new();
+    let terms = ListTerms::resolve(&mut dictionary);
+    let ontology = encode(&mut dictionary, ONTOLOGY);
+    let id = |term: &str| dictionary.get(term).unwrap();
+
+    let steps = decode_steps(terms, ontology, vec![]);
+    assert_eq!(
+        steps[0],
+        vec![
+            ListAxiom::PropertyChain {
+                property: id("<http://ex/uncle>"),
+                chain: vec![id("<http://ex/parent>"), id("<http://ex/brother>")],
+            },
+            ListAxiom::HasKey {
+                class: id("<http://ex/Employee>"),
+                properties: vec![id("<http://ex/employer>"), id("<http://ex/employeeId>")],
+            },
+            ListAxiom::AllDisjointClasses {
+                classes: vec![id("<http://ex/employeeId>")],
+            },
+        ]
+    );
+}
+
+#[test]
+fn rebuilds_axioms_when_lists_change() {
+    let mut dictionary = Dictionary::new();
+    let terms = ListTerms::resolve(&mut dictionary);
+    let ontology = encode(&mut dictionary, ONTOLOGY);
+    let old_rest = encode(
+        &mut dictionary,
+        "_:c1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> <http://www.w3.org/1999/02/22-rdf-syntax-ns#nil> .",
+    );
+    let new_cell = encode(
+        &mut dictionary,
+        "_:c1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> _:c2 .
+        _:c2 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> <http://ex/son> .
+        _:c2 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> <http://www.w3.org/1999/02/22-rdf-syntax-ns#nil> .",
+    );
+    let id = |term: &str| dictionary.get(term).unwrap();
+
+    let steps = decode_steps(
+        terms,
+        ontology,
+        vec![
+            vec![(old_rest[0], -1)],
+            new_cell.iter().map(|&triple| (triple, 1)).collect(),
+        ],
+    );
+
+    let chains = |axioms: &[ListAxiom]| -> Vec<Vec<usize>> {
+        axioms
+            .iter()
+            .filter_map(|axiom| match axiom {
+                ListAxiom::PropertyChain { chain, .. } => Some(chain.clone()),
+                _ => None,
+            })
+            .collect()
+    };
+    assert_eq!(
+        chains(&steps[0]),
+        vec![vec![id("<http://ex/parent>"), id("<http://ex/brother>")]]
+    );
+    // Without its last rdf:rest the list is incomplete.
+    assert_eq!(chains(&steps[1]), Vec::<Vec<usize>>::new());
+    assert_eq!(
+        chains(&steps[2]),
+        vec![vec![
+            id("<http://ex/parent>"),
+            id("<http://ex/brother>"),
+            id("<http://ex/son>"),
+        ]]
+    );
+}
+
+const RULES: &str = r#"
+<http://ex/uncle> <http://www.w3.org/2002/07/owl#propertyChainAxiom> _:c0 .
+_:c0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> <http://ex/parent> .
+_:c0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> _:c1 .
+_:c1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> <http://ex/brother> .
+_:c1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> <http://www.w3.org/1999/02/22-rdf-syntax-ns#nil> .
+<http://ex/Nephew> <http://www.w3.org/2002/07/owl#oneOf> _:o0 .
+_:o0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> <http://ex/ann> .
+_:o0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> <http://www.w3.org/1999/02/22-rdf-syntax-ns#nil> .
+_:d <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://www.w3.org/2002/07/owl#AllDisjointClasses> .
+_:d <http://www.w3.org/2002/07/owl#members> _:d0 .
+_:d0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> <http://ex/Nephew> .
+_:d0 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> _:d1 .
+_:d1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#first> <http://ex/Uncle> .
+_:d1 <http://www.w3.org/1999/02/22-rdf-syntax-ns#rest> <http://www.w3.org/1999/02/22-rdf-syntax-ns#nil> .
+"#;
+
+const FACTS: &str = r#"
+<http://ex/ann> <http://ex/parent> <http://ex/bob> .
+<http://ex/bob> <http://ex/brother> <http://ex/carl> .
+<http://ex/ann> <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://ex/Uncle> .
+"#;
+
+#[test]
+fn applies_the_rules_of_decoded_axioms() {
+    let mut dictionary = Dictionary::new();
+    let terms = ListTerms::resolve(&mut dictionary);
+    let tbox = encode(&mut dictionary, RULES);
+    let abox = encode(&mut dictionary, FACTS);
+    let id = |term: &str| dictionary.get(term).unwrap();
+
+    let (triples, clashes) = timely::execute_directly(move |worker| {
+        let axioms = decode(worker, &tbox, terms);
+        let triples = Rc::new(RefCell::new(BTreeSet::new()));
+        let clashes = Rc::new(RefCell::new(BTreeSet::new()));
+        let (triple_sink, clash_sink) = (triples.clone(), clashes.clone());
+        let mut probe = Handle::new();
+
+        let mut input = worker.dataflow::<usize, _, _>(|scope| {
+            let (input, abox) = scope.new_collection::<EncodedTriple, isize>();
+            let conclusions = list_rules(&abox, &axioms, scope).unwrap();
+            conclusions
+                .triples
+                .inspect(move |(triple, _time, _diff)| {
+                    triple_sink.borrow_mut().insert(*triple);
+                })
+                .probe_with(&mut probe);
+            conclusions
+                .clashes
+                .inspect(move |(x, _time, _diff)| {
+                    clash_sink.borrow_mut().insert(*x);
+                })
+                .probe_with(&mut probe);
+            input
+        });
+        for triple in abox.iter() {
+            input.insert(*triple);
+        }
+        input.advance_to(1);
+        input.flush();
+        worker.step_while(|| probe.less_than(input.time()));
+
+        let triples = triples.borrow().clone();
+        let clashes = clashes.borrow().clone();
+        (triples, clashes)
+    });
+
+    // prp-spo2 follows the chain, cls-oo types ann, and cax-adc finds ann in
+    // two of the disjoint classes.
+    let (ann, carl) = (id("<http://ex/ann>"), id("<http://ex/carl>"));
+    assert!(triples.contains(&(ann, id("<http://ex/uncle>"), carl)));
+    assert!(triples.contains(&(ann, 4, id("<http://ex/Nephew>"))));
+    assert_eq!(clashes, vec![ann].into_iter().collect());
+}
@@ -0,0 +1,157 @@
+use differential_reasoner::error::Error;
+use differential_reasoner::load_encode_triples::{
+    load3enc, load3enc_delta, load3nt, load3nt_delta, load4nq, parse3nt, parse4nq,
+};
+use differential_reasoner::trig::parse_trig;
+use std::fs;
+use std::path::PathBuf;
+
+fn write_temp(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "differential-reasoner-{}-{}",
+        std::process::id(),
+        name
+    ));
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn missing_file_is_an_io_error() {
+    match load3enc("./encoded_data/test/does_not_exist.ntenc") {
+        Err(Error::Io { path, .. }) => {
+            assert_eq!(path, "./encoded_data/test/does_not_exist.ntenc")
+        }
+        _ => panic!("expected an I/O error"),
+    }
+}
+
+#[test]
+fn blank_lines_are_skipped() {
+    let path = write_temp("blank.ntenc", "1 2 3\n\n4 5 6\n\n");
+    let triples = load3enc(path.to_str().unwrap())
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(triples, vec![(1, 2, 3), (4, 5, 6)]);
+}
+
+#[test]
+fn malformed_id_reports_its_line() {
+    let path = write_temp("malformed.ntenc", "1 2 3\n4 five 6\n");
+    let results = load3enc(path.to_str().unwrap())
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert!(results[0].is_ok());
+    match &results[1] {
+        Err(Error::Parse { line, message, .. }) => {
+            assert_eq!(*line, 2);
+            assert!(message.contains("predicate"));
+        }
+        _ => panic!("expected a parse error"),
+    }
+}
+
+#[test]
+fn ntriples_terms_keep_literals_intact() {
+    let (s, p, o) = parse3nt(
+        "<http://ex.org/a> <http://ex.org/label> \"a b . c\"^^<http://www.w3.org/2001/XMLSchema#string> .",
+    )
+    .unwrap();
+    assert_eq!(s, "<http://ex.org/a>");
+    assert_eq!(p, "<http://ex.org/label>");
+    assert_eq!(o, "\"a b . c\"^^<http://www.w3.org/2001/XMLSchema#string>");
+
+    assert!(parse3nt("<http://ex.org/a> <http://ex.org/b> .").is_err());
+
+    let path = write_temp(
+        "comments.nt",
+        "# a comment\n_:a <http://ex.org/p> \"x\"@en .\n_:b <http://ex.org/p> _:c.\n",
+    );
+    let triples = load3nt(path.to_str().unwrap())
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(triples.len(), 2);
+    assert_eq!(triples[1].2, "_:c");
+}
+
+#[test]
+fn nquads_keep_their_graph() {
+    assert_eq!(
+        parse4nq("<http://ex.org/a> <http://ex.org/p> \"x\" <http://ex.org/g> .").unwrap(),
+        (
+            "<http://ex.org/a>".to_string(),
+            "<http://ex.org/p>".to_string(),
+            "\"x\"".to_string(),
+            Some("<http://ex.org/g>".to_string())
+        )
+    );
+    assert_eq!(parse4nq("_:a <http://ex.org/p> _:b .").unwrap().3, None);
+    assert!(parse4nq("_:a <http://ex.org/p> _:b \"g\" .").is_err());
+
+    let path = write_temp(
+        "graphs.nq",
+        "_:a <http://ex.org/p> _:b _:g .\n_:a <http://ex.org/p> _:b <http://ex.org/p> _:c .\n",
+    );
+    match &load4nq(path.to_str().unwrap()).unwrap().collect::<Vec<_>>()[1] {
+        Err(Error::Parse { line, .. }) => assert_eq!(*line, 2),
+        _ => panic!("expected a parse error"),
+    }
+}
+
+#[test]
+fn trig_blocks_name_their_graph() {
+    let triples = parse_trig(
+        "@prefix ex: <http://ex.org/> .\n\
+         PREFIX xsd: <http://www.w3.org/2001/XMLSchema#>\n\
+         ex:a ex:p ex:b .\n\
+         GRAPH ex:g { ex:a a ex:C ; ex:q \"1\"^^xsd:integer , ex:c . }\n\
+         <http://ex.org/h> { _:x ex:p ex:y }\n\
+         { ex:d ex:p ex:e }\n",
+    )
+    .unwrap();
+    let graph = |name: &str| Some(format!("<http://ex.org/{}>", name));
+    let graphs: Vec<_> = triples.iter().map(|(_, graph)| graph.clone()).collect();
+    assert_eq!(
+        graphs,
+        vec![None, graph("g"), graph("g"), graph("g"), graph("h"), None]
+    );
+    assert_eq!(
+        triples[2].0 .2,
+        "\"1\"^^<http://www.w3.org/2001/XMLSchema#integer>"
+    );
+    assert_eq!(triples[4].0 .0, "_:x");
+
+    assert_eq!(
+        parse_trig("@prefix ex: <http://ex.org/> .\nex:g {\n ex:a ex:p }\n")
+            .unwrap_err()
+            .0,
+        3
+    );
+}
+
+#[test]
+fn delta_lines_carry_their_sign() {
+    let path = write_temp(
+        "delta.nt",
+        "# retract and add\n- <http://ex.org/A> <http://ex.org/p> <http://ex.org/B> .\n+<http://ex.org/A> <http://ex.org/p> <http://ex.org/C> .\n",
+    );
+    let updates = load3nt_delta(path.to_str().unwrap())
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(updates.len(), 2);
+    assert_eq!((updates[0].1, updates[1].1), (-1, 1));
+    assert_eq!(updates[1].0 .2, "<http://ex.org/C>");
+
+    let path = write_temp("delta.ntenc", "+ 1 0 2\n1 0 3\n");
+    let results = load3enc_delta(path.to_str().unwrap())
+        .unwrap()
+        .collect::<Vec<_>>();
+    assert_eq!(results[0].as_ref().unwrap(), &((1, 0, 2), 1));
+    match &results[1] {
+        Err(Error::Parse { line, .. }) => assert_eq!(*line, 2),
+        _ => panic!("expected a parse error"),
+    }
+}
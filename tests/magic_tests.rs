@@ -0,0 +1,192 @@
+mod common;
+
+use common::{is_tbox, reference, Rng, Triples};
+use differential_reasoner::magic::{parse_goal, Answer, GoalReasoner};
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::reasoner::{matches, Pattern, Profile};
+use std::collections::HashSet;
+
+const SEEDS: u64 = 25;
+const STEPS: usize = 6;
+const TYPE: usize = 4;
+
+type Answers = HashSet<Answer>;
+
+/// Each step lists the triples and goals to insert (`1`) or delete (`-1`).
+type Script = Vec<(Vec<(EncodedTriple, isize)>, Vec<(Pattern, isize)>)>;
+
+/// A goal binding a class, a property, a subject or nothing at all, over the
+/// ids of `Rng::triple`.
+fn goal(rng: &mut Rng) -> Pattern {
+    let class = 100 + rng.below(5);
+    let property = 120 + rng.below(5);
+    let individual = 140 + rng.below(8);
+    match rng.below(6) {
+        0 | 1 => (None, Some(TYPE), Some(class)),
+        2 => (None, Some(property), None),
+        3 => (Some(individual), Some(property), None),
+        4 => (Some(individual), Some(TYPE), None),
+        _ => (None, None, Some(individual)),
+    }
+}
+
+/// Random triple and goal updates; like the reference tests, only absent
+/// triples and goals are inserted and only present ones deleted.
+fn script(seed: u64) -> Script {
+    let mut rng = Rng::new(seed);
+    let mut input = Triples::new();
+    let mut goals = HashSet::new();
+    let mut script = Vec::new();
+    for step in 0..STEPS {
+        let mut updates = Vec::new();
+        let mut goal_updates = Vec::new();
+        let inserts = if step == 0 { 16 } else { rng.below(5) };
+        for _ in 0..inserts {
+            let triple = rng.triple();
+            if input.insert(triple) {
+                updates.push((triple, 1));
+            }
+        }
+        for _ in 0..rng.below(3) + usize::from(step == 0) {
+            let goal = goal(&mut rng);
+            if goals.insert(goal) {
+                goal_updates.push((goal, 1));
+            }
+        }
+        if step > 0 {
+            let mut present: Vec<EncodedTriple> = input.iter().cloned().collect();
+            present.sort_unstable();
+            for _ in 0..rng.below(4) {
+                if present.is_empty() {
+                    break;
+                }
+                let triple = present.swap_remove(rng.below(present.len()));
+                input.remove(&triple);
+                updates.push((triple, -1));
+            }
+            let mut present: Vec<Pattern> = goals.iter().cloned().collect();
+            present.sort_unstable();
+            if !present.is_empty() && rng.below(3) == 0 {
+                let goal = present.swap_remove(rng.below(present.len()));
+                goals.remove(&goal);
+                goal_updates.push((goal, -1));
+            }
+        }
+        script.push((updates, goal_updates));
+    }
+    script
+}
+
+/// The answers after every step of `script`, gathered from all workers.
+fn answer(workers: usize, script: &Script) -> Vec<Answers> {
+    let script = script.clone();
+    let shares = timely::execute(timely::Config::process(workers), move |worker| {
+        let mut reasoner = GoalReasoner::new(worker);
+        let mut results = Vec::new();
+        for (updates, goal_updates) in script.iter() {
+            if worker.index() == 0 {
+                for &(triple, diff) in updates.iter() {
+                    if is_tbox(&triple) {
+                        reasoner.update_tbox(triple, diff);
+                    } else {
+                        reasoner.update_abox(triple, diff);
+                    }
+                }
+                for &(goal, diff) in goal_updates.iter() {
+                    reasoner.update_goal(goal, diff);
+                }
+            }
+            reasoner.advance(worker);
+            results.push(reasoner.answers());
+        }
+        results
+    })
+    .unwrap()
+    .join();
+
+    let mut steps = vec![Answers::new(); script.len()];
+    for share in shares {
+        for (step, answers) in steps.iter_mut().zip(share.unwrap()) {
+            step.extend(answers);
+        }
+    }
+    steps
+}
+
+fn check(workers: usize) {
+    for seed in 0..SEEDS {
+        let script = script(seed);
+        let mut tbox = Triples::new();
+        let mut abox = Triples::new();
+        let mut goals = HashSet::new();
+        let steps = answer(workers, &script);
+        for (step, ((updates, goal_updates), actual)) in script.iter().zip(steps).enumerate() {
+            for &(triple, diff) in updates.iter() {
+                let input = if is_tbox(&triple) {
+                    &mut tbox
+                } else {
+                    &mut abox
+                };
+                if diff > 0 {
+                    input.insert(triple);
+                } else {
+                    input.remove(&triple);
+                }
+            }
+            for &(goal, diff) in goal_updates.iter() {
+                if diff > 0 {
+                    goals.insert(goal);
+                } else {
+                    goals.remove(&goal);
+                }
+            }
+            let (_tbox, closure) = reference(Profile::Rdfspp, &tbox, &abox);
+            let expected: Answers = goals
+                .iter()
+                .flat_map(|goal| {
+                    closure
+                        .iter()
+                        .filter(move |triple| matches(goal, triple))
+                        .map(move |triple| (*goal, *triple))
+                })
+                .collect();
+            assert_eq!(
+                actual, expected,
+                "answers differ for seed {} at step {} with {} workers",
+                seed, step, workers
+            );
+        }
+    }
+}
+
+#[test]
+fn goals_match_the_full_closure() {
+    check(1);
+}
+
+#[test]
+fn goals_match_the_full_closure_with_several_workers() {
+    check(3);
+}
+
+#[test]
+fn parses_goals() {
+    assert_eq!(
+        parse_goal("?x <http://www.w3.org/1999/02/22-rdf-syntax-ns#type> <http://example.org/C>"),
+        Ok((
+            None,
+            Some("<http://www.w3.org/1999/02/22-rdf-syntax-ns#type>".to_string()),
+            Some("<http://example.org/C>".to_string())
+        ))
+    );
+    assert_eq!(
+        parse_goal(" 140  ?p \"a b\"@en "),
+        Ok((
+            Some("140".to_string()),
+            None,
+            Some("\"a b\"@en".to_string())
+        ))
+    );
+    assert!(parse_goal("?x ?p").is_err());
+    assert!(parse_goal("?x ?p ?o ?g").is_err());
+}
@@ -2,15 +2,15 @@ use differential_dataflow::input::Input;
 use differential_dataflow::operators::arrange::ArrangeBySelf;
 use differential_dataflow::trace::cursor::CursorDebug;
 use differential_dataflow::trace::TraceReader;
-use differential_reasoner::load_encode_triples::{load3enc, loadkvenc, read_file};
+use differential_reasoner::load_encode_triples::{load3enc, loadkvenc};
 use differential_reasoner::materializations::{rdfs, rdfspp};
 use std::collections::BTreeMap;
 use timely::dataflow::operators::probe::Handle;
 
 #[test]
 fn rdfs_test() {
-    let tbox_triples = load3enc("./encoded_data/test/tbox.ntenc");
-    let abox_triples = load3enc("./encoded_data/test/abox.ntenc");
+    let tbox_triples = load3enc("./encoded_data/test/tbox.ntenc").unwrap();
+    let abox_triples = load3enc("./encoded_data/test/abox.ntenc").unwrap();
 
     let (mut tbox_summaries, mut abox_summaries) = timely::execute_directly(move |worker| {
         let mut tbox_probe = Handle::new();
@@ -33,7 +33,7 @@ fn rdfs_test() {
             });
 
         tbox_triples.for_each(|triple| {
-            tbox_input_stream.insert((triple.0, triple.1, triple.2));
+            tbox_input_stream.insert(triple.unwrap());
         });
 
         tbox_input_stream.advance_to(1);
@@ -41,7 +41,7 @@ fn rdfs_test() {
         worker.step_while(|| tbox_probe.less_than(tbox_input_stream.time()));
 
         abox_triples.for_each(|triple| {
-            abox_input_stream.insert((triple.0, triple.1, triple.2));
+            abox_input_stream.insert(triple.unwrap());
         });
         abox_input_stream.advance_to(1);
         abox_input_stream.flush();
@@ -56,12 +56,12 @@ fn rdfs_test() {
         )
     });
 
-    let encoding_map_file = loadkvenc("./encoded_data/test/encoding_mapping.kv");
+    let encoding_map_file = loadkvenc("./encoded_data/test/encoding_mapping.kv").unwrap();
 
     let mut encoding_map = BTreeMap::<usize, String>::new();
 
     for key_value_par in encoding_map_file {
-        let (key, value) = key_value_par;
+        let (key, value) = key_value_par.unwrap();
         encoding_map.insert(key, value.clone());
     }
 
@@ -95,8 +95,8 @@ fn rdfs_test() {
 
 #[test]
 fn rdfspp_test() {
-    let tbox_triples = load3enc("./encoded_data/test/tbox.ntenc");
-    let abox_triples = load3enc("./encoded_data/test/abox.ntenc");
+    let tbox_triples = load3enc("./encoded_data/test/tbox.ntenc").unwrap();
+    let abox_triples = load3enc("./encoded_data/test/abox.ntenc").unwrap();
 
     let (mut tbox_summaries, mut abox_summaries) = timely::execute_directly(move |worker| {
         let mut tbox_probe = Handle::new();
@@ -119,7 +119,7 @@ fn rdfspp_test() {
             });
 
         tbox_triples.for_each(|triple| {
-            tbox_input_stream.insert((triple.0, triple.1, triple.2));
+            tbox_input_stream.insert(triple.unwrap());
         });
 
         tbox_input_stream.advance_to(1);
@@ -127,7 +127,7 @@ fn rdfspp_test() {
         worker.step_while(|| tbox_probe.less_than(tbox_input_stream.time()));
 
         abox_triples.for_each(|triple| {
-            abox_input_stream.insert((triple.0, triple.1, triple.2));
+            abox_input_stream.insert(triple.unwrap());
         });
         abox_input_stream.advance_to(1);
         abox_input_stream.flush();
@@ -142,12 +142,12 @@ fn rdfspp_test() {
         )
     });
 
-    let encoding_map_file = loadkvenc("./encoded_data/test/encoding_mapping.kv");
+    let encoding_map_file = loadkvenc("./encoded_data/test/encoding_mapping.kv").unwrap();
 
     let mut encoding_map = BTreeMap::<usize, String>::new();
 
     for key_value_par in encoding_map_file {
-        let (key, value) = key_value_par;
+        let (key, value) = key_value_par.unwrap();
         encoding_map.insert(key, value.clone());
     }
 
@@ -0,0 +1,223 @@
+mod common;
+
+use common::{is_tbox, Rng, Triples};
+use differential_reasoner::dictionary::Dictionary;
+use differential_reasoner::graphs::GraphMode;
+use differential_reasoner::load_encode_triples::load3enc;
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::partition::Schema;
+use differential_reasoner::reasoner::{Profile, Reasoner};
+use differential_reasoner::vocabulary::*;
+
+#[test]
+fn triples_are_routed_by_vocabulary() {
+    let mut dictionary = Dictionary::new();
+    let schema = Schema::resolve(&mut dictionary);
+    let mut encode = |s: &str, p: &str, o: &str| {
+        dictionary.encode_triple(&(s.to_string(), p.to_string(), o.to_string()))
+    };
+    let (a, b, x) = (
+        "<http://ex.org/A>",
+        "<http://ex.org/B>",
+        "<http://ex.org/x>",
+    );
+
+    for axiom in [
+        encode(a, RDFS_SUB_CLASS_OF, b),
+        encode(a, OWL_EQUIVALENT_CLASS, b),
+        encode(a, OWL_ON_PROPERTY, b),
+        encode("_:l", RDF_FIRST, a),
+        encode("_:l", RDF_REST, RDF_NIL),
+        encode(a, RDF_TYPE, OWL_CLASS),
+        encode(a, RDF_TYPE, OWL_TRANSITIVE_PROPERTY),
+        encode(a, RDF_TYPE, OWL_FUNCTIONAL_PROPERTY),
+    ]
+    .iter()
+    {
+        assert!(schema.is_schema(axiom), "{:?}", axiom);
+    }
+    for assertion in [
+        encode(x, RDF_TYPE, a),
+        encode(x, b, a),
+        encode(x, RDFS_LABEL, "\"x\""),
+    ]
+    .iter()
+    {
+        assert!(!schema.is_schema(assertion), "{:?}", assertion);
+    }
+}
+
+#[test]
+fn triples_about_declared_terms_go_to_the_tbox() {
+    let mut dictionary = Dictionary::new();
+    let schema = Schema::resolve(&mut dictionary);
+    let mut encode = |s: &str, p: &str, o: &str| {
+        dictionary.encode_triple(&(s.to_string(), p.to_string(), o.to_string()))
+    };
+    let (a, x) = ("<http://ex.org/A>", "<http://ex.org/x>");
+    let declaration = encode(a, RDF_TYPE, OWL_CLASS);
+    let class_label = encode(a, RDFS_LABEL, "\"A\"");
+    let assertion = encode(x, RDF_TYPE, a);
+    let individual_label = encode(x, RDFS_LABEL, "\"x\"");
+
+    let steps = vec![
+        vec![(class_label, 1), (assertion, 1), (individual_label, 1)],
+        vec![(declaration, 1)],
+        vec![(declaration, -1)],
+    ];
+    let results = timely::execute_directly(move |worker| {
+        let mut reasoner = Reasoner::with_schema(worker, Profile::Rdfs, GraphMode::Union, schema);
+        let mut results = Vec::new();
+        for updates in steps.iter() {
+            for &(triple, diff) in updates.iter() {
+                reasoner.update(triple, diff);
+            }
+            reasoner.advance(worker);
+            results.push((
+                reasoner.tbox().into_iter().collect::<Triples>(),
+                reasoner.abox().into_iter().collect::<Triples>(),
+            ));
+        }
+        results
+    });
+    let (tbox, abox) = &results[0];
+    assert!(!tbox.contains(&class_label) && abox.contains(&class_label));
+    let (tbox, abox) = &results[1];
+    assert!(tbox.contains(&declaration) && tbox.contains(&class_label));
+    assert!(!abox.contains(&class_label));
+    assert!(abox.contains(&assertion) && abox.contains(&individual_label));
+    let (tbox, abox) = &results[2];
+    assert!(!tbox.contains(&class_label) && abox.contains(&class_label));
+}
+
+/// Materializes every step of `steps` once through the mixed input and once
+/// through pre-split inputs, and checks both agree.
+fn check(profile: Profile, graphs: GraphMode, steps: Vec<Vec<(EncodedTriple, isize)>>) {
+    let results = timely::execute(timely::Config::process(2), move |worker| {
+        let mut mixed = Reasoner::with_graphs(worker, profile, graphs);
+        let mut split = Reasoner::with_graphs(worker, profile, graphs);
+        let mut results = Vec::new();
+        for updates in steps.iter() {
+            if worker.index() == 0 {
+                for &(triple, diff) in updates.iter() {
+                    mixed.update(triple, diff);
+                    if is_tbox(&triple) {
+                        split.update_tbox(triple, diff);
+                    } else {
+                        split.update_abox(triple, diff);
+                    }
+                }
+            }
+            mixed.advance(worker);
+            split.advance(worker);
+            results.push(((mixed.tbox(), mixed.abox()), (split.tbox(), split.abox())));
+        }
+        results
+    })
+    .unwrap()
+    .join();
+
+    let mut merged: Vec<((Triples, Triples), (Triples, Triples))> = Vec::new();
+    for share in results {
+        for (step, (mixed, split)) in share.unwrap().into_iter().enumerate() {
+            if merged.len() <= step {
+                merged.push(Default::default());
+            }
+            let entry = &mut merged[step];
+            (entry.0).0.extend(mixed.0);
+            (entry.0).1.extend(mixed.1);
+            (entry.1).0.extend(split.0);
+            (entry.1).1.extend(split.1);
+        }
+    }
+    for (step, (mixed, split)) in merged.into_iter().enumerate() {
+        assert_eq!(mixed, split, "{} differs at step {}", profile, step);
+    }
+}
+
+#[test]
+fn mixed_input_matches_split_input() {
+    for seed in 0..10 {
+        let mut rng = Rng::new(seed);
+        let mut present = Vec::new();
+        let mut steps = Vec::new();
+        for step in 0..4 {
+            let mut updates = Vec::new();
+            for _ in 0..if step == 0 { 16 } else { 4 } {
+                let triple = rng.triple();
+                if !present.contains(&triple) {
+                    present.push(triple);
+                    updates.push((triple, 1));
+                }
+            }
+            if step > 0 && !present.is_empty() {
+                let triple = present.swap_remove(rng.below(present.len()));
+                updates.push((triple, -1));
+            }
+            steps.push(updates);
+        }
+        check(Profile::Rdfspp, GraphMode::Union, steps.clone());
+        check(Profile::Rdfs, GraphMode::PerGraph, steps);
+    }
+}
+
+#[test]
+fn schema_in_a_mixed_file_is_used_as_schema() {
+    let (a, b, x) = (10, 11, 30);
+    let steps = vec![
+        vec![((x, 4, a), 1)],
+        vec![((a, 0, b), 1)],
+        vec![((a, 0, b), -1)],
+    ];
+    let results = timely::execute_directly(move |worker| {
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfs);
+        let mut results = Vec::new();
+        for updates in steps.iter() {
+            for &(triple, diff) in updates.iter() {
+                reasoner.update(triple, diff);
+            }
+            reasoner.advance(worker);
+            results.push(reasoner.abox().into_iter().collect::<Triples>());
+        }
+        results
+    });
+    assert!(!results[0].contains(&(x, 4, b)));
+    assert!(results[1].contains(&(x, 4, b)));
+    assert!(!results[1].contains(&(a, 0, b)));
+    assert!(!results[2].contains(&(x, 4, b)));
+}
+
+#[test]
+fn shipped_encoded_data_is_routed_by_its_mapping() {
+    let mut dictionary = Dictionary::load("./encoded_data/test/encoding_mapping.kv").unwrap();
+    let schema = Schema::resolve(&mut dictionary);
+    let read = |path: &str| -> Vec<EncodedTriple> {
+        load3enc(path).unwrap().map(Result::unwrap).collect()
+    };
+    let tbox = read("./encoded_data/test/tbox.ntenc");
+    let abox = read("./encoded_data/test/abox.ntenc");
+
+    let (mixed, split) = timely::execute_directly(move |worker| {
+        let mut mixed = Reasoner::with_schema(worker, Profile::Rdfspp, GraphMode::Union, schema);
+        let mut split = Reasoner::new(worker, Profile::Rdfspp);
+        for triple in tbox.iter() {
+            mixed.insert(*triple);
+            split.insert_tbox(*triple);
+        }
+        for triple in abox.iter() {
+            mixed.insert(*triple);
+            split.insert_abox(*triple);
+        }
+        mixed.advance(worker);
+        split.advance(worker);
+        let sorted = |mut triples: Vec<EncodedTriple>| {
+            triples.sort_unstable();
+            triples
+        };
+        (
+            (sorted(mixed.tbox()), sorted(mixed.abox())),
+            (sorted(split.tbox()), sorted(split.abox())),
+        )
+    });
+    assert_eq!(mixed, split);
+}
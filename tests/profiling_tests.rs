@@ -0,0 +1,100 @@
+use differential_reasoner::profiling::{merge, Profiler, RegionProfile, ReportFormat};
+use differential_reasoner::reasoner::{Profile, Reasoner};
+use std::time::Duration;
+
+/// Profiles of materializing a transitive chain with a domain and a class
+/// hierarchy, gathered from all workers.
+fn profile(profile: Profile, workers: usize) -> Vec<RegionProfile> {
+    let shares = timely::execute(timely::Config::process(workers), move |worker| {
+        let profiler = Profiler::install(worker);
+        let mut reasoner = Reasoner::new(worker, profile);
+        if worker.index() == 0 {
+            reasoner.insert_tbox((20, 4, 5));
+            reasoner.insert_tbox((20, 2, 10));
+            reasoner.insert_tbox((10, 0, 11));
+            for individual in 30..40 {
+                reasoner.insert_abox((individual, 20, individual + 1));
+            }
+        }
+        reasoner.advance(worker);
+        profiler.report(worker)
+    })
+    .unwrap()
+    .join();
+
+    merge(shares.into_iter().map(|share| share.unwrap()).collect())
+}
+
+fn region<'a>(profiles: &'a [RegionProfile], name: &str) -> &'a RegionProfile {
+    profiles
+        .iter()
+        .find(|profile| profile.region == name)
+        .unwrap_or_else(|| panic!("no profile for {:?} in {:?}", name, profiles))
+}
+
+#[test]
+fn reports_every_region() {
+    for workers in [1, 2].iter() {
+        let profiles = profile(Profile::Rdfspp, *workers);
+        for name in [
+            "Tbox transitive rules",
+            "CAX-SCO",
+            "Domain and Range type rules",
+            "Abox transitive property rules",
+            "Concatenating all rules",
+        ]
+        .iter()
+        {
+            assert!(region(&profiles, name).records > 0, "{}", name);
+        }
+        let transitive = region(&profiles, "Abox transitive property rules");
+        assert!(transitive.iterations > 0);
+        assert!(transitive.arranged > 0);
+        // Sorted by region name.
+        assert!(profiles
+            .windows(2)
+            .all(|pair| pair[0].region < pair[1].region));
+    }
+}
+
+#[test]
+fn merges_and_renders_profiles() {
+    let profile = |region: &str, millis, records| RegionProfile {
+        region: region.to_string(),
+        elapsed: Duration::from_millis(millis),
+        records,
+        arranged: 4,
+        iterations: 1,
+    };
+    let merged = merge(vec![
+        vec![profile("CAX-SCO", 2, 10)],
+        vec![
+            profile("CAX-SCO", 3, 5),
+            profile("A \"quoted\" region", 1, 1),
+        ],
+    ]);
+    assert_eq!(
+        merged,
+        vec![
+            profile("A \"quoted\" region", 1, 1),
+            RegionProfile {
+                arranged: 8,
+                iterations: 2,
+                ..profile("CAX-SCO", 5, 15)
+            },
+        ]
+    );
+
+    assert_eq!(
+        ReportFormat::Json.render(&merged[1..]),
+        "[{\"region\": \"CAX-SCO\", \"elapsed_secs\": 0.005000, \"records\": 15, \"arranged\": 8, \"iterations\": 2}]\n"
+    );
+    assert!(ReportFormat::Json
+        .render(&merged)
+        .contains("\"A \\\"quoted\\\" region\""));
+    let table = ReportFormat::Table.render(&merged);
+    assert_eq!(table.lines().count(), 3);
+    assert!(table.lines().nth(2).unwrap().starts_with("CAX-SCO"));
+    assert_eq!("json".parse::<ReportFormat>().unwrap(), ReportFormat::Json);
+    assert!("csv".parse::<ReportFormat>().is_err());
+}
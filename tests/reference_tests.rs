@@ -0,0 +1,132 @@
+mod common;
+
+use common::{is_tbox, reference, Rng, Triples};
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::reasoner::{Profile, Reasoner};
+
+const SEEDS: u64 = 25;
+const STEPS: usize = 6;
+
+/// Each step lists the triples to insert (`1`) or delete (`-1`).
+type Script = Vec<Vec<(EncodedTriple, isize)>>;
+
+/// A random initial load followed by random insertions and deletions. Only
+/// absent triples are inserted and only present ones deleted, so every input
+/// triple has multiplicity one.
+fn script(seed: u64) -> Script {
+    let mut rng = Rng::new(seed);
+    let mut input = Triples::new();
+    let mut script = Vec::new();
+    for step in 0..STEPS {
+        let mut updates = Vec::new();
+        let inserts = if step == 0 { 16 } else { rng.below(5) };
+        for _ in 0..inserts {
+            let triple = rng.triple();
+            if input.insert(triple) {
+                updates.push((triple, 1));
+            }
+        }
+        if step > 0 {
+            let mut present: Vec<EncodedTriple> = input.iter().cloned().collect();
+            present.sort_unstable();
+            for _ in 0..rng.below(4) {
+                if present.is_empty() {
+                    break;
+                }
+                let triple = present.swap_remove(rng.below(present.len()));
+                input.remove(&triple);
+                updates.push((triple, -1));
+            }
+        }
+        script.push(updates);
+    }
+    script
+}
+
+/// The materialization after every step of `script`, gathered from all workers.
+fn materialize(profile: Profile, workers: usize, script: &Script) -> Vec<(Triples, Triples)> {
+    let script = script.clone();
+    let shares = timely::execute(timely::Config::process(workers), move |worker| {
+        let mut reasoner = Reasoner::new(worker, profile);
+        let mut results = Vec::new();
+        for updates in script.iter() {
+            if worker.index() == 0 {
+                for &(triple, diff) in updates.iter() {
+                    if is_tbox(&triple) {
+                        reasoner.update_tbox(triple, diff);
+                    } else {
+                        reasoner.update_abox(triple, diff);
+                    }
+                }
+            }
+            reasoner.advance(worker);
+            results.push((reasoner.tbox(), reasoner.abox()));
+        }
+        results
+    })
+    .unwrap()
+    .join();
+
+    let mut steps = vec![(Triples::new(), Triples::new()); script.len()];
+    for share in shares {
+        for (step, (tbox, abox)) in steps.iter_mut().zip(share.unwrap()) {
+            step.0.extend(tbox);
+            step.1.extend(abox);
+        }
+    }
+    steps
+}
+
+fn check(profile: Profile, workers: usize) {
+    for seed in 0..SEEDS {
+        let script = script(seed);
+        let mut tbox = Triples::new();
+        let mut abox = Triples::new();
+        let steps = materialize(profile, workers, &script);
+        for (step, (updates, (actual_tbox, actual_abox))) in script.iter().zip(steps).enumerate() {
+            for &(triple, diff) in updates.iter() {
+                let input = if is_tbox(&triple) {
+                    &mut tbox
+                } else {
+                    &mut abox
+                };
+                if diff > 0 {
+                    input.insert(triple);
+                } else {
+                    input.remove(&triple);
+                }
+            }
+            let (expected_tbox, expected_abox) = reference(profile, &tbox, &abox);
+            assert_eq!(
+                actual_tbox, expected_tbox,
+                "{} tbox differs for seed {} at step {} with {} workers",
+                profile, seed, step, workers
+            );
+            assert_eq!(
+                actual_abox, expected_abox,
+                "{} abox differs for seed {} at step {} with {} workers",
+                profile, seed, step, workers
+            );
+        }
+    }
+}
+
+#[test]
+fn rdfs_matches_reference() {
+    check(Profile::Rdfs, 1);
+}
+
+#[test]
+fn rdfs_matches_reference_with_several_workers() {
+    check(Profile::Rdfs, 3);
+}
+
+#[test]
+fn rdfspp_matches_reference() {
+    check(Profile::Rdfspp, 1);
+}
+
+#[test]
+fn rdfspp_matches_reference_with_several_workers() {
+    check(Profile::Rdfspp, 3);
+}
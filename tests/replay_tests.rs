@@ -0,0 +1,164 @@
+use differential_reasoner::dictionary::Dictionary;
+use differential_reasoner::load_encode_triples::{load3enc_stream, load3nt_stream};
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::reasoner::{Profile, Reasoner};
+use differential_reasoner::replay::{batches, replay, Batch, LatencyHistogram, Pace};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const SUB_CLASS_OF: usize = 0;
+const TYPE: usize = 4;
+const PERSON: usize = 10;
+const PROFESSOR: usize = 11;
+
+fn temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "differential-reasoner-{}-{}",
+        std::process::id(),
+        name
+    ));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+fn stream() -> Vec<Batch> {
+    let path = temp_file(
+        "stream.ntenc",
+        "0 +11 0 10\n\
+         10 +20 4 11\n\
+         10 +21 4 11\n\
+         30 -20 4 11\n\
+         30 +22 4 11\n",
+    );
+    let path = path.display().to_string();
+    batches(&path, load3enc_stream(&path).unwrap()).unwrap()
+}
+
+/// Replays `stream` with two workers and returns the merged histogram and the
+/// materialized ABox.
+fn replay_with(stream: Vec<Batch>, pace: Pace) -> (LatencyHistogram, Vec<EncodedTriple>) {
+    let stream = Arc::new(stream);
+    let start = Instant::now();
+    let shares = timely::execute(timely::Config::process(2), move |worker| {
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfs);
+        let histogram = replay(worker, &mut reasoner, &stream, pace, start);
+        (histogram, reasoner.abox())
+    })
+    .unwrap()
+    .join();
+
+    let (mut histogram, mut abox) = (LatencyHistogram::default(), Vec::new());
+    for share in shares {
+        let (share_histogram, share_abox) = share.unwrap();
+        histogram.merge(&share_histogram);
+        abox.extend(share_abox);
+    }
+    abox.sort_unstable();
+    (histogram, abox)
+}
+
+#[test]
+fn groups_updates_by_timestamp() {
+    assert_eq!(
+        stream(),
+        vec![
+            (0, vec![((PROFESSOR, SUB_CLASS_OF, PERSON), 1)]),
+            (
+                10,
+                vec![((20, TYPE, PROFESSOR), 1), ((21, TYPE, PROFESSOR), 1)]
+            ),
+            (
+                30,
+                vec![((20, TYPE, PROFESSOR), -1), ((22, TYPE, PROFESSOR), 1)]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn skips_comments_in_encoded_streams() {
+    let path = temp_file(
+        "comments.ntenc",
+        "# professors\n\
+         \n\
+         0 11 0 10\n\
+         10 - 20 4 11\n",
+    );
+    let updates: Vec<_> = load3enc_stream(&path.display().to_string())
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        updates,
+        vec![
+            (3, 0, (PROFESSOR, SUB_CLASS_OF, PERSON), 1),
+            (4, 10, (20, TYPE, PROFESSOR), -1)
+        ]
+    );
+}
+
+#[test]
+fn rejects_decreasing_timestamps() {
+    let path = temp_file(
+        "stream.nt",
+        "# alice, then bob\n\
+         5 + <http://ex/alice> <http://ex/knows> <http://ex/bob> .\n\
+         3 + <http://ex/bob> <http://ex/knows> <http://ex/alice> .\n",
+    );
+    let path = path.display().to_string();
+    let mut dictionary = Dictionary::new();
+    let updates = load3nt_stream(&path).unwrap().map(|update| {
+        let (line, time, triple, diff) = update?;
+        Ok((line, time, dictionary.encode_triple(&triple), diff))
+    });
+    let error = batches(&path, updates).unwrap_err().to_string();
+    assert!(
+        error.ends_with(":3: timestamp 3 is before the previous one, 5"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn records_the_latency_of_every_timestamp() {
+    let (histogram, abox) = replay_with(stream(), Pace::AsFastAsPossible);
+    assert_eq!(histogram.count(), 3);
+    assert!(histogram.quantile(1.0) >= histogram.max());
+    assert_eq!(
+        abox,
+        vec![
+            (21, TYPE, PERSON),
+            (21, TYPE, PROFESSOR),
+            (22, TYPE, PERSON),
+            (22, TYPE, PROFESSOR)
+        ]
+    );
+}
+
+#[test]
+fn paces_timestamps_at_a_scaled_rate() {
+    // 30 milliseconds of stream, at half speed.
+    let start = Instant::now();
+    let (histogram, _abox) = replay_with(stream(), Pace::Scaled(0.5));
+    assert!(start.elapsed() >= Duration::from_millis(60));
+    assert_eq!(histogram.count(), 3);
+}
+
+#[test]
+fn buckets_latencies_by_powers_of_two() {
+    let mut histogram = LatencyHistogram::default();
+    for micros in [1, 3, 4, 100, 1000].iter() {
+        histogram.record(Duration::from_micros(*micros));
+    }
+    assert_eq!(histogram.count(), 5);
+    assert_eq!(histogram.max(), Duration::from_micros(1000));
+    assert_eq!(histogram.mean(), Duration::from_nanos(221_600));
+    assert_eq!(histogram.quantile(0.2), Duration::from_micros(1));
+    assert_eq!(histogram.quantile(0.6), Duration::from_micros(4));
+    assert_eq!(histogram.quantile(0.99), Duration::from_micros(1024));
+    let rendered = histogram.render();
+    assert!(rendered.starts_with("timestamps 5,"), "{}", rendered);
+    // The buckets from 1µs to 1024µs.
+    assert_eq!(rendered.lines().count(), 1 + 11);
+}
@@ -0,0 +1,129 @@
+use differential_reasoner::partition::Schema;
+use differential_reasoner::reasoner::Profile;
+use differential_reasoner::runtime::{ReasonerHandle, Runtime, RuntimeConfig};
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+use std::time::{Duration, SystemTime};
+
+const SUB_CLASS_OF: usize = 0;
+const TYPE: usize = 4;
+const PERSON: usize = 10;
+const PROFESSOR: usize = 11;
+
+fn start(workers: usize) -> Runtime {
+    Runtime::start(RuntimeConfig {
+        profile: Profile::Rdfs,
+        workers,
+        history: 1,
+        schema: Schema::default(),
+    })
+    .unwrap()
+}
+
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Polls `future` on the current thread until it is ready.
+fn block_on<F: Future + Unpin>(mut future: F) -> F::Output {
+    let waker = Arc::new(Unpark(thread::current())).into();
+    let mut context = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = std::pin::Pin::new(&mut future).poll(&mut context) {
+            return output;
+        }
+        thread::park();
+    }
+}
+
+#[test]
+fn materializes_batches_from_several_threads() {
+    for workers in 1..=2 {
+        let runtime = start(workers);
+        let handle = runtime.handle();
+        handle
+            .wait(handle.insert_tbox(vec![(PROFESSOR, SUB_CLASS_OF, PERSON)]))
+            .unwrap();
+
+        let threads: Vec<_> = (0..4)
+            .map(|thread| {
+                let handle: ReasonerHandle = handle.clone();
+                thread::spawn(move || {
+                    let individuals = (0..5).map(|i| (20 + 5 * thread + i, TYPE, PROFESSOR));
+                    let time = handle.insert_abox(individuals);
+                    handle.wait(time).unwrap();
+                    time
+                })
+            })
+            .collect();
+        let latest = threads
+            .into_iter()
+            .map(|t| t.join().unwrap())
+            .max()
+            .unwrap();
+        assert!(handle.completed() > latest);
+
+        let people = handle
+            .query(false, (None, Some(TYPE), Some(PERSON)), latest)
+            .unwrap();
+        assert_eq!(
+            people,
+            (20..40).map(|s| (s, TYPE, PERSON)).collect::<Vec<_>>()
+        );
+
+        let deleted = handle.delete_abox(vec![(20, TYPE, PROFESSOR)]);
+        block_on(handle.materialized(deleted)).unwrap();
+        assert_eq!(
+            handle
+                .diff(false, (Some(20), None, None), latest, deleted)
+                .unwrap(),
+            vec![((20, TYPE, PERSON), -1), ((20, TYPE, PROFESSOR), -1)]
+        );
+
+        runtime.shutdown().unwrap();
+    }
+}
+
+#[test]
+fn waits_fail_once_the_runtime_stops() {
+    let runtime = start(1);
+    let handle = runtime.handle();
+    assert!(handle.wait(0).is_err(), "nothing is assigned yet");
+
+    let time = handle.insert_abox(vec![(20, TYPE, PERSON)]);
+    handle.wait(time).unwrap();
+    runtime.shutdown().unwrap();
+
+    let later = handle.insert_abox(vec![(21, TYPE, PERSON)]);
+    assert!(handle.wait(later).is_err());
+    assert!(block_on(handle.materialized(later)).is_err());
+    assert!(handle.query(false, (None, None, None), later).is_err());
+}
+
+#[test]
+fn maps_instants_to_timestamps() {
+    let runtime = start(1);
+    let handle = runtime.handle();
+    let before = SystemTime::now() - Duration::from_secs(1);
+    let mut assigned = Vec::new();
+    for individual in 20..24 {
+        handle.insert_abox(vec![(individual, TYPE, PERSON)]);
+        assigned.push(SystemTime::now());
+    }
+
+    assert_eq!(handle.timestamp_at(assigned[3]), Some(3));
+    assert_eq!(
+        handle.timestamp_at(SystemTime::now() + Duration::from_secs(60)),
+        Some(3)
+    );
+    assert!(handle.timestamp_at(assigned[2]) >= Some(2));
+    // Only timestamps 2 and 3 are still readable.
+    assert_eq!(handle.timestamp_at(before), None);
+    runtime.shutdown().unwrap();
+}
@@ -0,0 +1,252 @@
+use differential_reasoner::reasoner::Profile;
+use differential_reasoner::server::{Server, ServerConfig};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+
+const SUB_CLASS_OF: &str = "<http://www.w3.org/2000/01/rdf-schema#subClassOf>";
+const TYPE: &str = "<http://www.w3.org/1999/02/22-rdf-syntax-ns#type>";
+
+fn request(address: SocketAddr, method: &str, target: &str, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        target,
+        address,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+    (status, body)
+}
+
+fn time(body: &str) -> usize {
+    body.trim_start_matches("{\"time\":")
+        .trim_end_matches('}')
+        .parse()
+        .unwrap()
+}
+
+fn sorted_lines(body: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = body.lines().collect();
+    lines.sort_unstable();
+    lines
+}
+
+fn start(workers: usize) -> Server {
+    start_with_history(workers, 0)
+}
+
+fn start_with_history(workers: usize, history: usize) -> Server {
+    Server::start(ServerConfig {
+        address: "127.0.0.1:0".to_string(),
+        profile: Profile::Rdfs,
+        workers,
+        tbox_path: None,
+        abox_path: None,
+        history,
+    })
+    .unwrap()
+}
+
+#[test]
+fn reads_wait_for_their_updates() {
+    for workers in 1..=2 {
+        let server = start(workers);
+        let address = server.local_addr();
+
+        let tbox = format!("<http://ex/A> {} <http://ex/B> .\n", SUB_CLASS_OF);
+        let (status, _) = request(address, "POST", "/tbox/insert", &tbox);
+        assert_eq!(status, 200);
+        let abox = format!("<http://ex/x> {} <http://ex/A> .\n", TYPE);
+        let (status, body) = request(address, "POST", "/abox/insert", &abox);
+        assert_eq!(status, 200);
+        let inserted = time(&body);
+
+        let (status, body) = request(
+            address,
+            "GET",
+            &format!("/abox?s=%3Chttp%3A%2F%2Fex%2Fx%3E&after={}", inserted),
+            "",
+        );
+        assert_eq!(status, 200);
+        assert_eq!(
+            sorted_lines(&body),
+            vec![
+                format!("<http://ex/x> {} <http://ex/A> .", TYPE),
+                format!("<http://ex/x> {} <http://ex/B> .", TYPE),
+            ]
+        );
+
+        let (_, body) = request(address, "POST", "/abox/delete", &abox);
+        let (_, body) = request(address, "GET", &format!("/abox?after={}", time(&body)), "");
+        assert_eq!(body, "");
+
+        server.shutdown().unwrap();
+    }
+}
+
+#[test]
+fn applies_sparql_updates() {
+    let server = start(1);
+    let address = server.local_addr();
+
+    let update = "PREFIX ex: <http://ex/>
+        PREFIX rdfs: <http://www.w3.org/2000/01/rdf-schema#>
+        INSERT DATA { ex:A rdfs:subClassOf ex:B . }";
+    let (status, _) = request(address, "POST", "/update?box=tbox", update);
+    assert_eq!(status, 200);
+    let update = "PREFIX ex: <http://ex/>
+        INSERT DATA { ex:x a ex:A ; ex:knows ex:y, ex:z . }";
+    let (status, _) = request(address, "POST", "/update", update);
+    assert_eq!(status, 200);
+
+    let (_, body) = request(address, "GET", "/abox?p=%3Chttp%3A%2F%2Fex%2Fknows%3E", "");
+    assert_eq!(sorted_lines(&body).len(), 2);
+    let (_, body) = request(address, "GET", "/abox?o=%3Chttp%3A%2F%2Fex%2FB%3E", "");
+    assert_eq!(
+        sorted_lines(&body),
+        vec![format!("<http://ex/x> {} <http://ex/B> .", TYPE)]
+    );
+
+    let (status, _) = request(address, "POST", "/update", "DELETE WHERE { ?s ?p ?o }");
+    assert_eq!(status, 400);
+    let (status, _) = request(address, "GET", "/abox?after=1000", "");
+    assert_eq!(status, 400);
+
+    server.shutdown().unwrap();
+}
+
+#[test]
+fn updates_have_set_semantics() {
+    let server = start(1);
+    let address = server.local_addr();
+
+    let knows = "%3Chttp%3A%2F%2Fex%2Fknows%3E";
+    let insert = "INSERT DATA { <http://ex/x> <http://ex/knows> <http://ex/y> . }";
+    let delete = "DELETE DATA { <http://ex/x> <http://ex/knows> <http://ex/y> . }";
+    for update in [insert, insert, delete].iter() {
+        let (status, _) = request(address, "POST", "/update", update);
+        assert_eq!(status, 200);
+    }
+    let (_, body) = request(address, "GET", &format!("/abox?p={}", knows), "");
+    assert_eq!(body, "");
+
+    for update in [delete, insert].iter() {
+        request(address, "POST", "/update", update);
+    }
+    let (_, body) = request(address, "GET", &format!("/abox?p={}", knows), "");
+    assert_eq!(sorted_lines(&body).len(), 1);
+
+    server.shutdown().unwrap();
+}
+
+#[test]
+fn rejects_malformed_requests() {
+    let server = start(1);
+    let address = server.local_addr();
+
+    let (status, _) = request(address, "GET", "/abox?s=%3", "");
+    assert_eq!(status, 400);
+    let (status, _) = request(address, "GET", "/abox?s=%3Cx", "");
+    assert_eq!(status, 400);
+
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "POST /abox/insert HTTP/1.1\r\nContent-Length: 1000000000000\r\n\r\n"
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 413"));
+
+    server.shutdown().unwrap();
+}
+
+#[test]
+fn reads_after_compacted_timestamps_as_of_later_ones() {
+    let server = start(1);
+    let address = server.local_addr();
+
+    let abox = format!("<http://ex/x> {} <http://ex/A> .\n", TYPE);
+    let (_, body) = request(address, "POST", "/abox/insert", &abox);
+    let inserted = time(&body);
+    // Another client's write compacts `inserted` away.
+    let (_, body) = request(address, "POST", "/abox/delete", &abox);
+    let deleted = time(&body);
+
+    for after in [inserted, deleted].iter() {
+        let (status, body) = request(address, "GET", &format!("/abox?after={}", after), "");
+        assert_eq!(status, 200);
+        assert_eq!(body, "");
+    }
+
+    server.shutdown().unwrap();
+}
+
+#[test]
+fn reads_past_timestamps_and_their_changes() {
+    let server = start_with_history(2, 4);
+    let address = server.local_addr();
+
+    let tbox = format!("<http://ex/A> {} <http://ex/B> .\n", SUB_CLASS_OF);
+    let (_, body) = request(address, "POST", "/tbox/insert", &tbox);
+    let schema = time(&body);
+    let x = format!("<http://ex/x> {} <http://ex/A> .\n", TYPE);
+    let (_, body) = request(address, "POST", "/abox/insert", &x);
+    let inserted = time(&body);
+    let (_, body) = request(address, "POST", "/tbox/delete", &tbox);
+    let retracted = time(&body);
+
+    let (status, body) = request(address, "GET", &format!("/abox?after={}", inserted), "");
+    assert_eq!(status, 200);
+    assert_eq!(sorted_lines(&body).len(), 2);
+    let (_, body) = request(address, "GET", &format!("/abox?after={}", retracted), "");
+    assert_eq!(
+        sorted_lines(&body),
+        vec![format!("<http://ex/x> {} <http://ex/A> .", TYPE)]
+    );
+
+    let (status, body) = request(
+        address,
+        "GET",
+        &format!("/abox/diff?since={}&after={}", schema, retracted),
+        "",
+    );
+    assert_eq!(status, 200);
+    assert_eq!(
+        sorted_lines(&body),
+        vec![format!("+ <http://ex/x> {} <http://ex/A> .", TYPE)]
+    );
+    let (_, body) = request(
+        address,
+        "GET",
+        &format!("/abox/diff?since={}&after={}", inserted, retracted),
+        "",
+    );
+    assert_eq!(
+        sorted_lines(&body),
+        vec![format!("- <http://ex/x> {} <http://ex/B> .", TYPE)]
+    );
+    let (status, _) = request(address, "GET", "/abox/diff", "");
+    assert_eq!(status, 400);
+
+    // Every update so far was assigned by now, and none by the epoch.
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 1;
+    let (_, body) = request(address, "GET", &format!("/abox?at={}", now), "");
+    assert_eq!(sorted_lines(&body).len(), 1);
+    let (status, _) = request(address, "GET", "/abox?at=0", "");
+    assert_eq!(status, 400);
+
+    server.shutdown().unwrap();
+}
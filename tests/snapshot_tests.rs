@@ -0,0 +1,129 @@
+use differential_reasoner::error::Error;
+use differential_reasoner::load_encode_triples::load3enc;
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::profiling::{Profiler, OUTSIDE_REGIONS};
+use differential_reasoner::reasoner::{Profile, Reasoner};
+use differential_reasoner::snapshot::Manifest;
+use timely::communication::allocator::Thread;
+use timely::worker::Worker;
+
+fn sorted(mut triples: Vec<EncodedTriple>) -> Vec<EncodedTriple> {
+    triples.sort_unstable();
+    triples
+}
+
+/// Inserts the test data into `reasoner`, along with `extra` ABox triples.
+fn load(reasoner: &mut Reasoner, extra: &[EncodedTriple]) {
+    for triple in load3enc("./encoded_data/test/tbox.ntenc").unwrap() {
+        reasoner.insert_tbox(triple.unwrap());
+    }
+    for triple in load3enc("./encoded_data/test/abox.ntenc").unwrap() {
+        reasoner.insert_abox(triple.unwrap());
+    }
+    for triple in extra {
+        reasoner.insert_abox(*triple);
+    }
+}
+
+/// Materializes the test data and snapshots it to a directory of its own,
+/// and returns the directory, its manifest and the materialization.
+fn snapshot(name: &str) -> (String, Manifest, Vec<EncodedTriple>, Vec<EncodedTriple>) {
+    let dir = std::env::temp_dir()
+        .join(format!(
+            "differential-reasoner-{}-{}",
+            std::process::id(),
+            name
+        ))
+        .display()
+        .to_string();
+
+    let snapshot_dir = dir.clone();
+    let (tbox, abox) = timely::execute_directly(move |worker| {
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfspp);
+        load(&mut reasoner, &[]);
+        reasoner.advance(worker);
+        reasoner.snapshot(&snapshot_dir, worker.index()).unwrap();
+        (sorted(reasoner.tbox()), sorted(reasoner.abox()))
+    });
+    Manifest::new(Profile::Rdfspp, 1, 1, tbox.len(), abox.len())
+        .write(&dir)
+        .unwrap();
+    let manifest = Manifest::read(&dir).unwrap();
+    (dir, manifest, tbox, abox)
+}
+
+#[test]
+fn restored_snapshot_matches_materialization() {
+    let (dir, manifest, tbox, abox) = snapshot("snapshot");
+    assert_eq!((manifest.tbox_size, manifest.abox_size), (29, 28));
+    assert!(matches!(
+        manifest.check(Profile::Rdfs),
+        Err(Error::Snapshot(_))
+    ));
+
+    let (restored_tbox, restored_abox) = timely::execute_directly(move |worker| {
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfspp);
+        reasoner.restore(&dir, &manifest).unwrap();
+        reasoner.advance_to(worker, manifest.time);
+        (sorted(reasoner.tbox()), sorted(reasoner.abox()))
+    });
+    assert_eq!(restored_tbox, tbox);
+    assert_eq!(restored_abox, abox);
+}
+
+/// The records the rules sent so far, leaving out the operators outside
+/// their regions, such as the inputs and the traces.
+fn rule_records(profiler: &Profiler, worker: &mut Worker<Thread>) -> usize {
+    profiler
+        .report(worker)
+        .iter()
+        .filter(|profile| profile.region != OUTSIDE_REGIONS)
+        .map(|profile| profile.records)
+        .sum()
+}
+
+#[test]
+fn first_update_after_restore_costs_as_much_as_in_a_live_run() {
+    let (dir, manifest, _tbox, _abox) = snapshot("resume");
+    // A new member of a class with superclasses.
+    let update = (99, 4, 13);
+
+    let (restored_work, restored) = timely::execute_directly(move |worker| {
+        let profiler = Profiler::install(worker);
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfspp);
+        reasoner.restore(&dir, &manifest).unwrap();
+        reasoner.advance_to(worker, manifest.time);
+        let before = rule_records(&profiler, worker);
+        reasoner.insert_abox(update);
+        reasoner.advance(worker);
+        (
+            rule_records(&profiler, worker) - before,
+            (sorted(reasoner.tbox()), sorted(reasoner.abox())),
+        )
+    });
+
+    let (materialization_work, live_work, live) = timely::execute_directly(move |worker| {
+        let profiler = Profiler::install(worker);
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfspp);
+        load(&mut reasoner, &[]);
+        reasoner.advance(worker);
+        let before = rule_records(&profiler, worker);
+        reasoner.insert_abox(update);
+        reasoner.advance(worker);
+        (
+            before,
+            rule_records(&profiler, worker) - before,
+            (sorted(reasoner.tbox()), sorted(reasoner.abox())),
+        )
+    });
+    assert_eq!(restored, live);
+    assert!(live_work > 0);
+    // Deriving the closure anew would cost about the whole materialization.
+    assert!(2 * live_work < materialization_work);
+    assert!(
+        restored_work <= live_work,
+        "{} records after the restore, {} in a live run",
+        restored_work,
+        live_work
+    );
+}
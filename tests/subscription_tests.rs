@@ -0,0 +1,153 @@
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::reasoner::{Profile, Reasoner};
+use differential_reasoner::subscriptions::{Notification, Subscriptions};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const SUB_CLASS_OF: usize = 0;
+const TYPE: usize = 4;
+const PERSON: usize = 10;
+const PROFESSOR: usize = 11;
+
+#[test]
+fn notifies_matching_changes_as_timestamps_close() {
+    let notifications = timely::execute(timely::Config::process(2), |worker| {
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfs);
+        let mut subscriptions = Subscriptions::install(worker, &mut reasoner);
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let (people, channel) = if worker.index() == 0 {
+            let sink = Rc::clone(&received);
+            let people = subscriptions.subscribe(
+                false,
+                (None, Some(TYPE), Some(PERSON)),
+                move |notification| sink.borrow_mut().push(notification.clone()),
+            );
+            let (_id, channel) =
+                subscriptions.subscribe_channel(true, (None, Some(SUB_CLASS_OF), None));
+            (Some(people), Some(channel))
+        } else {
+            (None, None)
+        };
+
+        let steps: Vec<Vec<(EncodedTriple, isize, bool)>> = vec![
+            vec![((PROFESSOR, SUB_CLASS_OF, PERSON), 1, true)],
+            vec![
+                ((20, TYPE, PROFESSOR), 1, false),
+                ((21, TYPE, PERSON), 1, false),
+            ],
+            // Nothing matching changes: no notification.
+            vec![((20, 30, 21), 1, false)],
+            vec![((PROFESSOR, SUB_CLASS_OF, PERSON), -1, true)],
+            // No longer subscribed.
+            vec![((23, TYPE, PERSON), 1, false)],
+        ];
+        for (time, updates) in steps.iter().enumerate() {
+            if worker.index() == 0 {
+                for &(triple, diff, tbox) in updates.iter() {
+                    if tbox {
+                        reasoner.update_tbox(triple, diff);
+                    } else {
+                        reasoner.update_abox(triple, diff);
+                    }
+                }
+            }
+            reasoner.advance(worker);
+            subscriptions.wait(worker, reasoner.time());
+            if time == 3 {
+                if let Some(people) = people {
+                    subscriptions.unsubscribe(people);
+                }
+            }
+        }
+
+        let schema: Vec<Notification> =
+            channel.map_or(Vec::new(), |channel| channel.try_iter().collect());
+        let people = received.borrow().clone();
+        (people, schema)
+    })
+    .unwrap()
+    .join();
+
+    let mut shares = notifications.into_iter().map(|share| share.unwrap());
+    let (people, schema) = shares.next().unwrap();
+    assert_eq!(
+        people,
+        vec![
+            Notification {
+                time: 1,
+                added: vec![(20, TYPE, PERSON), (21, TYPE, PERSON)],
+                removed: vec![],
+            },
+            Notification {
+                time: 3,
+                added: vec![],
+                removed: vec![(20, TYPE, PERSON)],
+            },
+        ]
+    );
+    assert_eq!(
+        schema,
+        vec![
+            Notification {
+                time: 0,
+                added: vec![(PROFESSOR, SUB_CLASS_OF, PERSON)],
+                removed: vec![],
+            },
+            Notification {
+                time: 3,
+                added: vec![],
+                removed: vec![(PROFESSOR, SUB_CLASS_OF, PERSON)],
+            },
+        ]
+    );
+    // Only worker 0 subscribed.
+    assert_eq!(shares.next().unwrap(), (vec![], vec![]));
+}
+
+#[test]
+fn notifies_only_when_a_triple_enters_or_leaves_the_materialization() {
+    let notifications = timely::execute_directly(|worker| {
+        let mut reasoner = Reasoner::new(worker, Profile::Rdfs);
+        let mut subscriptions = Subscriptions::install(worker, &mut reasoner);
+        let (_id, channel) = subscriptions.subscribe_channel(false, (None, Some(TYPE), None));
+
+        let steps: Vec<Vec<(EncodedTriple, isize, bool)>> = vec![
+            vec![
+                ((PROFESSOR, SUB_CLASS_OF, PERSON), 1, true),
+                ((20, TYPE, PROFESSOR), 1, false),
+            ],
+            // Asserted as well as derived, then only derived again.
+            vec![((20, TYPE, PERSON), 1, false)],
+            vec![((20, TYPE, PERSON), -1, false)],
+            vec![((PROFESSOR, SUB_CLASS_OF, PERSON), -1, true)],
+        ];
+        for updates in steps.iter() {
+            for &(triple, diff, tbox) in updates.iter() {
+                if tbox {
+                    reasoner.update_tbox(triple, diff);
+                } else {
+                    reasoner.update_abox(triple, diff);
+                }
+            }
+            reasoner.advance(worker);
+            subscriptions.wait(worker, reasoner.time());
+        }
+        channel.try_iter().collect::<Vec<_>>()
+    });
+
+    assert_eq!(
+        notifications,
+        vec![
+            Notification {
+                time: 0,
+                added: vec![(20, TYPE, PERSON), (20, TYPE, PROFESSOR)],
+                removed: vec![],
+            },
+            Notification {
+                time: 3,
+                added: vec![],
+                removed: vec![(20, TYPE, PERSON)],
+            },
+        ]
+    );
+}
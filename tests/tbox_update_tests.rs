@@ -0,0 +1,146 @@
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::reasoner::{Profile, Reasoner};
+use std::collections::BTreeSet;
+
+const SUB_CLASS_OF: usize = 0;
+const DOMAIN: usize = 2;
+const RANGE: usize = 3;
+const TYPE: usize = 4;
+const TRANSITIVE: usize = 5;
+const INVERSE_OF: usize = 6;
+
+type Triples = BTreeSet<EncodedTriple>;
+
+/// The materialized TBox and ABox after the initial load and after each TBox
+/// delta, gathered from all workers.
+fn materialize(
+    profile: Profile,
+    tbox: &[EncodedTriple],
+    abox: &[EncodedTriple],
+    deltas: &[Vec<(EncodedTriple, isize)>],
+) -> Vec<(Triples, Triples)> {
+    let (tbox, abox, deltas) = (tbox.to_vec(), abox.to_vec(), deltas.to_vec());
+    let steps = deltas.len() + 1;
+    let shares = timely::execute(timely::Config::process(2), move |worker| {
+        let mut reasoner = Reasoner::new(worker, profile);
+        let mut results = Vec::new();
+        if worker.index() == 0 {
+            tbox.iter().for_each(|triple| reasoner.insert_tbox(*triple));
+            abox.iter().for_each(|triple| reasoner.insert_abox(*triple));
+        }
+        reasoner.advance(worker);
+        results.push((reasoner.tbox(), reasoner.abox()));
+        for delta in deltas.iter() {
+            if worker.index() == 0 {
+                for &(triple, diff) in delta.iter() {
+                    reasoner.update_tbox(triple, diff);
+                }
+            }
+            reasoner.advance(worker);
+            results.push((reasoner.tbox(), reasoner.abox()));
+        }
+        results
+    })
+    .unwrap()
+    .join();
+
+    let mut results = vec![(Triples::new(), Triples::new()); steps];
+    for share in shares {
+        for (result, (tbox, abox)) in results.iter_mut().zip(share.unwrap()) {
+            result.0.extend(tbox);
+            result.1.extend(abox);
+        }
+    }
+    results
+}
+
+fn difference(before: &Triples, after: &Triples) -> Triples {
+    before.difference(after).cloned().collect()
+}
+
+fn set(triples: &[EncodedTriple]) -> Triples {
+    triples.iter().cloned().collect()
+}
+
+#[test]
+fn retracting_sub_class_of_removes_dependent_typings() {
+    // 10 ⊑ 11 ⊑ 12 and 13 ⊑ 11; 31 is a 11 through 13 as well.
+    let tbox = [
+        (10, SUB_CLASS_OF, 11),
+        (11, SUB_CLASS_OF, 12),
+        (13, SUB_CLASS_OF, 11),
+    ];
+    let abox = [
+        (30, TYPE, 10),
+        (31, TYPE, 10),
+        (31, TYPE, 13),
+        (32, TYPE, 11),
+    ];
+    let retract = vec![((10, SUB_CLASS_OF, 11), -1)];
+    let restore = vec![((10, SUB_CLASS_OF, 11), 1)];
+
+    for profile in [Profile::Rdfs, Profile::Rdfspp].iter() {
+        let steps = materialize(*profile, &tbox, &abox, &[retract.clone(), restore.clone()]);
+        let (tbox_before, abox_before) = &steps[0];
+        let (tbox_after, abox_after) = &steps[1];
+
+        assert_eq!(
+            difference(abox_before, abox_after),
+            set(&[(30, TYPE, 11), (30, TYPE, 12)])
+        );
+        assert!(difference(abox_after, abox_before).is_empty());
+        assert_eq!(
+            difference(tbox_before, tbox_after),
+            set(&[(10, SUB_CLASS_OF, 11), (10, SUB_CLASS_OF, 12)])
+        );
+        assert_eq!(&steps[2], &steps[0]);
+    }
+}
+
+#[test]
+fn domain_and_range_can_change() {
+    let abox = [(30, 20, 31)];
+    let deltas = [
+        vec![((20, DOMAIN, 10), 1), ((20, RANGE, 11), 1)],
+        vec![((20, DOMAIN, 10), -1)],
+    ];
+
+    for profile in [Profile::Rdfs, Profile::Rdfspp].iter() {
+        let steps = materialize(*profile, &[], &abox, &deltas);
+        assert_eq!(steps[0].1, set(&abox));
+        assert_eq!(
+            steps[1].1,
+            set(&[(30, 20, 31), (30, TYPE, 10), (31, TYPE, 11)])
+        );
+        assert_eq!(steps[2].1, set(&[(30, 20, 31), (31, TYPE, 11)]));
+    }
+}
+
+#[test]
+fn transitive_and_inverse_axioms_can_change() {
+    let abox = [(30, 20, 31), (31, 20, 32)];
+    let deltas = [
+        vec![((20, TYPE, TRANSITIVE), 1)],
+        vec![((21, INVERSE_OF, 20), 1)],
+        vec![((20, TYPE, TRANSITIVE), -1)],
+    ];
+
+    let steps = materialize(Profile::Rdfspp, &[], &abox, &deltas);
+    assert_eq!(steps[0].1, set(&abox));
+    assert_eq!(steps[1].1, set(&[(30, 20, 31), (31, 20, 32), (30, 20, 32)]));
+    assert_eq!(
+        steps[2].1,
+        set(&[
+            (30, 20, 31),
+            (31, 20, 32),
+            (30, 20, 32),
+            (31, 21, 30),
+            (32, 21, 31),
+            (32, 21, 30),
+        ])
+    );
+    assert_eq!(
+        steps[3].1,
+        set(&[(30, 20, 31), (31, 20, 32), (31, 21, 30), (32, 21, 31)])
+    );
+}
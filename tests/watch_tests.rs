@@ -0,0 +1,206 @@
+use differential_reasoner::materializations::EncodedTriple;
+use differential_reasoner::partition::Schema;
+use differential_reasoner::reasoner::Profile;
+use differential_reasoner::runtime::{ReasonerHandle, Runtime, RuntimeConfig};
+use differential_reasoner::watch::{DeltaDirectory, PROCESSED, SNAPSHOT};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SUB_CLASS_OF: usize = 0;
+const TYPE: usize = 4;
+const PERSON: usize = 10;
+const PROFESSOR: usize = 11;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "differential-reasoner-{}-{}",
+        std::process::id(),
+        name
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn config() -> RuntimeConfig {
+    RuntimeConfig {
+        profile: Profile::Rdfs,
+        workers: 2,
+        history: 0,
+        schema: Schema::default(),
+    }
+}
+
+/// A runtime with `professor ⊑ person` loaded at timestamp 0.
+fn start() -> (Runtime, ReasonerHandle) {
+    let runtime = Runtime::start(config()).unwrap();
+    let reasoner = runtime.handle();
+    let time = reasoner.submit(vec![((PROFESSOR, SUB_CLASS_OF, PERSON), 1)], Vec::new());
+    reasoner.wait(time).unwrap();
+    (runtime, reasoner)
+}
+
+fn people(reasoner: &ReasonerHandle) -> Vec<EncodedTriple> {
+    let time = reasoner.latest().unwrap();
+    reasoner
+        .query(false, (None, Some(TYPE), Some(PERSON)), time)
+        .unwrap()
+}
+
+fn names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort_unstable();
+    names
+}
+
+#[test]
+fn applies_new_delta_files_once() {
+    let dir = temp_dir("watch");
+    fs::write(
+        dir.join("02.ntenc"),
+        format!("-20 {} {}\n", TYPE, PROFESSOR),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("01.ntenc"),
+        format!("+20 {} {}\n+21 {} {}\n", TYPE, PROFESSOR, TYPE, PROFESSOR),
+    )
+    .unwrap();
+    // Still being written.
+    fs::write(dir.join("03.ntenc.tmp"), "+22 4 11\n").unwrap();
+
+    let (_runtime, reasoner) = start();
+    let mut deltas = DeltaDirectory::open(&dir, None).unwrap();
+    assert_eq!(deltas.recover(&reasoner).unwrap(), 0);
+    assert_eq!(
+        deltas.poll(&reasoner).unwrap(),
+        vec![("01.ntenc".to_string(), 1), ("02.ntenc".to_string(), 2)]
+    );
+    assert_eq!(people(&reasoner), vec![(21, TYPE, PERSON)]);
+    assert_eq!(names(&dir), vec!["03.ntenc.tmp", PROCESSED]);
+    assert_eq!(
+        names(&dir.join(PROCESSED)),
+        vec!["01.ntenc", "02.ntenc", "progress"]
+    );
+
+    fs::rename(dir.join("03.ntenc.tmp"), dir.join("03.ntenc")).unwrap();
+    assert_eq!(
+        deltas.poll(&reasoner).unwrap(),
+        vec![("03.ntenc".to_string(), 3)]
+    );
+    assert!(deltas.poll(&reasoner).unwrap().is_empty());
+    assert_eq!(
+        people(&reasoner),
+        vec![(21, TYPE, PERSON), (22, TYPE, PERSON)]
+    );
+}
+
+#[test]
+fn replays_recorded_batches_after_a_restart() {
+    let dir = temp_dir("watch-restart");
+    fs::write(
+        dir.join("01.ntenc"),
+        format!("+20 {} {}\n", TYPE, PROFESSOR),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("02.ntenc"),
+        format!("+21 {} {}\n", TYPE, PROFESSOR),
+    )
+    .unwrap();
+    let expected = vec![(20, TYPE, PERSON), (21, TYPE, PERSON)];
+    {
+        let (runtime, reasoner) = start();
+        let mut deltas = DeltaDirectory::open(&dir, None).unwrap();
+        assert_eq!(deltas.poll(&reasoner).unwrap().len(), 2);
+        assert_eq!(people(&reasoner), expected);
+        runtime.shutdown().unwrap();
+    }
+
+    // The last file was moved, but the run stopped before recording it.
+    let progress = dir.join(PROCESSED).join("progress");
+    fs::write(&progress, "01.ntenc\n").unwrap();
+
+    let (_runtime, reasoner) = start();
+    let mut deltas = DeltaDirectory::open(&dir, None).unwrap();
+    assert!(deltas.snapshot().is_none());
+    assert_eq!(deltas.recover(&reasoner).unwrap(), 2);
+    assert!(deltas.poll(&reasoner).unwrap().is_empty());
+    assert_eq!(reasoner.latest(), Some(2));
+    assert_eq!(people(&reasoner), expected);
+    assert_eq!(names(&dir), vec![PROCESSED]);
+    assert_eq!(
+        fs::read_to_string(&progress).unwrap(),
+        "01.ntenc\n02.ntenc\n"
+    );
+}
+
+#[test]
+fn resumes_from_the_snapshot_after_a_restart() {
+    let dir = temp_dir("watch-snapshot");
+    let delta = |name: &str, individual: usize| {
+        fs::write(
+            dir.join(name),
+            format!("+{} {} {}\n", individual, TYPE, PROFESSOR),
+        )
+        .unwrap()
+    };
+    delta("01.ntenc", 20);
+    delta("02.ntenc", 21);
+    {
+        let (runtime, reasoner) = start();
+        let mut deltas = DeltaDirectory::open(&dir, None).unwrap();
+        assert_eq!(deltas.poll(&reasoner).unwrap().len(), 2);
+        deltas.checkpoint(&reasoner).unwrap();
+        delta("03.ntenc", 22);
+        assert_eq!(deltas.poll(&reasoner).unwrap().len(), 1);
+        runtime.shutdown().unwrap();
+    }
+
+    let mut deltas = DeltaDirectory::open(&dir, None).unwrap();
+    let snapshot = deltas.snapshot().unwrap();
+    assert_eq!(snapshot, dir.join(PROCESSED).join(SNAPSHOT));
+    let runtime = Runtime::restore(config(), &snapshot.display().to_string()).unwrap();
+    let reasoner = runtime.handle();
+    // Only the file applied after the snapshot is replayed.
+    assert_eq!(deltas.recover(&reasoner).unwrap(), 1);
+    assert_eq!(reasoner.latest(), Some(3));
+    assert_eq!(
+        people(&reasoner),
+        vec![(20, TYPE, PERSON), (21, TYPE, PERSON), (22, TYPE, PERSON)]
+    );
+}
+
+#[test]
+fn applies_files_reusing_a_name() {
+    let dir = temp_dir("watch-rotate");
+    let (_runtime, reasoner) = start();
+    let mut deltas = DeltaDirectory::open(&dir, None).unwrap();
+    for individual in [20, 21].iter() {
+        fs::write(
+            dir.join("delta.ntenc"),
+            format!("+{} {} {}\n", individual, TYPE, PROFESSOR),
+        )
+        .unwrap();
+        assert_eq!(deltas.poll(&reasoner).unwrap().len(), 1);
+    }
+    assert_eq!(
+        names(&dir.join(PROCESSED)),
+        vec!["delta.ntenc", "delta.ntenc.1", "progress"]
+    );
+    assert_eq!(
+        people(&reasoner),
+        vec![(20, TYPE, PERSON), (21, TYPE, PERSON)]
+    );
+
+    let (_runtime, reasoner) = start();
+    let mut deltas = DeltaDirectory::open(&dir, None).unwrap();
+    assert_eq!(deltas.recover(&reasoner).unwrap(), 2);
+    assert_eq!(
+        people(&reasoner),
+        vec![(20, TYPE, PERSON), (21, TYPE, PERSON)]
+    );
+}